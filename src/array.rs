@@ -0,0 +1,164 @@
+use crate::math::Transform;
+use crate::shapes::types::WPos;
+use std::f64::consts::PI;
+
+// Most copies one array command can make, more would stall the drawing
+pub const MAX_ARRAY_COPIES: usize = 1000;
+
+// How the copies of a selection are laid out by the array command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArrayPattern {
+    // Columns along x and rows along y, the spacing being the offset between
+    // two neighbouring copies
+    Linear {
+        rows: usize,
+        columns: usize,
+        spacing: WPos,
+    },
+    // The count includes the original. A full turn spreads the copies
+    // evenly, a smaller angle puts the last copy at its end. The angle
+    // follows the canvas convention.
+    Polar {
+        center: WPos,
+        count: usize,
+        angle: f64,
+        rotate_items: bool,
+    },
+}
+impl ArrayPattern {
+    // Past the largest number of copies the pattern is refused, the rows and
+    // columns are multiplied without overflowing
+    pub fn check(&self) -> Result<(), String> {
+        let nb_items = match self {
+            ArrayPattern::Linear { rows, columns, .. } => rows.checked_mul(*columns),
+            ArrayPattern::Polar { count, .. } => Some(*count),
+        };
+        match nb_items {
+            Some(0) => Err("the rows, columns and count must be at least 1".into()),
+            Some(nb_items) if nb_items <= MAX_ARRAY_COPIES + 1 => Ok(()),
+            _ => Err(format!(
+                "at most {} copies can be made at once",
+                MAX_ARRAY_COPIES
+            )),
+        }
+    }
+    // One transformation per copy, the original left out. Not turned, a
+    // copy is only moved for its reference point to follow the circle.
+    pub fn get_transforms(&self, reference: &WPos) -> Vec<Transform> {
+        if self.check().is_err() {
+            return vec![];
+        }
+        match self {
+            ArrayPattern::Linear {
+                rows,
+                columns,
+                spacing,
+            } => (0..*rows)
+                .flat_map(|row| (0..*columns).map(move |column| (row, column)))
+                .skip(1)
+                .map(|(row, column)| {
+                    Transform::translate(column as f64 * spacing.wx, row as f64 * spacing.wy)
+                })
+                .collect(),
+            ArrayPattern::Polar {
+                center,
+                count,
+                angle,
+                rotate_items,
+            } => {
+                if *count < 2 {
+                    return vec![];
+                }
+                let step = if angle.abs() >= 2. * PI - 1e-9 {
+                    angle / *count as f64
+                } else {
+                    angle / (*count - 1) as f64
+                };
+                (1..*count)
+                    .map(|i| {
+                        let turn = Transform::rotate(i as f64 * step).around(center);
+                        if *rotate_items {
+                            turn
+                        } else {
+                            let offset = turn.apply(reference) - *reference;
+                            Transform::translate(offset.wx, offset.wy)
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transforms() {
+        let reference = WPos::new(10., 0.);
+        let grid = ArrayPattern::Linear {
+            rows: 2,
+            columns: 3,
+            spacing: WPos::new(5., 20.),
+        };
+        let positions: Vec<WPos> = grid
+            .get_transforms(&reference)
+            .iter()
+            .map(|transform| transform.apply(&reference))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                WPos::new(15., 0.),
+                WPos::new(20., 0.),
+                WPos::new(10., 20.),
+                WPos::new(15., 20.),
+                WPos::new(20., 20.),
+            ]
+        );
+
+        // A full turn in four, the last copy doesn't fall on the original
+        let circle = ArrayPattern::Polar {
+            center: WPos::zero(),
+            count: 4,
+            angle: 2. * PI,
+            rotate_items: true,
+        };
+        let transforms = circle.get_transforms(&reference);
+        assert_eq!(transforms.len(), 3);
+        assert!(transforms[0].apply(&reference).dist(&WPos::new(0., 10.)) < 1e-9);
+        assert!(transforms[2].apply(&reference).dist(&WPos::new(0., -10.)) < 1e-9);
+        // Turned, a copy of a point beside the reference is turned too
+        let pos = transforms[0].apply(&WPos::new(10., 5.));
+        assert!(pos.dist(&WPos::new(-5., 10.)) < 1e-9);
+
+        // Over a half turn, the last copy is at its end, only moved
+        let half = ArrayPattern::Polar {
+            center: WPos::zero(),
+            count: 3,
+            angle: PI,
+            rotate_items: false,
+        };
+        let transforms = half.get_transforms(&reference);
+        assert!(transforms[1].apply(&reference).dist(&WPos::new(-10., 0.)) < 1e-9);
+        let pos = transforms[0].apply(&WPos::new(10., 5.));
+        assert!(pos.dist(&WPos::new(0., 15.)) < 1e-9);
+    }
+
+    #[test]
+    fn test_check() {
+        let linear = |rows: usize, columns: usize| ArrayPattern::Linear {
+            rows,
+            columns,
+            spacing: WPos::new(5., 5.),
+        };
+        assert!(linear(1, MAX_ARRAY_COPIES + 1).check().is_ok());
+        assert!(linear(2, MAX_ARRAY_COPIES).check().is_err());
+        assert!(linear(0, 3).check().is_err());
+        // Too many to be counted
+        let huge = linear(usize::MAX, 2);
+        assert!(huge.check().is_err());
+        assert!(huge.get_transforms(&WPos::zero()).is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+use super::gcode::flip_sheet_y;
+use super::toolpath::{get_angle, get_arc_sweep, PathSegment, Toolpath};
+use crate::import::{push_shape, ImportReport};
+use crate::shapes::ellipse::Ellipse;
+use crate::shapes::line::Line;
+use crate::shapes::types::{Shape, WPos};
+
+// Extensions of the files opened as G-code programs
+pub const GCODE_EXTENSIONS: [&str; 6] = ["nc", "ngc", "gcode", "gc", "tap", "cnc"];
+
+const MM_PER_INCH: f64 = 25.4;
+
+// Largest difference between the distances of the start and of the end of an
+// arc to its center, in millimeters
+const ARC_RADIUS_TOLERANCE: f64 = 0.05;
+
+// A move of the tool read from a program, it cuts when the torch is on and it
+// is not a rapid
+#[derive(Clone, Debug)]
+pub struct BackplotMove {
+    pub start: WPos,
+    pub segment: PathSegment,
+    pub cut: bool,
+    // Line of the program, from 1
+    pub line: usize,
+}
+impl BackplotMove {
+    pub fn get_polyline(&self, tolerance: f64) -> Vec<WPos> {
+        let mut toolpath = Toolpath::new(&self.start);
+        toolpath.segments.push(self.segment);
+        toolpath.get_polyline(tolerance)
+    }
+}
+
+// Modal state of the controller, the position is kept in millimeters
+struct GcodeState {
+    pos: WPos,
+    motion: Option<u32>,
+    absolute: bool,
+    absolute_arc_centers: bool,
+    mm_per_unit: f64,
+    torch_on: bool,
+}
+
+// The XY moves of a program (G0, G1, G2, G3), the coordinates being absolute
+// or incremental (G90, G91), in inches or millimeters (G20, G21). The torch
+// is turned on by M3 and off by M5, the other words are ignored. The moves
+// are put on the sheet the way the G-code writer takes them off.
+pub fn get_backplot_from_gcode(
+    gcode: &str,
+    working_area: &WPos,
+) -> (Vec<BackplotMove>, ImportReport) {
+    let mut report = ImportReport::new();
+    let mut moves = vec![];
+    let mut state = GcodeState {
+        pos: WPos::zero(),
+        motion: None,
+        absolute: true,
+        absolute_arc_centers: false,
+        mm_per_unit: 1.,
+        torch_on: false,
+    };
+    for (idx, line) in gcode.lines().enumerate() {
+        let nb_line = idx + 1;
+        match get_words(line) {
+            Ok(words) => push_line_moves(&words, nb_line, &mut state, &mut moves, &mut report),
+            Err(message) => report.add_error(nb_line, message),
+        }
+    }
+    // The Y axis flipped, the arcs turn the other way round
+    for bp_move in moves.iter_mut() {
+        bp_move.start = flip_sheet_y(&bp_move.start, working_area);
+        bp_move.segment = match bp_move.segment {
+            PathSegment::Line(end) => PathSegment::Line(flip_sheet_y(&end, working_area)),
+            PathSegment::Arc(center, end, ccw) => PathSegment::Arc(
+                flip_sheet_y(&center, working_area),
+                flip_sheet_y(&end, working_area),
+                !ccw,
+            ),
+        };
+    }
+    report.nb_shapes = moves.len();
+    (moves, report)
+}
+
+// The cut moves as lines and circle arcs
+pub fn get_shapes_from_backplot(moves: &[BackplotMove]) -> Vec<Box<dyn Shape>> {
+    let mut shapes: Vec<Box<dyn Shape>> = vec![];
+    for bp_move in moves.iter().filter(|bp_move| bp_move.cut) {
+        match &bp_move.segment {
+            PathSegment::Line(end) => push_shape(Line::new(&bp_move.start, end), &mut shapes),
+            PathSegment::Arc(center, end, ccw) => {
+                let radius = center.dist(&bp_move.start);
+                let start_angle = get_angle(center, &bp_move.start);
+                let end_angle = start_angle + get_arc_sweep(center, &bp_move.start, end, *ccw);
+                // The arcs of the ellipses go counterclockwise
+                let (start_angle, end_angle) = if *ccw {
+                    (start_angle, end_angle)
+                } else {
+                    (end_angle, start_angle)
+                };
+                push_shape(
+                    Ellipse::from_arc(
+                        center,
+                        &WPos::new(radius, radius),
+                        0.,
+                        start_angle,
+                        end_angle,
+                    ),
+                    &mut shapes,
+                );
+            }
+        }
+    }
+    shapes
+}
+
+// Letters and their values, without the comments in parentheses or after a
+// semicolon
+fn get_words(line: &str) -> Result<Vec<(char, f64)>, String> {
+    let mut words = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => {
+                if !chars.by_ref().any(|c| c == ')') {
+                    return Err("Unclosed comment".into());
+                }
+            }
+            ';' => break,
+            '%' => (),
+            c if c.is_whitespace() => (),
+            c if c.is_ascii_alphabetic() => {
+                let mut value = String::new();
+                while let Some(c) = chars.peek() {
+                    if c.is_ascii_digit() || *c == '.' || *c == '-' || *c == '+' {
+                        value.push(*c);
+                        chars.next();
+                    } else if c.is_whitespace() && value.is_empty() {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let letter = c.to_ascii_uppercase();
+                match value.parse::<f64>() {
+                    Ok(value) => words.push((letter, value)),
+                    Err(_) => return Err(format!("Invalid value for {}: \"{}\"", letter, value)),
+                }
+            }
+            _ => return Err(format!("Unexpected character '{}'", c)),
+        }
+    }
+    Ok(words)
+}
+
+fn push_line_moves(
+    words: &[(char, f64)],
+    line: usize,
+    state: &mut GcodeState,
+    moves: &mut Vec<BackplotMove>,
+    report: &mut ImportReport,
+) {
+    let get_value = |letter: char| {
+        words
+            .iter()
+            .find(|(word_letter, _)| *word_letter == letter)
+            .map(|(_, value)| *value)
+    };
+    // The codes with one decimal are kept as tenths
+    let codes = |letter: char| {
+        words
+            .iter()
+            .filter(move |(word_letter, _)| *word_letter == letter)
+            .map(|(_, value)| (value * 10.).round() as u32)
+    };
+
+    for code in codes('G') {
+        match code {
+            0 | 10 | 20 | 30 => state.motion = Some(code / 10),
+            200 => state.mm_per_unit = MM_PER_INCH,
+            210 => state.mm_per_unit = 1.,
+            900 => state.absolute = true,
+            910 => state.absolute = false,
+            901 => state.absolute_arc_centers = true,
+            911 => state.absolute_arc_centers = false,
+            // Dwell, XY plane, offsets, path control and feed modes
+            40 | 170 | 400 | 490 | 540..=590 | 610 | 611 | 640 | 800 | 940 => (),
+            180 | 190 => report.add_warning(
+                line,
+                "Arcs outside of the XY plane are not supported".into(),
+            ),
+            410 | 420 => report.add_warning(line, "The cutter compensation is not applied".into()),
+            _ => report.add_warning(line, format!("G{} is not supported", code as f64 / 10.)),
+        }
+    }
+    // The torch is turned on before the moves of the line and off after them
+    let torch_off = codes('M').any(|code| code == 50);
+    if codes('M').any(|code| code == 30) {
+        state.torch_on = true;
+    }
+
+    let (x, y) = (get_value('X'), get_value('Y'));
+    let has_arc_words = ['I', 'J', 'R']
+        .iter()
+        .any(|letter| get_value(*letter).is_some());
+    if x.is_some() || y.is_some() || has_arc_words {
+        let start = state.pos;
+        let get_coordinate = |value: Option<f64>, current: f64| match value {
+            Some(value) if state.absolute => value * state.mm_per_unit,
+            Some(value) => current + value * state.mm_per_unit,
+            None => current,
+        };
+        let end = WPos::new(get_coordinate(x, start.wx), get_coordinate(y, start.wy));
+        match state.motion {
+            None => report.add_error(line, "Move without a motion mode".into()),
+            Some(motion @ (0 | 1)) => {
+                if start != end {
+                    moves.push(BackplotMove {
+                        start,
+                        segment: PathSegment::Line(end),
+                        cut: motion == 1 && state.torch_on,
+                        line,
+                    });
+                }
+                state.pos = end;
+            }
+            Some(motion) => {
+                let ccw = motion == 3;
+                let center = match (get_value('I'), get_value('J'), get_value('R')) {
+                    (None, None, Some(radius)) => {
+                        get_arc_center_from_radius(&start, &end, radius * state.mm_per_unit, ccw)
+                    }
+                    (None, None, None) => Err("Arc without center nor radius".into()),
+                    (i, j, _) => {
+                        let offset = WPos::new(
+                            i.unwrap_or(0.) * state.mm_per_unit,
+                            j.unwrap_or(0.) * state.mm_per_unit,
+                        );
+                        if state.absolute_arc_centers {
+                            Ok(offset)
+                        } else {
+                            Ok(start + offset)
+                        }
+                    }
+                };
+                match center {
+                    Ok(center) => {
+                        if (center.dist(&start) - center.dist(&end)).abs() > ARC_RADIUS_TOLERANCE {
+                            report.add_warning(
+                                line,
+                                "The end of the arc is not on its circle".into(),
+                            );
+                        }
+                        push_arc_moves(&start, &center, &end, ccw, state.torch_on, line, moves);
+                        state.pos = end;
+                    }
+                    Err(message) => report.add_error(line, message),
+                }
+            }
+        }
+    }
+
+    if torch_off {
+        state.torch_on = false;
+    }
+}
+
+// An arc ending where it starts is a whole circle, it is drawn in two halves
+fn push_arc_moves(
+    start: &WPos,
+    center: &WPos,
+    end: &WPos,
+    ccw: bool,
+    cut: bool,
+    line: usize,
+    moves: &mut Vec<BackplotMove>,
+) {
+    let mut ends = vec![*end];
+    if start == end {
+        ends.insert(0, *center * 2. - *start);
+    }
+    let mut start = *start;
+    for end in ends {
+        moves.push(BackplotMove {
+            start,
+            segment: PathSegment::Arc(*center, end, ccw),
+            cut,
+            line,
+        });
+        start = end;
+    }
+}
+
+// A negative radius gives the arc greater than a half turn
+fn get_arc_center_from_radius(
+    start: &WPos,
+    end: &WPos,
+    radius: f64,
+    ccw: bool,
+) -> Result<WPos, String> {
+    let chord = *end - *start;
+    let length = start.dist(end);
+    if length == 0. {
+        return Err("A whole circle can't be given by its radius".into());
+    }
+    if length > 2. * radius.abs() + ARC_RADIUS_TOLERANCE {
+        return Err("The radius of the arc is too small".into());
+    }
+    let height = (radius * radius - length * length / 4.).max(0.).sqrt();
+    // Counterclockwise, the center of the small arc is on the left of the
+    // chord
+    let left = WPos::new(-chord.wy, chord.wx) / length;
+    let side = if ccw == (radius > 0.) { 1. } else { -1. };
+    Ok((*start + *end) / 2. + left * height * side)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cam::gcode::get_gcode_from_pool;
+    use crate::cam::parameters::{CamParameters, LeadType};
+    use crate::cam::postprocessor::{Dialect, PostProcessor};
+    use crate::datapool::ShapesPool;
+    use crate::shapes::types::ShapeId;
+
+    fn get_end(bp_move: &BackplotMove) -> WPos {
+        match bp_move.segment {
+            PathSegment::Line(end) | PathSegment::Arc(_, end, _) => end,
+        }
+    }
+
+    #[test]
+    fn test_backplot_modes() {
+        let gcode = "\
+%
+N10 G21 G90 (millimeters, absolute)
+G0 X10 Y10
+M3 S1000 ; torch on
+G1 X20
+G91 Y5
+G90 G2 X30 Y15 I5 J0
+X40 Y15 R5
+G20 G91 G3 X1 Y0 I0.5 J0
+M5
+G21 G90 G0 X0 Y0
+G1 X10
+G3 X10 Y0 I5 J0
+";
+        // Taken back off the sheet, the positions are the ones of the program
+        let working_area = WPos::new(200., 100.);
+        let (moves, report) = get_backplot_from_gcode(gcode, &working_area);
+        assert!(report.is_clean(), "{}", report.get_summary());
+        let ends: Vec<WPos> = moves
+            .iter()
+            .map(|bp_move| flip_sheet_y(&get_end(bp_move), &working_area))
+            .collect();
+        let expected = [
+            (10., 10.),
+            (20., 10.),
+            (20., 15.),
+            (30., 15.),
+            (40., 15.),
+            (65.4, 15.),
+            (0., 0.),
+            (10., 0.),
+            (20., 0.),
+            (10., 0.),
+        ];
+        assert_eq!(ends.len(), expected.len());
+        for (end, (wx, wy)) in ends.iter().zip(expected.iter()) {
+            assert!(end.dist(&WPos::new(*wx, *wy)) < 1e-9, "{:?}", end);
+        }
+        let cuts: Vec<bool> = moves.iter().map(|bp_move| bp_move.cut).collect();
+        assert_eq!(
+            cuts,
+            vec![false, true, true, true, true, true, false, false, false, false]
+        );
+        assert_eq!(moves[1].line, 5);
+        // The arc given by its radius turns clockwise around (35, 15), so
+        // counterclockwise on the sheet
+        if let PathSegment::Arc(center, _, ccw) = moves[4].segment {
+            assert!(flip_sheet_y(&center, &working_area).dist(&WPos::new(35., 15.)) < 1e-9 && ccw);
+        }
+        // In inches the center is half an inch after the start
+        if let PathSegment::Arc(center, _, ccw) = moves[5].segment {
+            assert!(
+                flip_sheet_y(&center, &working_area).dist(&WPos::new(52.7, 15.)) < 1e-9 && !ccw
+            );
+        }
+    }
+
+    #[test]
+    fn test_backplot_report() {
+        let gcode = "X10\nG0 X10 Y10 G42\nG2 X20 Y10\nG1 X(1\nG1 X1Q\nG3 X20 Y10 I20 J0";
+        let (moves, report) = get_backplot_from_gcode(gcode, &WPos::new(200., 100.));
+        assert_eq!(moves.len(), 2);
+        let errors: Vec<usize> = report.errors.iter().map(|error| error.line).collect();
+        assert_eq!(errors, vec![1, 3, 4, 5]);
+        let warnings: Vec<usize> = report.warnings.iter().map(|warning| warning.line).collect();
+        assert_eq!(warnings, vec![2, 6]);
+    }
+
+    #[test]
+    fn test_backplot_of_exported_gcode() {
+        let mut shapes_pool = ShapesPool::new();
+        let circle = Ellipse::from_arc(&WPos::new(50., 50.), &WPos::new(10., 10.), 0., 0., 0.);
+        shapes_pool.insert(ShapeId(0), Box::new(circle.unwrap()));
+        // Off the middle of the sheet, a flipped drawing would not match
+        let line = Line::new(&WPos::new(100., 20.), &WPos::new(150., 20.)).unwrap();
+        shapes_pool.insert(ShapeId(1), Box::new(line));
+
+        let mut cam_parameters = CamParameters {
+            kerf_width: 0.,
+            ..CamParameters::default()
+        };
+        cam_parameters.lead_in.lead_type = LeadType::None;
+        cam_parameters.lead_out.lead_type = LeadType::None;
+        for dialect in [
+            Dialect::LinuxCnc,
+            Dialect::Grbl,
+            Dialect::Mach3,
+            Dialect::FluidNc,
+        ] {
+            let mut post_processor = PostProcessor::from_dialect(&dialect).unwrap();
+            let gcode = get_gcode_from_pool(
+                &shapes_pool,
+                &WPos::new(200., 100.),
+                &cam_parameters,
+                &mut post_processor,
+            );
+            let (moves, report) = get_backplot_from_gcode(&gcode, &WPos::new(200., 100.));
+            assert!(report.errors.is_empty(), "{}", report.get_summary());
+            assert!(moves.iter().any(|bp_move| !bp_move.cut));
+
+            // Without kerf nor leads only the shapes are cut
+            let shapes = get_shapes_from_backplot(&moves);
+            let originals: Vec<&Box<dyn Shape>> = shapes_pool.values().collect();
+            for (from, to) in [
+                (shapes.iter().collect::<Vec<_>>(), originals.clone()),
+                (originals.clone(), shapes.iter().collect()),
+            ] {
+                for shape in from.iter() {
+                    for i in 0..=10 {
+                        let pos = shape.get_pos_from_ratio(i as f64 / 10.);
+                        let dist = to
+                            .iter()
+                            .map(|shape| shape.dist(&pos))
+                            .fold(f64::MAX, f64::min);
+                        assert!(dist < 1e-2, "{} {:?}", dialect.get_name(), pos);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_backplot_is_y_up_from_the_bottom_left_corner() {
+        // Up from near the bottom left corner of the machine, then clockwise
+        // over the top of a half circle
+        let gcode = "\
+G21 G90
+G0 X10 Y10
+M3
+G1 X10 Y30
+G2 X30 Y30 I10 J0
+M5
+";
+        let (moves, report) = get_backplot_from_gcode(gcode, &WPos::new(200., 100.));
+        assert!(report.is_clean(), "{}", report.get_summary());
+        assert!(moves[0].start.dist(&WPos::new(0., 100.)) < 1e-9);
+        assert!(get_end(&moves[0]).dist(&WPos::new(10., 90.)) < 1e-9);
+        assert!(get_end(&moves[1]).dist(&WPos::new(10., 70.)) < 1e-9);
+
+        // On the sheet the top of the half circle is above its center
+        let shapes = get_shapes_from_backplot(&moves);
+        assert_eq!(shapes.len(), 2);
+        assert!(shapes[1].dist(&WPos::new(20., 60.)) < 1e-6);
+        assert!(shapes[1].dist(&WPos::new(20., 80.)) > 1.);
+    }
+}
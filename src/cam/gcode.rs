@@ -0,0 +1,123 @@
+use super::ordering::get_ordered_toolpaths;
+use super::parameters::CamParameters;
+use super::postprocessor::PostProcessor;
+use super::toolpath::{PathSegment, Toolpath};
+use crate::datapool::ShapesPool;
+use crate::shapes::types::WPos;
+
+pub fn get_gcode_from_pool(
+    shapes_pool: &ShapesPool,
+    working_area: &WPos,
+    cam_parameters: &CamParameters,
+    post_processor: &mut PostProcessor,
+) -> String {
+    let toolpaths = get_ordered_toolpaths(shapes_pool, &get_home(working_area), cam_parameters);
+    get_gcode(&toolpaths, working_area, cam_parameters, post_processor)
+}
+
+// The world Y axis goes down from the top left corner of the sheet, the one
+// of the machine goes up from its bottom left corner. The flip is its own
+// inverse, it maps the machine positions back to the sheet too.
+pub fn flip_sheet_y(pos: &WPos, working_area: &WPos) -> WPos {
+    WPos::new(pos.wx, working_area.wy - pos.wy)
+}
+
+// The machine origin on the sheet, where the programs start and end
+pub fn get_home(working_area: &WPos) -> WPos {
+    flip_sheet_y(&WPos::zero(), working_area)
+}
+
+// Each toolpath is cut on its own: rapid to its start, torch on,
+// cut moves, torch off. The post processor gives the controller dialect.
+pub fn get_gcode(
+    toolpaths: &[Toolpath],
+    working_area: &WPos,
+    cam_parameters: &CamParameters,
+    post_processor: &mut PostProcessor,
+) -> String {
+    post_processor.write_header(cam_parameters);
+
+    for toolpath in toolpaths.iter() {
+        let start = flip_sheet_y(&toolpath.start, working_area);
+        post_processor.write_rapid(&start);
+        post_processor.write_torch_on(cam_parameters);
+        let mut current_pos = start;
+        for segment in toolpath.segments.iter() {
+            match segment {
+                PathSegment::Line(end) => {
+                    let end = flip_sheet_y(end, working_area);
+                    post_processor.write_line(&end, cam_parameters);
+                    current_pos = end;
+                }
+                PathSegment::Arc(center, end, ccw) => {
+                    // The Y axis flipped, the arcs turn the other way round
+                    let center = flip_sheet_y(center, working_area);
+                    let end = flip_sheet_y(end, working_area);
+                    post_processor.write_arc(&current_pos, &center, &end, !ccw, cam_parameters);
+                    current_pos = end;
+                }
+            }
+        }
+        post_processor.write_torch_off(cam_parameters);
+    }
+
+    post_processor.write_footer(cam_parameters);
+    post_processor.get_gcode().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cam::postprocessor::Dialect;
+    use crate::shapes::ellipse::Ellipse;
+    use crate::shapes::line::Line;
+    use crate::shapes::types::ShapeId;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_gcode_for_a_line() {
+        let mut shapes_pool = ShapesPool::new();
+        let line = Line::new(&WPos::new(10., 20.), &WPos::new(30.5, 20.)).unwrap();
+        shapes_pool.insert(ShapeId(0), Box::new(line));
+
+        let mut post_processor = PostProcessor::from_dialect(&Dialect::Grbl).unwrap();
+        let gcode = get_gcode_from_pool(
+            &shapes_pool,
+            &WPos::new(100., 40.),
+            &CamParameters::default(),
+            &mut post_processor,
+        );
+        let lines: Vec<&str> = gcode.lines().collect();
+        let rapid = lines.iter().position(|l| *l == "G0 X10 Y20").unwrap();
+        assert_eq!(lines[rapid + 1], "M3 S1000");
+        assert_eq!(lines[rapid + 2], "G4 P0.5");
+        assert_eq!(lines[rapid + 3], "G1 X30.5 Y20 F1500");
+        assert_eq!(lines[rapid + 4], "M5");
+    }
+
+    #[test]
+    fn test_gcode_is_y_up_from_the_bottom_left_corner() {
+        // A quarter of circle going down on the sheet from the right, then a
+        // line going left at its end: an upside down mirrored L
+        let mut shapes_pool = ShapesPool::new();
+        let arc = Ellipse::new(&WPos::new(50., 50.), &WPos::new(60., 60.), 0., PI / 2., 1.);
+        shapes_pool.insert(ShapeId(0), Box::new(arc));
+        let line = Line::new(&WPos::new(50., 60.), &WPos::new(20., 60.)).unwrap();
+        shapes_pool.insert(ShapeId(1), Box::new(line));
+
+        let mut post_processor = PostProcessor::from_dialect(&Dialect::Grbl).unwrap();
+        let gcode = get_gcode_from_pool(
+            &shapes_pool,
+            &WPos::new(200., 100.),
+            &CamParameters::default(),
+            &mut post_processor,
+        );
+        let lines: Vec<&str> = gcode.lines().collect();
+        // On the machine the arc goes down from (60, 50) to (50, 40) around
+        // (50, 50), clockwise, then the line goes on under its center
+        let rapid = lines.iter().position(|l| *l == "G0 X60 Y50").unwrap();
+        assert_eq!(lines[rapid + 3], "G2 X50 Y40 I-10 J0 F1500");
+        assert_eq!(lines[rapid + 4], "G1 X20 Y40");
+        assert!(!lines.iter().any(|l| l.starts_with("G3")));
+    }
+}
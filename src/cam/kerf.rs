@@ -0,0 +1,514 @@
+use super::parameters::CamParameters;
+use super::toolpath::{
+    get_angle, get_arc_sweep, get_arc_tangent, get_toolpaths_from_shape, PathSegment, Toolpath,
+};
+use crate::datapool::{Contour, ShapesPool, Winding};
+use crate::intersection::{get_curves_intersections, Curve};
+use crate::math::{cross, perp};
+use crate::shapes::types::{ConstructionType, Shape, WPos};
+
+// How a contour is cut, the scrap side is where the leads go
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ContourKind {
+    Open,
+    Outer,
+    Hole,
+}
+
+#[derive(Clone, Debug)]
+pub struct CutContour {
+    pub toolpath: Toolpath,
+    pub kind: ContourKind,
+    // Side of the scrap when following the toolpath
+    pub scrap_on_left: bool,
+}
+
+// Kerf compensation: closed contours are offset by half the kerf width,
+// outwards for outer profiles and inwards for holes, so that the part keeps
+// its drawn dimensions. Open contours are cut on the line.
+// The drawn shapes are only read, the offset exists in the toolpaths only.
+pub fn get_cut_contours(
+    shapes_pool: &ShapesPool,
+    cam_parameters: &CamParameters,
+) -> Vec<CutContour> {
+    let drawn_contours = shapes_pool.get_contours(cam_parameters.tolerance);
+    get_cut_contours_from_contours(shapes_pool, &drawn_contours, cam_parameters)
+}
+
+// Same, the contours of the drawing being already chained
+pub fn get_cut_contours_from_contours(
+    shapes_pool: &ShapesPool,
+    drawn_contours: &[Contour],
+    cam_parameters: &CamParameters,
+) -> Vec<CutContour> {
+    let tolerance = cam_parameters.tolerance;
+    let half_kerf = cam_parameters.kerf_width / 2.;
+
+    let contour_toolpaths: Vec<Toolpath> = drawn_contours
+        .iter()
+        .map(|contour| get_contour_toolpath(shapes_pool, contour, tolerance))
+        .collect();
+
+    // Nesting depth of each closed contour: an odd depth means a hole
+    let polygons: Vec<Option<Vec<WPos>>> = drawn_contours
+        .iter()
+        .zip(contour_toolpaths.iter())
+        .map(|(contour, toolpath)| {
+            if contour.closed {
+                Some(toolpath.get_polyline(tolerance))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let mut contours = vec![];
+    for (idx, (contour, toolpath)) in drawn_contours
+        .iter()
+        .zip(contour_toolpaths.iter())
+        .enumerate()
+    {
+        let polygon = match polygons[idx].as_ref() {
+            Some(polygon) => polygon,
+            None => {
+                contours.push(CutContour {
+                    toolpath: toolpath.clone(),
+                    kind: ContourKind::Open,
+                    scrap_on_left: false,
+                });
+                continue;
+            }
+        };
+        let depth = polygons
+            .iter()
+            .enumerate()
+            .filter(|(other_idx, other)| {
+                *other_idx != idx
+                    && other
+                        .as_ref()
+                        .map_or(false, |other| is_point_in_polygon(&polygon[0], other))
+            })
+            .count();
+        let kind = if depth % 2 == 1 {
+            ContourKind::Hole
+        } else {
+            ContourKind::Outer
+        };
+        // The scrap is outside of the outer profiles and inside of the holes,
+        // i.e. on the right of a counter-clockwise outer profile
+        let ccw = contour.winding == Some(Winding::CounterClockwise);
+        let scrap_on_left = (kind == ContourKind::Hole) == ccw;
+        let toolpath = if half_kerf > 0. {
+            // Offsets are given along the left normal of the path
+            let offset = if scrap_on_left { half_kerf } else { -half_kerf };
+            get_offset_contour_toolpath(shapes_pool, contour, offset, tolerance)
+        } else {
+            toolpath.clone()
+        };
+        contours.push(CutContour {
+            toolpath,
+            kind,
+            scrap_on_left,
+        });
+    }
+    contours
+}
+
+// The contour followed on the line, as a single toolpath
+pub fn get_contour_toolpath(
+    shapes_pool: &ShapesPool,
+    contour: &Contour,
+    tolerance: f64,
+) -> Toolpath {
+    let mut contour_toolpath: Option<Toolpath> = None;
+    for (sh_id, reversed) in contour.links.iter() {
+        for toolpath in get_toolpaths_from_shape(shapes_pool[sh_id].as_ref(), tolerance) {
+            let toolpath = if *reversed {
+                toolpath.get_reversed()
+            } else {
+                toolpath
+            };
+            if let Some(contour_toolpath) = contour_toolpath.as_mut() {
+                if contour_toolpath.get_end().dist(&toolpath.start) > 0. {
+                    contour_toolpath.push_line(&toolpath.start);
+                }
+                contour_toolpath.segments.extend(toolpath.segments);
+            } else {
+                contour_toolpath = Some(toolpath);
+            }
+        }
+    }
+    contour_toolpath.unwrap_or(Toolpath::new(&WPos::zero()))
+}
+
+pub fn is_point_in_polygon(pos: &WPos, polygon: &[WPos]) -> bool {
+    let n = polygon.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (p1, p2) = (polygon[i], polygon[(i + 1) % n]);
+        if (p1.wy > pos.wy) != (p2.wy > pos.wy) {
+            let wx = p1.wx + (pos.wy - p1.wy) * (p2.wx - p1.wx) / (p2.wy - p1.wy);
+            if pos.wx < wx {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+// Offset of a part of the contour, with the path tangents and the original
+// vertices at its ends needed to join it to its neighbours
+struct OffsetPiece {
+    path: Toolpath,
+    vertex_end: WPos,
+    tangent_start: WPos,
+    tangent_end: WPos,
+}
+
+fn get_offset_contour_toolpath(
+    shapes_pool: &ShapesPool,
+    contour: &Contour,
+    offset: f64,
+    tolerance: f64,
+) -> Toolpath {
+    let mut pieces: Vec<OffsetPiece> = vec![];
+    for (sh_id, reversed) in contour.links.iter() {
+        let shape = shapes_pool[sh_id].as_ref();
+        if is_curve(shape, tolerance) {
+            if let Some(piece) = get_offset_curve(shape, *reversed, offset, tolerance) {
+                pieces.push(piece);
+            }
+        } else {
+            for toolpath in get_toolpaths_from_shape(shape, tolerance) {
+                let toolpath = if *reversed {
+                    toolpath.get_reversed()
+                } else {
+                    toolpath
+                };
+                for idx in 0..toolpath.segments.len() {
+                    let start = toolpath.get_segment_start(idx);
+                    if let Some(piece) = get_offset_segment(&start, &toolpath.segments[idx], offset)
+                    {
+                        pieces.push(piece);
+                    }
+                }
+            }
+        }
+    }
+    if pieces.is_empty() {
+        return get_contour_toolpath(shapes_pool, contour, tolerance);
+    }
+
+    // Join each piece to the next one, the last one to the first one
+    let nb_pieces = pieces.len();
+    for idx in 0..nb_pieces {
+        let next_idx = (idx + 1) % nb_pieces;
+        if next_idx == 0 && !contour.closed {
+            break;
+        }
+        join_pieces(&mut pieces, idx, next_idx, offset, tolerance);
+    }
+
+    let mut toolpath = Toolpath::new(&pieces[0].path.start);
+    for piece in pieces.iter() {
+        if toolpath.get_end().dist(&piece.path.start) > tolerance {
+            toolpath.push_line(&piece.path.start);
+        }
+        toolpath
+            .segments
+            .extend(piece.path.segments.iter().cloned());
+    }
+    toolpath
+}
+
+// Beziers and elliptical arcs have no exact offset, they are approximated
+fn is_curve(shape: &dyn Shape, tolerance: f64) -> bool {
+    shape.get_construction().iter().any(|cst| match cst {
+        ConstructionType::QuadBezier(_, _) | ConstructionType::CubicBezier(_, _, _) => true,
+        ConstructionType::Ellipse(_, radius, _, _, _, _) => {
+            (radius.wx - radius.wy).abs() >= tolerance
+        }
+        _ => false,
+    })
+}
+
+fn get_offset_segment(start: &WPos, segment: &PathSegment, offset: f64) -> Option<OffsetPiece> {
+    match segment {
+        PathSegment::Line(end) => {
+            let dir = *end - *start;
+            let length = dir.norm();
+            if length == 0. {
+                return None;
+            }
+            let tangent = dir / length;
+            let normal = perp(&tangent) * offset;
+            let mut path = Toolpath::new(&(*start + normal));
+            path.push_line(&(*end + normal));
+            Some(OffsetPiece {
+                path,
+                vertex_end: *end,
+                tangent_start: tangent,
+                tangent_end: tangent,
+            })
+        }
+        PathSegment::Arc(center, end, ccw) => {
+            // The left normal points to the center of a counter-clockwise arc
+            let radius = center.dist(start);
+            let new_radius = if *ccw {
+                radius - offset
+            } else {
+                radius + offset
+            };
+            if new_radius <= 0. || radius == 0. {
+                return None;
+            }
+            let scale = new_radius / radius;
+            let new_start = *center + (*start - *center) * scale;
+            let new_end = *center + (*end - *center) * scale;
+            let mut path = Toolpath::new(&new_start);
+            path.segments.push(PathSegment::Arc(*center, new_end, *ccw));
+            Some(OffsetPiece {
+                path,
+                vertex_end: *end,
+                tangent_start: get_arc_tangent(center, start, *ccw),
+                tangent_end: get_arc_tangent(center, end, *ccw),
+            })
+        }
+    }
+}
+
+// Offset of a curve sampled with get_pos_from_ratio: the ratio interval is
+// split until the middle of each chord is closer than the tolerance to the
+// curve, then each sample is moved along the curve normal
+fn get_offset_curve(
+    shape: &dyn Shape,
+    reversed: bool,
+    offset: f64,
+    tolerance: f64,
+) -> Option<OffsetPiece> {
+    let mut ratios = vec![0.];
+    let mut stack = vec![(0.75, 1.), (0.5, 0.75), (0.25, 0.5), (0., 0.25)];
+    while let Some((r1, r2)) = stack.pop() {
+        let pos1 = shape.get_pos_from_ratio(r1);
+        let pos2 = shape.get_pos_from_ratio(r2);
+        let r_mid = (r1 + r2) / 2.;
+        let pos_mid = shape.get_pos_from_ratio(r_mid);
+        if pos_mid.dist(&pos1.lerp(&pos2, 0.5)) > tolerance && r2 - r1 > 1e-4 {
+            stack.push((r_mid, r2));
+            stack.push((r1, r_mid));
+        } else {
+            ratios.push(r2);
+        }
+    }
+    if reversed {
+        ratios = ratios.iter().rev().map(|r| 1. - r).collect();
+    }
+    let get_pos = |r: f64| {
+        if reversed {
+            shape.get_pos_from_ratio(1. - r)
+        } else {
+            shape.get_pos_from_ratio(r)
+        }
+    };
+    let get_tangent = |r: f64| {
+        let h = 1e-4;
+        let (r1, r2) = ((r - h).max(0.), (r + h).min(1.));
+        let dir = get_pos(r2) - get_pos(r1);
+        let norm = dir.norm();
+        if norm > 0. {
+            dir / norm
+        } else {
+            WPos::zero()
+        }
+    };
+
+    let mut points = vec![];
+    for r in ratios.iter() {
+        let tangent = get_tangent(*r);
+        if tangent == WPos::zero() {
+            continue;
+        }
+        points.push(get_pos(*r) + perp(&tangent) * offset);
+    }
+    if points.len() < 2 {
+        return None;
+    }
+    let mut path = Toolpath::new(&points[0]);
+    for point in points.iter().skip(1) {
+        path.push_line(point);
+    }
+    Some(OffsetPiece {
+        path,
+        vertex_end: get_pos(1.),
+        tangent_start: get_tangent(0.),
+        tangent_end: get_tangent(1.),
+    })
+}
+
+// Outer corners are rounded around the original vertex, inner corners are
+// trimmed at the intersection of the offset pieces
+fn join_pieces(
+    pieces: &mut [OffsetPiece],
+    idx: usize,
+    next_idx: usize,
+    offset: f64,
+    tolerance: f64,
+) {
+    let end = pieces[idx].path.get_end();
+    let start = pieces[next_idx].path.start;
+    if end.dist(&start) <= tolerance {
+        return;
+    }
+    let vertex = pieces[idx].vertex_end;
+    let turn = cross(&pieces[idx].tangent_end, &pieces[next_idx].tangent_start);
+    let ccw = if turn.abs() < 1e-9 {
+        offset < 0.
+    } else {
+        turn > 0.
+    };
+
+    if turn.abs() < 1e-9 || turn * offset < 0. {
+        let radius = offset.abs();
+        let start_angle = get_angle(&vertex, &end);
+        let sweep = get_arc_sweep(&vertex, &end, &start, ccw);
+        pieces[idx]
+            .path
+            .push_arc(&vertex, radius, start_angle, sweep);
+        return;
+    }
+
+    let last_idx = pieces[idx].path.segments.len() - 1;
+    let seg1_start = pieces[idx].path.get_segment_start(last_idx);
+    let seg1 = pieces[idx].path.segments[last_idx];
+    let seg2_start = pieces[next_idx].path.start;
+    let seg2 = pieces[next_idx].path.segments[0];
+    let curve1 = get_segment_curve(&seg1_start, &seg1);
+    let curve2 = get_segment_curve(&seg2_start, &seg2);
+    let intersection = get_curves_intersections(&curve1, &curve2, tolerance)
+        .into_iter()
+        .map(|(t1, _)| curve1.get_pos(t1))
+        .min_by(|p1, p2| p1.dist(&vertex).total_cmp(&p2.dist(&vertex)));
+    if let Some(pos) = intersection {
+        match &mut pieces[idx].path.segments[last_idx] {
+            PathSegment::Line(end) | PathSegment::Arc(_, end, _) => *end = pos,
+        }
+        pieces[next_idx].path.start = pos;
+    } else {
+        pieces[idx].path.push_line(&start);
+    }
+}
+
+// The segment as a curve of the intersection engine
+fn get_segment_curve(start: &WPos, segment: &PathSegment) -> Curve {
+    match segment {
+        PathSegment::Line(end) => Curve::Line(*start, *end),
+        PathSegment::Arc(center, end, ccw) => {
+            let radius = center.dist(start);
+            Curve::Arc(
+                *center,
+                WPos::new(radius, radius),
+                0.,
+                get_angle(center, start),
+                get_arc_sweep(center, start, end, *ccw),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::ellipse::Ellipse;
+    use crate::shapes::rectangle::Rectangle;
+    use crate::shapes::types::ShapeId;
+    use std::f64::consts::PI;
+
+    fn get_bounds(toolpath: &Toolpath, tolerance: f64) -> [WPos; 2] {
+        let polygon = toolpath.get_polyline(tolerance);
+        let mut bounds = [polygon[0], polygon[0]];
+        for pos in polygon.iter() {
+            bounds[0] = WPos::new(bounds[0].wx.min(pos.wx), bounds[0].wy.min(pos.wy));
+            bounds[1] = WPos::new(bounds[1].wx.max(pos.wx), bounds[1].wy.max(pos.wy));
+        }
+        bounds
+    }
+
+    #[test]
+    fn test_kerf_outer_profile_and_hole() {
+        let mut shapes_pool = ShapesPool::new();
+        let plate = Rectangle::new(&WPos::new(0., 0.), 100., 50.).unwrap();
+        let hole = Ellipse::new(&WPos::new(50., 25.), &WPos::new(60., 35.), 0., 2. * PI, 1.);
+        shapes_pool.insert(ShapeId(0), Box::new(plate));
+        shapes_pool.insert(ShapeId(1), Box::new(hole));
+
+        let cam_parameters = CamParameters {
+            kerf_width: 2.,
+            ..Default::default()
+        };
+        let contours = get_cut_contours(&shapes_pool, &cam_parameters);
+        assert_eq!(contours.len(), 2);
+        assert_eq!(contours[0].kind, ContourKind::Outer);
+        assert_eq!(contours[1].kind, ContourKind::Hole);
+
+        // The outer profile grows by half the kerf
+        let bounds = get_bounds(&contours[0].toolpath, 0.01);
+        assert!(bounds[0].dist(&WPos::new(-1., -1.)) < 0.01);
+        assert!(bounds[1].dist(&WPos::new(101., 51.)) < 0.01);
+        assert!(contours[0].toolpath.is_closed(0.01));
+
+        // The hole shrinks by half the kerf
+        let bounds = get_bounds(&contours[1].toolpath, 0.01);
+        assert!(bounds[0].dist(&WPos::new(41., 16.)) < 0.05);
+        assert!(bounds[1].dist(&WPos::new(59., 34.)) < 0.05);
+    }
+
+    #[test]
+    fn test_kerf_of_chained_lines() {
+        use crate::shapes::line::Line;
+        let mut shapes_pool = ShapesPool::new();
+        let pts = [
+            WPos::new(0., 0.),
+            WPos::new(10., 0.),
+            WPos::new(10., 10.),
+            WPos::new(0., 10.),
+        ];
+        // Out of order and one reversed
+        let lines = [(2, 3), (0, 1), (2, 1), (3, 0)];
+        for (idx, (i, j)) in lines.iter().enumerate() {
+            let line = Line::new(&pts[*i], &pts[*j]).unwrap();
+            shapes_pool.insert(ShapeId(idx), Box::new(line));
+        }
+
+        let cam_parameters = CamParameters {
+            kerf_width: 2.,
+            ..Default::default()
+        };
+        let contours = get_cut_contours(&shapes_pool, &cam_parameters);
+        let bounds = get_bounds(&contours[0].toolpath, 0.01);
+        assert!(bounds[0].dist(&WPos::new(-1., -1.)) < 0.01);
+        assert!(bounds[1].dist(&WPos::new(11., 11.)) < 0.01);
+    }
+
+    #[test]
+    fn test_kerf_trims_inner_corners() {
+        let mut shapes_pool = ShapesPool::new();
+        let plate = Rectangle::new(&WPos::new(0., 0.), 100., 50.).unwrap();
+        let hole = Rectangle::new(&WPos::new(20., 10.), 30., 20.).unwrap();
+        shapes_pool.insert(ShapeId(0), Box::new(plate));
+        shapes_pool.insert(ShapeId(1), Box::new(hole));
+
+        let cam_parameters = CamParameters {
+            kerf_width: 2.,
+            ..Default::default()
+        };
+        let contours = get_cut_contours(&shapes_pool, &cam_parameters);
+        assert_eq!(contours[1].kind, ContourKind::Hole);
+        // The sides of the hole meet at the sharp corners of the shrunk hole
+        let toolpath = &contours[1].toolpath;
+        assert_eq!(toolpath.segments.len(), 4);
+        for corner in [(21., 11.), (49., 11.), (49., 29.), (21., 29.)] {
+            let corner = WPos::new(corner.0, corner.1);
+            assert!((0..4).any(|idx| toolpath.get_segment_start(idx).dist(&corner) < 1e-6));
+        }
+    }
+}
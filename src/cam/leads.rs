@@ -0,0 +1,149 @@
+use super::kerf::{ContourKind, CutContour};
+use super::parameters::{CamParameters, Lead, LeadType};
+use super::toolpath::{get_angle, get_arc_tangent, Toolpath};
+use crate::shapes::types::WPos;
+
+// Lead-in, contour, lead-out as a single toolpath. The torch is fired on
+// the lead-in start so that the pierce crater ends in the scrap.
+pub fn get_leaded_toolpath(contour: &CutContour, cam_parameters: &CamParameters) -> Toolpath {
+    let lead_in = get_lead_in(contour, &cam_parameters.lead_in);
+    let lead_out = get_lead_out(contour, &cam_parameters.lead_out);
+
+    let mut toolpath = lead_in.unwrap_or(Toolpath::new(&contour.toolpath.start));
+    toolpath
+        .segments
+        .extend(contour.toolpath.segments.iter().cloned());
+    if let Some(lead_out) = lead_out {
+        toolpath.segments.extend(lead_out.segments);
+    }
+    toolpath
+}
+
+// Leads are only added to closed contours, the scrap side of an open one is
+// unknown
+pub fn get_lead_in(contour: &CutContour, lead: &Lead) -> Option<Toolpath> {
+    if contour.kind == ContourKind::Open {
+        return None;
+    }
+    let tangent = contour.toolpath.get_start_tangent()?;
+    get_lead_to(
+        &contour.toolpath.start,
+        &tangent,
+        contour.scrap_on_left,
+        lead,
+    )
+}
+
+// A lead-out is a lead-in of the reversed contour, followed backward
+pub fn get_lead_out(contour: &CutContour, lead: &Lead) -> Option<Toolpath> {
+    if contour.kind == ContourKind::Open {
+        return None;
+    }
+    let tangent = contour.toolpath.get_end_tangent()? * -1.;
+    get_lead_to(
+        &contour.toolpath.get_end(),
+        &tangent,
+        !contour.scrap_on_left,
+        lead,
+    )
+    .map(|lead_out| lead_out.get_reversed())
+}
+
+// Lead reaching pos with the given tangent, coming from the scrap side
+fn get_lead_to(pos: &WPos, tangent: &WPos, scrap_on_left: bool, lead: &Lead) -> Option<Toolpath> {
+    if lead.lead_type == LeadType::None || lead.length <= 0. {
+        return None;
+    }
+    let angle = lead.angle.clamp(1., 180.).to_radians();
+    let normal = if scrap_on_left {
+        WPos::new(-tangent.wy, tangent.wx)
+    } else {
+        WPos::new(tangent.wy, -tangent.wx)
+    };
+
+    match lead.lead_type {
+        LeadType::Straight => {
+            let direction = *tangent * angle.cos() - normal * angle.sin();
+            let mut toolpath = Toolpath::new(&(*pos - direction * lead.length));
+            toolpath.push_line(pos);
+            Some(toolpath)
+        }
+        LeadType::Arc | LeadType::LineArc => {
+            // Arc tangent to the contour, its center is on the scrap side
+            let radius = lead.length;
+            let center = *pos + normal * radius;
+            let sweep = if scrap_on_left { angle } else { -angle };
+            let start_angle = get_angle(&center, pos) - sweep;
+            let arc_start = center + WPos::new(start_angle.cos(), start_angle.sin()) * radius;
+            let mut toolpath = if lead.lead_type == LeadType::LineArc {
+                let arc_tangent = get_arc_tangent(&center, &arc_start, scrap_on_left);
+                let mut toolpath = Toolpath::new(&(arc_start - arc_tangent * lead.length));
+                toolpath.push_line(&arc_start);
+                toolpath
+            } else {
+                Toolpath::new(&arc_start)
+            };
+            toolpath.push_arc(&center, radius, start_angle, sweep);
+            Some(toolpath)
+        }
+        LeadType::None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_square_contour(kind: ContourKind) -> CutContour {
+        // Counter-clockwise square
+        let mut toolpath = Toolpath::new(&WPos::new(0., 0.));
+        toolpath.push_line(&WPos::new(10., 0.));
+        toolpath.push_line(&WPos::new(10., 10.));
+        toolpath.push_line(&WPos::new(0., 10.));
+        toolpath.push_line(&WPos::new(0., 0.));
+        CutContour {
+            toolpath,
+            kind,
+            scrap_on_left: kind == ContourKind::Hole,
+        }
+    }
+
+    #[test]
+    fn test_leads_are_on_the_scrap_side() {
+        let lead = Lead {
+            lead_type: LeadType::Straight,
+            length: 3.,
+            angle: 90.,
+        };
+        let outer = get_square_contour(ContourKind::Outer);
+        let lead_in = get_lead_in(&outer, &lead).unwrap();
+        assert!(lead_in.start.dist(&WPos::new(0., -3.)) < 1e-9);
+        let lead_out = get_lead_out(&outer, &lead).unwrap();
+        assert!(lead_out.get_end().dist(&WPos::new(-3., 0.)) < 1e-9);
+
+        let hole = get_square_contour(ContourKind::Hole);
+        let lead_in = get_lead_in(&hole, &lead).unwrap();
+        assert!(lead_in.start.dist(&WPos::new(0., 3.)) < 1e-9);
+    }
+
+    #[test]
+    fn test_arc_lead_in_is_tangent() {
+        let lead = Lead {
+            lead_type: LeadType::LineArc,
+            length: 2.,
+            angle: 90.,
+        };
+        let outer = get_square_contour(ContourKind::Outer);
+        let toolpath = get_leaded_toolpath(
+            &outer,
+            &CamParameters {
+                lead_in: lead,
+                ..Default::default()
+            },
+        );
+        // Quarter arc of radius 2 centered below the start, after a line
+        assert!(toolpath.start.dist(&WPos::new(-2., -4.)) < 1e-9);
+        assert!(toolpath.get_segment_start(1).dist(&WPos::new(-2., -2.)) < 1e-9);
+        assert!(toolpath.get_segment_start(2).dist(&WPos::new(0., 0.)) < 1e-9);
+    }
+}
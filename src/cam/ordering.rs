@@ -0,0 +1,235 @@
+use super::kerf::{get_cut_contours, is_point_in_polygon, ContourKind, CutContour};
+use super::leads::get_leaded_toolpath;
+use super::parameters::CamParameters;
+use super::toolpath::Toolpath;
+use crate::datapool::ShapesPool;
+use crate::shapes::types::WPos;
+
+// A part is an outer profile with the holes directly inside it, an open
+// contour makes a part on its own
+struct Part {
+    holes: Vec<usize>,
+    last: usize,
+    depth: usize,
+}
+
+// Leaded toolpaths of the drawing, in cutting order from the home position
+pub fn get_ordered_toolpaths(
+    shapes_pool: &ShapesPool,
+    home: &WPos,
+    cam_parameters: &CamParameters,
+) -> Vec<Toolpath> {
+    let contours = get_cut_contours(shapes_pool, cam_parameters);
+    let toolpaths: Vec<Toolpath> = contours
+        .iter()
+        .map(|contour| get_leaded_toolpath(contour, cam_parameters))
+        .collect();
+    get_cut_order(&contours, &toolpaths, home, cam_parameters.tolerance)
+        .iter()
+        .map(|idx| toolpaths[*idx].clone())
+        .collect()
+}
+
+// Cutting order of the contours, as indices, given the toolpaths that will
+// be followed for each of them (leads included):
+// - the holes of a part are cut before its outer profile, so that the part
+//   does not drop or shift before they are done,
+// - parts lying in the holes of other parts are cut first, deepest first,
+// - the parts of a same level are visited to minimize the rapid moves, by a
+//   nearest neighbor tour from the home position improved with 2-opt.
+pub fn get_cut_order(
+    contours: &[CutContour],
+    toolpaths: &[Toolpath],
+    home: &WPos,
+    tolerance: f64,
+) -> Vec<usize> {
+    let polygons: Vec<Option<Vec<WPos>>> = contours
+        .iter()
+        .map(|contour| {
+            if contour.kind == ContourKind::Open {
+                None
+            } else {
+                Some(contour.toolpath.get_polyline(tolerance))
+            }
+        })
+        .collect();
+    let is_inside = |idx: usize, other_idx: usize| {
+        idx != other_idx
+            && polygons[other_idx].as_ref().map_or(false, |polygon| {
+                is_point_in_polygon(&contours[idx].toolpath.start, polygon)
+            })
+    };
+    let depths: Vec<usize> = (0..contours.len())
+        .map(|idx| {
+            (0..contours.len())
+                .filter(|other_idx| is_inside(idx, *other_idx))
+                .count()
+        })
+        .collect();
+
+    let mut parts: Vec<Part> = vec![];
+    for (idx, contour) in contours.iter().enumerate() {
+        if contour.kind == ContourKind::Hole {
+            continue;
+        }
+        let holes = (0..contours.len())
+            .filter(|hole_idx| {
+                contours[*hole_idx].kind == ContourKind::Hole
+                    && depths[*hole_idx] == depths[idx] + 1
+                    && is_inside(*hole_idx, idx)
+            })
+            .collect();
+        parts.push(Part {
+            holes,
+            last: idx,
+            depth: depths[idx],
+        });
+    }
+
+    let mut order = vec![];
+    let mut current_pos = *home;
+    let max_depth = parts.iter().map(|part| part.depth).max().unwrap_or(0);
+    for depth in (0..=max_depth).rev() {
+        let level: Vec<&Part> = parts.iter().filter(|part| part.depth == depth).collect();
+        let entries: Vec<WPos> = level
+            .iter()
+            .map(|part| toolpaths[part.last].start)
+            .collect();
+        for part_idx in get_tour(&current_pos, &entries) {
+            let part = level[part_idx];
+            // Holes of the part by nearest neighbor, then its outer profile
+            let mut holes = part.holes.clone();
+            while !holes.is_empty() {
+                let nearest =
+                    get_nearest(&current_pos, holes.iter().map(|idx| toolpaths[*idx].start));
+                let idx = holes.remove(nearest);
+                order.push(idx);
+                current_pos = toolpaths[idx].get_end();
+            }
+            order.push(part.last);
+            current_pos = toolpaths[part.last].get_end();
+        }
+    }
+    order
+}
+
+fn get_nearest(pos: &WPos, candidates: impl Iterator<Item = WPos>) -> usize {
+    candidates
+        .enumerate()
+        .min_by(|(_, p1), (_, p2)| pos.dist(p1).total_cmp(&pos.dist(p2)))
+        .map_or(0, |(idx, _)| idx)
+}
+
+// Open tour from the start position visiting all points
+fn get_tour(start: &WPos, points: &[WPos]) -> Vec<usize> {
+    // Nearest neighbor
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut tour = vec![];
+    let mut pos = *start;
+    while !remaining.is_empty() {
+        let nearest = get_nearest(&pos, remaining.iter().map(|idx| points[*idx]));
+        let idx = remaining.remove(nearest);
+        tour.push(idx);
+        pos = points[idx];
+    }
+
+    // 2-opt: reverse the parts of the tour that shorten it, until none does
+    let get_pos = |tour: &[usize], i: usize| if i == 0 { *start } else { points[tour[i - 1]] };
+    let n = tour.len();
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 1..n {
+            for j in i + 1..=n {
+                // Edges (i-1, i) and (j, j+1) of the path start, p_1...p_n
+                let (a, b) = (get_pos(&tour, i - 1), get_pos(&tour, i));
+                let c = get_pos(&tour, j);
+                let delta = if j < n {
+                    let d = get_pos(&tour, j + 1);
+                    a.dist(&c) + b.dist(&d) - a.dist(&b) - c.dist(&d)
+                } else {
+                    a.dist(&c) - a.dist(&b)
+                };
+                if delta < -1e-9 {
+                    tour[i - 1..j].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+    tour
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cam::gcode::get_home;
+    use crate::shapes::ellipse::Ellipse;
+    use crate::shapes::rectangle::Rectangle;
+    use crate::shapes::types::ShapeId;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_holes_are_cut_before_their_part() {
+        let mut shapes_pool = ShapesPool::new();
+        let plate = Rectangle::new(&WPos::new(0., 0.), 100., 50.).unwrap();
+        let hole1 = Ellipse::new(&WPos::new(20., 25.), &WPos::new(25., 30.), 0., 2. * PI, 1.);
+        let hole2 = Ellipse::new(&WPos::new(80., 25.), &WPos::new(85., 30.), 0., 2. * PI, 1.);
+        shapes_pool.insert(ShapeId(0), Box::new(plate));
+        shapes_pool.insert(ShapeId(1), Box::new(hole1));
+        shapes_pool.insert(ShapeId(2), Box::new(hole2));
+
+        let cam_parameters = CamParameters::default();
+        let contours = get_cut_contours(&shapes_pool, &cam_parameters);
+        let toolpaths: Vec<Toolpath> = contours
+            .iter()
+            .map(|contour| get_leaded_toolpath(contour, &cam_parameters))
+            .collect();
+        let order = get_cut_order(
+            &contours,
+            &toolpaths,
+            &WPos::zero(),
+            cam_parameters.tolerance,
+        );
+        assert_eq!(order, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_first_cut_is_nearest_home() {
+        // The home is at the bottom left corner of the sheet
+        let mut shapes_pool = ShapesPool::new();
+        let top = Ellipse::new(&WPos::new(20., 20.), &WPos::new(25., 25.), 0., 2. * PI, 1.);
+        let bottom = Ellipse::new(&WPos::new(20., 80.), &WPos::new(25., 85.), 0., 2. * PI, 1.);
+        shapes_pool.insert(ShapeId(0), Box::new(top));
+        shapes_pool.insert(ShapeId(1), Box::new(bottom));
+
+        let home = get_home(&WPos::new(200., 100.));
+        let toolpaths = get_ordered_toolpaths(&shapes_pool, &home, &CamParameters::default());
+        assert_eq!(toolpaths.len(), 2);
+        assert!(toolpaths[0].start.wy > 50.);
+        assert!(toolpaths[1].start.wy < 50.);
+    }
+
+    #[test]
+    fn test_tour_is_nearest_neighbor_then_2opt() {
+        let points = [WPos::new(30., 0.), WPos::new(10., 0.), WPos::new(20., 0.)];
+        assert_eq!(get_tour(&WPos::zero(), &points), vec![1, 2, 0]);
+
+        // Nearest neighbor goes right first and has to come back
+        let points = [
+            WPos::new(1., 0.),
+            WPos::new(-1.5, 0.),
+            WPos::new(-2.5, 0.),
+            WPos::new(3.5, 0.),
+        ];
+        let tour = get_tour(&WPos::zero(), &points);
+        let mut length = 0.;
+        let mut pos = WPos::zero();
+        for idx in tour.iter() {
+            length += pos.dist(&points[*idx]);
+            pos = points[*idx];
+        }
+        assert_eq!(tour.len(), 4);
+        assert!((length - 8.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,79 @@
+use super::postprocessor::Dialect;
+
+// Cutting parameters used to turn the drawing into machine code.
+// Dimensions are in millimeters, like the working area.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CamParameters {
+    // Cutting speed in mm/min
+    pub feed_rate: f64,
+    // Time given to the arc to pierce the sheet, in seconds
+    pub pierce_delay: f64,
+    // Maximum distance between a curve and the lines that replace it
+    pub tolerance: f64,
+    // Width of the material removed by the arc, the closed contours are
+    // offset by half of it
+    pub kerf_width: f64,
+    // Moves added before and after each closed contour, on the scrap side
+    pub lead_in: Lead,
+    pub lead_out: Lead,
+    // Controller for which the G-code is written
+    pub dialect: Dialect,
+}
+impl Default for CamParameters {
+    fn default() -> Self {
+        CamParameters {
+            feed_rate: 1500.,
+            pierce_delay: 0.5,
+            tolerance: 0.05,
+            kerf_width: 1.5,
+            lead_in: Lead {
+                lead_type: LeadType::Arc,
+                length: 5.,
+                angle: 90.,
+            },
+            lead_out: Lead {
+                lead_type: LeadType::Straight,
+                length: 2.,
+                angle: 90.,
+            },
+            dialect: Dialect::LinuxCnc,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LeadType {
+    None,
+    Straight,
+    Arc,
+    LineArc,
+}
+impl LeadType {
+    pub fn from_name(name: &str) -> Option<LeadType> {
+        match name {
+            "none" => Some(LeadType::None),
+            "straight" => Some(LeadType::Straight),
+            "arc" => Some(LeadType::Arc),
+            "line_arc" => Some(LeadType::LineArc),
+            _ => None,
+        }
+    }
+    pub fn get_name(&self) -> &str {
+        match self {
+            LeadType::None => "none",
+            LeadType::Straight => "straight",
+            LeadType::Arc => "arc",
+            LeadType::LineArc => "line_arc",
+        }
+    }
+}
+
+// The length is the one of the straight part and the radius of the arc.
+// The angle, in degrees, is the one between a straight lead and the contour,
+// or the sweep of an arc lead.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Lead {
+    pub lead_type: LeadType,
+    pub length: f64,
+    pub angle: f64,
+}
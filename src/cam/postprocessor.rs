@@ -0,0 +1,406 @@
+use super::parameters::CamParameters;
+use crate::shapes::types::WPos;
+use std::fmt::Write;
+
+// Controllers supported out of the box, a custom dialect is defined by the
+// text of its template (see the built-in templates below for the syntax)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dialect {
+    LinuxCnc,
+    Grbl,
+    Mach3,
+    FluidNc,
+    Custom(String),
+}
+impl Dialect {
+    pub fn from_name(name: &str, custom_template: &str) -> Option<Dialect> {
+        match name {
+            "linuxcnc" => Some(Dialect::LinuxCnc),
+            "grbl" => Some(Dialect::Grbl),
+            "mach3" => Some(Dialect::Mach3),
+            "fluidnc" => Some(Dialect::FluidNc),
+            "custom" => Some(Dialect::Custom(custom_template.into())),
+            _ => None,
+        }
+    }
+    pub fn get_name(&self) -> &str {
+        match self {
+            Dialect::LinuxCnc => "linuxcnc",
+            Dialect::Grbl => "grbl",
+            Dialect::Mach3 => "mach3",
+            Dialect::FluidNc => "fluidnc",
+            Dialect::Custom(_) => "custom",
+        }
+    }
+    pub fn get_template(&self) -> &str {
+        match self {
+            Dialect::LinuxCnc => LINUXCNC_TEMPLATE,
+            Dialect::Grbl => GRBL_TEMPLATE,
+            Dialect::Mach3 => MACH3_TEMPLATE,
+            Dialect::FluidNc => FLUIDNC_TEMPLATE,
+            Dialect::Custom(template) => template,
+        }
+    }
+}
+
+// A template is made of [sections]. The text sections (header, torch_on,
+// torch_off, footer) are copied as is, after replacement of the {feed_rate},
+// {pierce_delay} (seconds) and {pierce_delay_ms} placeholders.
+// The [options] section holds "key = value" lines:
+// - arc_format: ij (center offsets) or r (radius)
+// - line_numbers: step between N words, 0 to disable them
+// - decimals: number of decimals of the coordinates
+// - extension: file extension of the programs
+// Lines starting with ';' are comments of the template itself.
+pub const LINUXCNC_TEMPLATE: &str = "\
+[name]
+LinuxCNC
+[options]
+arc_format = ij
+line_numbers = 0
+decimals = 4
+extension = ngc
+[header]
+%
+(plasmaCADCAM - LinuxCNC)
+G21 (millimeters)
+G90 (absolute distances)
+G91.1 (incremental arc centers)
+G17 G40 G49
+G64 P0.05
+M5
+[torch_on]
+M3 S1
+G4 P{pierce_delay}
+[torch_off]
+M5
+[footer]
+G0 X0 Y0
+M2
+%
+";
+
+pub const GRBL_TEMPLATE: &str = "\
+[name]
+GRBL
+[options]
+arc_format = ij
+line_numbers = 0
+decimals = 3
+extension = nc
+[header]
+(plasmaCADCAM - GRBL)
+G21
+G90
+G17
+M5
+[torch_on]
+M3 S1000
+G4 P{pierce_delay}
+[torch_off]
+M5
+[footer]
+G0 X0 Y0
+M30
+";
+
+pub const MACH3_TEMPLATE: &str = "\
+[name]
+Mach3
+[options]
+arc_format = ij
+line_numbers = 10
+decimals = 4
+extension = tap
+[header]
+(plasmaCADCAM - Mach3)
+G21
+G90
+G91.1
+G17 G40
+M5
+[torch_on]
+M3
+G4 P{pierce_delay_ms}
+[torch_off]
+M5
+[footer]
+G0 X0 Y0
+M30
+";
+
+pub const FLUIDNC_TEMPLATE: &str = "\
+[name]
+FluidNC
+[options]
+arc_format = r
+line_numbers = 0
+decimals = 3
+extension = gcode
+[header]
+(plasmaCADCAM - FluidNC)
+G21
+G90
+G17
+M5
+[torch_on]
+M3 S1000
+G4 P{pierce_delay}
+[torch_off]
+M5
+[footer]
+G0 X0 Y0
+M30
+";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ArcFormat {
+    CenterOffsets,
+    Radius,
+}
+
+// Turns the cutting events driven by the G-code writer into the text expected
+// by a given controller
+#[derive(Debug, Clone)]
+pub struct PostProcessor {
+    pub name: String,
+    pub extension: String,
+    arc_format: ArcFormat,
+    line_numbers_step: usize,
+    decimals: usize,
+    header: Vec<String>,
+    torch_on: Vec<String>,
+    torch_off: Vec<String>,
+    footer: Vec<String>,
+    // Output state
+    gcode: String,
+    line_number: usize,
+    feed_rate_set: bool,
+}
+impl PostProcessor {
+    pub fn from_dialect(dialect: &Dialect) -> Result<PostProcessor, String> {
+        PostProcessor::from_template(dialect.get_template())
+    }
+    pub fn from_template(template: &str) -> Result<PostProcessor, String> {
+        let mut post_processor = PostProcessor {
+            name: "Custom".into(),
+            extension: "nc".into(),
+            arc_format: ArcFormat::CenterOffsets,
+            line_numbers_step: 0,
+            decimals: 3,
+            header: vec![],
+            torch_on: vec![],
+            torch_off: vec![],
+            footer: vec![],
+            gcode: String::new(),
+            line_number: 0,
+            feed_rate_set: false,
+        };
+        let mut section = String::new();
+        for (idx, line) in template.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].trim().to_lowercase();
+                continue;
+            }
+            match section.as_str() {
+                "name" => post_processor.name = line.into(),
+                "options" => post_processor.set_option(line, idx + 1)?,
+                "header" => post_processor.header.push(line.into()),
+                "torch_on" => post_processor.torch_on.push(line.into()),
+                "torch_off" => post_processor.torch_off.push(line.into()),
+                "footer" => post_processor.footer.push(line.into()),
+                "" => return Err(format!("line {}: text outside of a section", idx + 1)),
+                _ => return Err(format!("line {}: unknown section [{}]", idx + 1, section)),
+            }
+        }
+        Ok(post_processor)
+    }
+    fn set_option(&mut self, line: &str, line_idx: usize) -> Result<(), String> {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or(format!("line {}: option must be 'key = value'", line_idx))?;
+        let (key, value) = (key.trim(), value.trim());
+        let bad_value = || format!("line {}: bad value '{}' for {}", line_idx, value, key);
+        match key {
+            "arc_format" => {
+                self.arc_format = match value.to_lowercase().as_str() {
+                    "ij" => ArcFormat::CenterOffsets,
+                    "r" => ArcFormat::Radius,
+                    _ => return Err(bad_value()),
+                }
+            }
+            "line_numbers" => self.line_numbers_step = value.parse().map_err(|_| bad_value())?,
+            "decimals" => self.decimals = value.parse().map_err(|_| bad_value())?,
+            "extension" => self.extension = value.into(),
+            _ => return Err(format!("line {}: unknown option {}", line_idx, key)),
+        }
+        Ok(())
+    }
+
+    pub fn write_header(&mut self, cam_parameters: &CamParameters) {
+        self.gcode.clear();
+        self.line_number = 0;
+        self.feed_rate_set = false;
+        let header = self.header.clone();
+        self.write_template_lines(&header, cam_parameters);
+    }
+    pub fn write_footer(&mut self, cam_parameters: &CamParameters) {
+        let footer = self.footer.clone();
+        self.write_template_lines(&footer, cam_parameters);
+    }
+    pub fn write_torch_on(&mut self, cam_parameters: &CamParameters) {
+        let torch_on = self.torch_on.clone();
+        self.write_template_lines(&torch_on, cam_parameters);
+    }
+    pub fn write_torch_off(&mut self, cam_parameters: &CamParameters) {
+        let torch_off = self.torch_off.clone();
+        self.write_template_lines(&torch_off, cam_parameters);
+    }
+    pub fn write_rapid(&mut self, end: &WPos) {
+        let block = format!("G0 {}", self.fmt_xy(end));
+        self.write_block(&block);
+    }
+    pub fn write_line(&mut self, end: &WPos, cam_parameters: &CamParameters) {
+        let block = format!(
+            "G1 {}{}",
+            self.fmt_xy(end),
+            self.fmt_feed_rate(cam_parameters)
+        );
+        self.write_block(&block);
+    }
+    pub fn write_arc(
+        &mut self,
+        start: &WPos,
+        center: &WPos,
+        end: &WPos,
+        ccw: bool,
+        cam_parameters: &CamParameters,
+    ) {
+        let arc = match self.arc_format {
+            ArcFormat::CenterOffsets => {
+                let offset = *center - *start;
+                format!("I{} J{}", self.fmt_num(offset.wx), self.fmt_num(offset.wy))
+            }
+            ArcFormat::Radius => {
+                // A negative radius asks for the arc greater than a half turn
+                let radius = center.dist(start);
+                let chord = *end - *start;
+                let to_center = *center - *start;
+                let cross = chord.wx * to_center.wy - chord.wy * to_center.wx;
+                let large_arc = (cross < 0.) == ccw;
+                format!(
+                    "R{}",
+                    self.fmt_num(if large_arc { -radius } else { radius })
+                )
+            }
+        };
+        let block = format!(
+            "{} {} {}{}",
+            if ccw { "G3" } else { "G2" },
+            self.fmt_xy(end),
+            arc,
+            self.fmt_feed_rate(cam_parameters)
+        );
+        self.write_block(&block);
+    }
+    pub fn get_gcode(&self) -> &str {
+        &self.gcode
+    }
+
+    fn write_template_lines(&mut self, lines: &[String], cam_parameters: &CamParameters) {
+        for line in lines.iter() {
+            let block = line
+                .replace("{feed_rate}", &self.fmt_num(cam_parameters.feed_rate))
+                .replace(
+                    "{pierce_delay_ms}",
+                    &self.fmt_num(cam_parameters.pierce_delay * 1000.),
+                )
+                .replace("{pierce_delay}", &self.fmt_num(cam_parameters.pierce_delay));
+            self.write_block(&block);
+        }
+    }
+    fn write_block(&mut self, block: &str) {
+        // Neither the program delimiter nor comments are numbered
+        if self.line_numbers_step > 0 && block != "%" && !block.starts_with('(') {
+            self.line_number += self.line_numbers_step;
+            let _ = writeln!(self.gcode, "N{} {}", self.line_number, block);
+        } else {
+            let _ = writeln!(self.gcode, "{}", block);
+        }
+    }
+    // The feed rate is modal, it is given with the first cutting move only
+    fn fmt_feed_rate(&mut self, cam_parameters: &CamParameters) -> String {
+        if self.feed_rate_set {
+            String::new()
+        } else {
+            self.feed_rate_set = true;
+            format!(" F{}", self.fmt_num(cam_parameters.feed_rate))
+        }
+    }
+    fn fmt_xy(&self, pos: &WPos) -> String {
+        format!("X{} Y{}", self.fmt_num(pos.wx), self.fmt_num(pos.wy))
+    }
+    // Trailing zeros are removed
+    fn fmt_num(&self, value: f64) -> String {
+        let s = format!("{:.*}", self.decimals, value);
+        let s = if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            &s
+        };
+        if s == "-0" {
+            "0".into()
+        } else {
+            s.into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_dialects_parse() {
+        for dialect in [
+            Dialect::LinuxCnc,
+            Dialect::Grbl,
+            Dialect::Mach3,
+            Dialect::FluidNc,
+        ] {
+            assert!(PostProcessor::from_dialect(&dialect).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_custom_template() {
+        let template = "[name]\nMy table\n[options]\narc_format = r\nline_numbers = 5\n\
+                        [torch_on]\nM3\nG4 P{pierce_delay}\n";
+        let mut post_processor = PostProcessor::from_template(template).unwrap();
+        let cam_parameters = CamParameters::default();
+        post_processor.write_header(&cam_parameters);
+        post_processor.write_torch_on(&cam_parameters);
+        post_processor.write_arc(
+            &WPos::new(10., 0.),
+            &WPos::new(0., 0.),
+            &WPos::new(0., 10.),
+            true,
+            &cam_parameters,
+        );
+        assert_eq!(post_processor.name, "My table");
+        assert_eq!(
+            post_processor.get_gcode(),
+            "N5 M3\nN10 G4 P0.5\nN15 G3 X0 Y10 R10 F1500\n"
+        );
+    }
+
+    #[test]
+    fn test_bad_template() {
+        assert!(PostProcessor::from_template("[options]\narc_format = xy\n").is_err());
+        assert!(PostProcessor::from_template("G21\n").is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use crate::datapool::ShapesPool;
+use crate::math::*;
+use crate::shapes::types::{ConstructionType, Shape, ShapeId, WPos};
+use std::f64::consts::PI;
+
+// Machining primitives: everything the cutting head can follow is either a
+// straight move or a circular arc, any other curve is flattened into lines
+#[derive(Copy, Clone, Debug)]
+pub enum PathSegment {
+    Line(WPos),
+    // center, end, counter-clockwise
+    Arc(WPos, WPos, bool),
+}
+
+#[derive(Clone, Debug)]
+pub struct Toolpath {
+    pub start: WPos,
+    pub segments: Vec<PathSegment>,
+}
+impl Toolpath {
+    pub fn new(start: &WPos) -> Toolpath {
+        Toolpath {
+            start: *start,
+            segments: vec![],
+        }
+    }
+    pub fn get_end(&self) -> WPos {
+        match self.segments.last() {
+            Some(PathSegment::Line(end)) | Some(PathSegment::Arc(_, end, _)) => *end,
+            None => self.start,
+        }
+    }
+    pub fn is_closed(&self, tolerance: f64) -> bool {
+        !self.segments.is_empty() && self.start.dist(&self.get_end()) <= tolerance
+    }
+    pub fn get_segment_start(&self, idx: usize) -> WPos {
+        if idx == 0 {
+            self.start
+        } else {
+            get_segment_end(&self.segments[idx - 1])
+        }
+    }
+    pub fn get_reversed(&self) -> Toolpath {
+        let mut reversed = Toolpath::new(&self.get_end());
+        for (idx, segment) in self.segments.iter().enumerate().rev() {
+            let start = self.get_segment_start(idx);
+            reversed.segments.push(match segment {
+                PathSegment::Line(_) => PathSegment::Line(start),
+                PathSegment::Arc(center, _, ccw) => PathSegment::Arc(*center, start, !ccw),
+            });
+        }
+        reversed
+    }
+    // Unit tangents at both ends, following the path direction
+    pub fn get_start_tangent(&self) -> Option<WPos> {
+        self.segments
+            .first()
+            .and_then(|segment| get_segment_tangent(&self.start, segment, true))
+    }
+    pub fn get_end_tangent(&self) -> Option<WPos> {
+        let idx = self.segments.len().checked_sub(1)?;
+        get_segment_tangent(&self.get_segment_start(idx), &self.segments[idx], false)
+    }
+    // Toolpath with arcs replaced by lines, used for area and inclusion
+    // tests and to display it
+    pub fn get_polyline(&self, tolerance: f64) -> Vec<WPos> {
+        let mut polyline = vec![self.start];
+        for (idx, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PathSegment::Line(end) => polyline.push(*end),
+                PathSegment::Arc(center, end, ccw) => {
+                    let start = self.get_segment_start(idx);
+                    let radius = center.dist(&start);
+                    let start_angle = get_angle(center, &start);
+                    let sweep = get_arc_sweep(center, &start, end, *ccw);
+                    let d_angle = if tolerance < radius {
+                        2. * (1. - tolerance / radius).acos()
+                    } else {
+                        PI / 2.
+                    };
+                    let nb_steps = ((sweep.abs() / d_angle).ceil() as usize).max(1);
+                    for i in 1..=nb_steps {
+                        let angle = start_angle + sweep * i as f64 / nb_steps as f64;
+                        polyline.push(*center + WPos::new(angle.cos(), angle.sin()) * radius);
+                    }
+                }
+            }
+        }
+        polyline
+    }
+    pub fn push_line(&mut self, end: &WPos) {
+        self.segments.push(PathSegment::Line(*end));
+    }
+    // Circular arc from the current end, split in pieces of a quarter turn at
+    // most so that every controller can read it whatever the arc format used
+    pub fn push_arc(&mut self, center: &WPos, radius: f64, start_angle: f64, sweep: f64) {
+        let nb_pieces = (sweep.abs() / (PI / 2.)).ceil().max(1.) as usize;
+        let d_angle = sweep / nb_pieces as f64;
+        for i in 1..=nb_pieces {
+            let angle = start_angle + d_angle * i as f64;
+            let end = *center + WPos::new(radius * angle.cos(), radius * angle.sin());
+            self.segments
+                .push(PathSegment::Arc(*center, end, sweep > 0.));
+        }
+    }
+}
+
+pub fn get_segment_end(segment: &PathSegment) -> WPos {
+    match segment {
+        PathSegment::Line(end) | PathSegment::Arc(_, end, _) => *end,
+    }
+}
+fn get_segment_tangent(start: &WPos, segment: &PathSegment, at_start: bool) -> Option<WPos> {
+    match segment {
+        PathSegment::Line(end) => {
+            let length = start.dist(end);
+            if length > 0. {
+                Some((*end - *start) / length)
+            } else {
+                None
+            }
+        }
+        PathSegment::Arc(center, end, ccw) => {
+            let pos = if at_start { start } else { end };
+            if center.dist(pos) > 0. {
+                Some(get_arc_tangent(center, pos, *ccw))
+            } else {
+                None
+            }
+        }
+    }
+}
+pub fn get_angle(center: &WPos, pos: &WPos) -> f64 {
+    (pos.wy - center.wy).atan2(pos.wx - center.wx)
+}
+// Signed sweep of an arc, positive when counter-clockwise
+pub fn get_arc_sweep(center: &WPos, start: &WPos, end: &WPos, ccw: bool) -> f64 {
+    let v1 = *start - *center;
+    let v2 = *end - *center;
+    let angle = cross(&v1, &v2).atan2(v1.dot(&v2));
+    match (ccw, angle > 0.) {
+        (true, false) => angle + 2. * PI,
+        (false, true) => angle - 2. * PI,
+        _ => angle,
+    }
+}
+pub fn get_arc_tangent(center: &WPos, pos: &WPos, ccw: bool) -> WPos {
+    let radial = (*pos - *center) / center.dist(pos);
+    if ccw {
+        perp(&radial)
+    } else {
+        -perp(&radial)
+    }
+}
+
+// Walk all shapes of the pool and convert them into toolpaths.
+// Shapes are taken by increasing id, i.e. in their drawing order.
+pub fn get_toolpaths_from_pool(shapes_pool: &ShapesPool, tolerance: f64) -> Vec<Toolpath> {
+    let mut sh_ids: Vec<&ShapeId> = shapes_pool.keys().collect();
+    sh_ids.sort_by_key(|sh_id| sh_id.0);
+    sh_ids
+        .iter()
+        .flat_map(|sh_id| get_toolpaths_from_shape(shapes_pool[sh_id].as_ref(), tolerance))
+        .collect()
+}
+
+// Convert a shape into toolpaths by following its drawing construction:
+// a Move starts a new toolpath, lines and circular arcs are kept as is,
+// beziers and elliptical arcs are flattened within the tolerance
+pub fn get_toolpaths_from_shape(shape: &dyn Shape, tolerance: f64) -> Vec<Toolpath> {
+    let mut toolpaths: Vec<Toolpath> = vec![];
+    let mut current: Option<Toolpath> = None;
+    for cst in shape.get_construction().iter() {
+        use ConstructionType::*;
+        match cst {
+            Move(pos) => {
+                if let Some(toolpath) = current.take() {
+                    if !toolpath.segments.is_empty() {
+                        toolpaths.push(toolpath);
+                    }
+                }
+                current = Some(Toolpath::new(pos));
+            }
+            Line(end) => {
+                if let Some(toolpath) = current.as_mut() {
+                    toolpath.push_line(end);
+                }
+            }
+            QuadBezier(ctrl, end) => {
+                if let Some(toolpath) = current.as_mut() {
+                    let start = toolpath.get_end();
+                    let ddp = (start - *ctrl * 2. + *end).norm();
+                    let nb_steps = get_nb_flattening_steps(2, ddp, tolerance);
+                    for i in 1..=nb_steps {
+                        let t = i as f64 / nb_steps as f64;
+                        toolpath.push_line(&get_point_on_quad_bezier(t, &start, ctrl, end));
+                    }
+                }
+            }
+            CubicBezier(ctrl1, ctrl2, end) => {
+                if let Some(toolpath) = current.as_mut() {
+                    let start = toolpath.get_end();
+                    let ddp = (start - *ctrl1 * 2. + *ctrl2)
+                        .norm()
+                        .max((*ctrl1 - *ctrl2 * 2. + *end).norm());
+                    let nb_steps = get_nb_flattening_steps(3, ddp, tolerance);
+                    for i in 1..=nb_steps {
+                        let t = i as f64 / nb_steps as f64;
+                        toolpath
+                            .push_line(&get_point_on_cubic_bezier(t, &start, ctrl1, ctrl2, end));
+                    }
+                }
+            }
+            Ellipse(center, radius, rotation, start_angle, end_angle, _) => {
+                if let Some(toolpath) = current.as_mut() {
+                    push_elliptical_arc(
+                        toolpath,
+                        center,
+                        radius,
+                        *rotation,
+                        *start_angle,
+                        *end_angle,
+                        tolerance,
+                    );
+                }
+            }
+            Layer(_) | Rectangle(_, _, _) | Text(_, _) => (),
+        }
+    }
+    if let Some(toolpath) = current {
+        if !toolpath.segments.is_empty() {
+            toolpaths.push(toolpath);
+        }
+    }
+    toolpaths
+}
+
+// Uniform subdivision of a bezier of given degree: the chord error is bounded
+// by degree * (degree - 1) / 8 * max|second difference| / steps^2
+fn get_nb_flattening_steps(degree: usize, ddp: f64, tolerance: f64) -> usize {
+    let d = (degree * (degree - 1)) as f64 / 8.;
+    ((d * ddp / tolerance).sqrt().ceil() as usize).max(1)
+}
+
+fn push_elliptical_arc(
+    toolpath: &mut Toolpath,
+    center: &WPos,
+    radius: &WPos,
+    rotation: f64,
+    start_angle: f64,
+    end_angle: f64,
+    tolerance: f64,
+) {
+    let sweep = get_sweep_angle(start_angle, end_angle);
+    let get_point = |angle: f64| {
+        let pt = WPos::new(radius.wx * angle.cos(), radius.wy * angle.sin());
+        *center
+            + WPos::new(
+                pt.wx * rotation.cos() - pt.wy * rotation.sin(),
+                pt.wx * rotation.sin() + pt.wy * rotation.cos(),
+            )
+    };
+    // The toolpath must start exactly on the arc
+    let start = get_point(start_angle);
+    if toolpath.get_end().dist(&start) > tolerance {
+        toolpath.push_line(&start);
+    }
+
+    if (radius.wx - radius.wy).abs() < tolerance {
+        toolpath.push_arc(center, radius.wx, start_angle + rotation, sweep);
+    } else {
+        let r_max = radius.wx.max(radius.wy);
+        let d_angle = if tolerance < r_max {
+            2. * (1. - tolerance / r_max).acos()
+        } else {
+            PI / 2.
+        };
+        let nb_steps = ((sweep / d_angle).ceil() as usize).max(1);
+        for i in 1..=nb_steps {
+            let angle = start_angle + sweep * i as f64 / nb_steps as f64;
+            toolpath.push_line(&get_point(angle));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::ellipse::Ellipse;
+    use crate::shapes::rectangle::Rectangle;
+
+    #[test]
+    fn test_rectangle_toolpath_is_closed() {
+        let rectangle = Rectangle::new(&WPos::new(10., 10.), 20., 10.).unwrap();
+        let toolpaths = get_toolpaths_from_shape(&rectangle, 0.01);
+        assert_eq!(toolpaths.len(), 1);
+        assert_eq!(toolpaths[0].segments.len(), 4);
+        assert!(toolpaths[0].is_closed(0.01));
+    }
+
+    #[test]
+    fn test_circle_toolpath_is_made_of_arcs() {
+        let circle = Ellipse::new(&WPos::new(0., 0.), &WPos::new(10., 10.), 0., 2. * PI, 1.);
+        let toolpaths = get_toolpaths_from_shape(&circle, 0.01);
+        assert_eq!(toolpaths.len(), 1);
+        assert!(toolpaths[0]
+            .segments
+            .iter()
+            .all(|segment| matches!(segment, PathSegment::Arc(_, _, true))));
+        assert!(toolpaths[0].is_closed(0.01));
+    }
+}
@@ -6,14 +6,34 @@ macro_rules! log {
     }
 }
 
-use crate::datapool::DataPools;
+use crate::array::ArrayPattern;
+use crate::cam::backplot::{
+    get_backplot_from_gcode, get_shapes_from_backplot, BackplotMove, GCODE_EXTENSIONS,
+};
+use crate::cam::gcode::{get_gcode_from_pool, get_home};
+use crate::cam::kerf::get_cut_contours_from_contours;
+use crate::cam::leads::{get_lead_in, get_lead_out, get_leaded_toolpath};
+use crate::cam::ordering::get_cut_order;
+use crate::cam::parameters::{CamParameters, Lead, LeadType};
+use crate::cam::postprocessor::{Dialect, PostProcessor, LINUXCNC_TEMPLATE};
+use crate::cam::toolpath::Toolpath;
+use crate::datapool::{Contour, DataPools};
+use crate::document::{
+    get_project_document, get_project_from_document, is_project_document, ProjectSettings,
+    DOCUMENT_EXTENSION,
+};
+use crate::dxfexport::{get_dxf_from_pools, DxfVersion};
+use crate::dxfimport::get_shapes_from_dxf;
+use crate::import::{ImportOptions, ImportOrigin};
 use crate::math::*;
 use crate::shapes::cubicbezier::CubicBezier;
 use crate::shapes::ellipse::Ellipse;
 use crate::shapes::line::Line;
 use crate::shapes::quadbezier::QuadBezier;
 use crate::shapes::rectangle::Rectangle;
-use crate::shapes::types::{CPos, ConstructionType, LayerType, Point, WPos};
+use crate::shapes::types::{CPos, ConstructionType, LayerType, Point, ShapeId, WPos};
+use crate::svgexport::get_svg_from_pools;
+use crate::svgimport::get_shapes_from_svg;
 
 use js_sys::Array;
 use std::cell::{Ref, RefCell, RefMut};
@@ -22,8 +42,10 @@ use std::f64::consts::PI;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use web_sys::{
-    CanvasRenderingContext2d, Document, Element, Event, FileList, FileReader, HtmlCanvasElement,
-    HtmlElement, HtmlInputElement, KeyboardEvent, MouseEvent, Path2d, WheelEvent, Window,
+    Blob, BlobPropertyBag, CanvasRenderingContext2d, Document, Element, Event, FileList,
+    FileReader, HtmlAnchorElement, HtmlCanvasElement, HtmlElement, HtmlInputElement,
+    HtmlSelectElement, HtmlTextAreaElement, KeyboardEvent, MouseEvent, Path2d, Url, WheelEvent,
+    Window,
 };
 
 //console::log_1(&format!("{:?}", xxx).into());
@@ -49,6 +71,45 @@ struct KeysStates {
     shift_pressed: bool,
 }
 
+// Part of the transformation tool being dragged
+#[derive(Clone, Copy)]
+enum TransformHandle {
+    // Corner or middle of a side of the bounding box, scaling from the
+    // opposite one
+    Scale(WPos),
+    Rotate,
+    Pivot,
+    // Axis of the mirroring, drawn from the point picked
+    Mirror,
+}
+
+// Steps of the rotations when Shift is pressed
+const ROTATION_STEP: f64 = PI / 12.;
+// Under which a scaling would flatten the selection
+const MIN_SCALE: f64 = 1e-3;
+
+// File waiting for the import options
+enum ImportedFile {
+    Svg(String),
+    Dxf(String),
+}
+
+// What the machining of the drawing gives, worked out again only once the
+// drawing or the parameters change: it is far too slow to be done on every
+// render, the mouse moves included
+struct CamPreview {
+    revision: usize,
+    cam_parameters: CamParameters,
+    // The cuts start from the home of the machine
+    working_area: WPos,
+    // The shapes chained, as drawn
+    shape_contours: Vec<Contour>,
+    // Lead-ins and lead-outs alone, flattened to be shown
+    leads: Vec<Vec<WPos>>,
+    toolpaths: Vec<Toolpath>,
+    order: Vec<usize>,
+}
+
 pub struct PlayingArea {
     data_pools: DataPools,
     //
@@ -68,6 +129,19 @@ pub struct PlayingArea {
     apply_settings_button: HtmlElement,
     settings_width_input: HtmlInputElement,
     settings_height_input: HtmlInputElement,
+    settings_kerf_width_input: HtmlInputElement,
+    settings_undo_depth_input: HtmlInputElement,
+    settings_lead_in_inputs: LeadInputs,
+    settings_lead_out_inputs: LeadInputs,
+    settings_post_processor_select: HtmlSelectElement,
+    settings_post_processor_template_input: HtmlTextAreaElement,
+    import_panel: HtmlElement,
+    apply_import_button: HtmlElement,
+    import_scale_input: HtmlInputElement,
+    import_origin_select: HtmlSelectElement,
+    import_flip_y_input: HtmlInputElement,
+    array_inputs: ArrayInputs,
+    fillet_inputs: FilletInputs,
 
     // Mouse position on worksheet
     mouse_worksheet_position: HtmlElement,
@@ -82,6 +156,9 @@ pub struct PlayingArea {
 
     icon_selected: &'static str,
     selection_area: Option<[WPos; 2]>,
+    // Center of the rotations, the one of the selection if not picked
+    transform_pivot: Option<WPos>,
+    transform_handle: Option<TransformHandle>,
     keys_states: KeysStates,
     //
     mouse_state: MouseState,
@@ -94,6 +171,14 @@ pub struct PlayingArea {
     working_area_visual_grid: f64,
     working_area_snap_grid: f64,
 
+    // Machining
+    cam_parameters: CamParameters,
+    cam_preview: Option<CamPreview>,
+
+    file_to_import: Option<ImportedFile>,
+    // Moves of the last G-code program opened
+    backplot: Vec<BackplotMove>,
+
     // Drawing colors
     worksheet_color: String,
     dimension_color: String,
@@ -105,6 +190,12 @@ pub struct PlayingArea {
     background_color: String,
     fill_color: String,
     highlight_color: String,
+    lead_color: String,
+    rapid_color: String,
+    closed_contour_color: String,
+    open_contour_color: String,
+    backplot_cut_color: String,
+    backplot_rapid_color: String,
 
     // line patterns
     pub pattern_dashed: JsValue,
@@ -155,6 +246,51 @@ pub fn create_playing_area(window: Window) -> Result<(), JsValue> {
         .get_element_by_id("worksheetHeightInput")
         .expect("should have settings_height_input on settingsPanel")
         .dyn_into()?;
+    let settings_kerf_width_input: HtmlInputElement = document
+        .get_element_by_id("kerfWidthInput")
+        .expect("should have kerfWidthInput on settingsPanel")
+        .dyn_into()?;
+    let settings_undo_depth_input: HtmlInputElement = document
+        .get_element_by_id("undoDepthInput")
+        .expect("should have undoDepthInput on settingsPanel")
+        .dyn_into()?;
+    let settings_lead_in_inputs = LeadInputs::new(&document, "leadIn")?;
+    let settings_lead_out_inputs = LeadInputs::new(&document, "leadOut")?;
+    let settings_post_processor_select: HtmlSelectElement = document
+        .get_element_by_id("postProcessorSelect")
+        .expect("should have postProcessorSelect on settingsPanel")
+        .dyn_into()?;
+    let settings_post_processor_template_input: HtmlTextAreaElement = document
+        .get_element_by_id("postProcessorTemplateInput")
+        .expect("should have postProcessorTemplateInput on settingsPanel")
+        .dyn_into()?;
+    settings_post_processor_template_input.set_value(LINUXCNC_TEMPLATE);
+    let import_panel = document
+        .get_element_by_id("importPanel")
+        .expect("should have importPanel on the page")
+        .dyn_into::<HtmlElement>()?;
+    let apply_import_button = document
+        .get_element_by_id("applySvgImport")
+        .expect("should have applySvgImport on importPanel")
+        .dyn_into::<HtmlElement>()?;
+    let import_scale_input: HtmlInputElement = document
+        .get_element_by_id("importScaleInput")
+        .expect("should have importScaleInput on importPanel")
+        .dyn_into()?;
+    let import_origin_select: HtmlSelectElement = document
+        .get_element_by_id("importOriginSelect")
+        .expect("should have importOriginSelect on importPanel")
+        .dyn_into()?;
+    let import_flip_y_input: HtmlInputElement = document
+        .get_element_by_id("importFlipYInput")
+        .expect("should have importFlipYInput on importPanel")
+        .dyn_into()?;
+    let array_inputs = ArrayInputs::new(&document)?;
+    let fillet_inputs = FilletInputs::new(&document)?;
+    let cam_parameters = CamParameters::default();
+    settings_kerf_width_input.set_value(&cam_parameters.kerf_width.to_string());
+    settings_lead_in_inputs.set_lead(&cam_parameters.lead_in);
+    settings_lead_out_inputs.set_lead(&cam_parameters.lead_out);
     let mouse_worksheet_position: HtmlElement = document
         .get_element_by_id("status-info-worksheet-pos")
         .expect("should have status-info-worksheet-pos on the page")
@@ -177,6 +313,9 @@ pub fn create_playing_area(window: Window) -> Result<(), JsValue> {
     user_icons.insert("icon-rectangle", None);
     user_icons.insert("icon-ellipse", None);
     user_icons.insert("icon-scissors", None);
+    user_icons.insert("icon-fillet", None);
+    user_icons.insert("icon-transform", None);
+    user_icons.insert("icon-array", None);
     user_icons.insert("icon-cog", None);
 
     let document_element = document
@@ -197,6 +336,12 @@ pub fn create_playing_area(window: Window) -> Result<(), JsValue> {
     let background_color = style.get_property_value("--canvas-background-color")?;
     let fill_color = style.get_property_value("--canvas-fill-color")?;
     let highlight_color = style.get_property_value("--canvas-highlight-color")?;
+    let lead_color = style.get_property_value("--canvas-lead-color")?;
+    let rapid_color = style.get_property_value("--canvas-rapid-color")?;
+    let closed_contour_color = style.get_property_value("--canvas-closed-contour-color")?;
+    let open_contour_color = style.get_property_value("--canvas-open-contour-color")?;
+    let backplot_cut_color = style.get_property_value("--canvas-backplot-cut-color")?;
+    let backplot_rapid_color = style.get_property_value("--canvas-backplot-rapid-color")?;
     let dash_pattern = Array::new();
     let solid_pattern = Array::new();
     dash_pattern.push(&JsValue::from_f64(3.0));
@@ -239,6 +384,19 @@ pub fn create_playing_area(window: Window) -> Result<(), JsValue> {
         apply_settings_button,
         settings_width_input,
         settings_height_input,
+        settings_kerf_width_input,
+        settings_undo_depth_input,
+        settings_lead_in_inputs,
+        settings_lead_out_inputs,
+        settings_post_processor_select,
+        settings_post_processor_template_input,
+        import_panel,
+        apply_import_button,
+        import_scale_input,
+        import_origin_select,
+        import_flip_y_input,
+        array_inputs,
+        fillet_inputs,
         mouse_worksheet_position,
         _viewgrid_element: viewgrid_element,
         _snapgrid_element: snapgrid_element,
@@ -252,6 +410,8 @@ pub fn create_playing_area(window: Window) -> Result<(), JsValue> {
 
         icon_selected: "icon-arrow",
         selection_area: None,
+        transform_pivot: None,
+        transform_handle: None,
         keys_states: KeysStates::default(),
         mouse_state: MouseState::NoButton,
         mouse_previous_pos_canvas: CPos::default(),
@@ -266,6 +426,13 @@ pub fn create_playing_area(window: Window) -> Result<(), JsValue> {
         working_area_visual_grid,
         working_area_snap_grid,
 
+        // Machining
+        cam_parameters,
+        cam_preview: None,
+
+        file_to_import: None,
+        backplot: vec![],
+
         // Drawing colors
         worksheet_color,
         dimension_color,
@@ -277,6 +444,12 @@ pub fn create_playing_area(window: Window) -> Result<(), JsValue> {
         background_color,
         fill_color,
         highlight_color,
+        lead_color,
+        rapid_color,
+        closed_contour_color,
+        open_contour_color,
+        backplot_cut_color,
+        backplot_rapid_color,
 
         pattern_dashed: JsValue::from(dash_pattern),
         pattern_solid: JsValue::from(solid_pattern),
@@ -288,6 +461,8 @@ pub fn create_playing_area(window: Window) -> Result<(), JsValue> {
     init_context_menu(playing_area.clone())?;
     init_icons(playing_area.clone())?;
     init_settings_panel(playing_area.clone())?;
+    init_import_panel(playing_area.clone())?;
+    init_array_panel(playing_area.clone())?;
     init_status(playing_area.clone())?;
 
     resize_area(playing_area.clone());
@@ -338,6 +513,45 @@ fn init_settings_panel(pa: RefArea) -> Result<(), JsValue> {
     )?;
     Ok(())
 }
+fn init_import_panel(pa: RefArea) -> Result<(), JsValue> {
+    let pa_ref = pa.borrow_mut();
+    set_callback(
+        pa.clone(),
+        "click".into(),
+        &pa_ref.apply_import_button,
+        Box::new(on_apply_import_click),
+    )?;
+    Ok(())
+}
+fn init_array_panel(pa: RefArea) -> Result<(), JsValue> {
+    let pa_ref = pa.borrow_mut();
+    set_callback(
+        pa.clone(),
+        "click".into(),
+        &pa_ref.array_inputs.apply_button,
+        Box::new(on_apply_array_click),
+    )?;
+    set_callback(
+        pa.clone(),
+        "click".into(),
+        &pa_ref.array_inputs.cancel_button,
+        Box::new(on_cancel_array_click),
+    )?;
+    // The preview follows the values typed
+    for event_str in ["input", "change"] {
+        let pa_cloned = pa.clone();
+        let closure = Closure::<dyn FnMut(_)>::new(move |_event: Event| {
+            pa_cloned.borrow().array_inputs.show_fields();
+            render(pa_cloned.clone());
+        });
+        pa_ref
+            .array_inputs
+            .form
+            .add_event_listener_with_callback(event_str, closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+    Ok(())
+}
 fn init_icons(pa: RefArea) -> Result<(), JsValue> {
     let mut pa_ref = pa.borrow_mut();
     let document = pa_ref.document.clone();
@@ -384,6 +598,20 @@ fn init_context_menu(pa: RefArea) -> Result<(), JsValue> {
         &delete_group,
         Box::new(on_context_menu_delete_click),
     )?;
+    let transforms = [
+        ("action-rotate", Transform::rotate(-PI / 2.)),
+        ("action-mirror-x", Transform::scale(1., -1.)),
+        ("action-mirror-y", Transform::scale(-1., 1.)),
+    ];
+    for (action, transform) in transforms {
+        let element = document.get_element_by_id(action).unwrap();
+        set_callback(
+            pa.clone(),
+            "click".into(),
+            &element,
+            Box::new(move |pa, _event| on_context_menu_transform_click(pa, transform)),
+        )?;
+    }
     Ok(())
 }
 fn init_canvas(pa: RefArea) -> Result<(), JsValue> {
@@ -449,6 +677,12 @@ fn init_menu(pa: RefArea) -> Result<(), JsValue> {
     let save_element = document.get_element_by_id("save-option").unwrap();
     let save_element: HtmlElement = save_element.dyn_into::<HtmlElement>()?;
 
+    let export_svg_element = document.get_element_by_id("export-svg-option").unwrap();
+    let export_svg_element: HtmlElement = export_svg_element.dyn_into::<HtmlElement>()?;
+
+    let export_gcode_element = document.get_element_by_id("export-gcode-option").unwrap();
+    let export_gcode_element: HtmlElement = export_gcode_element.dyn_into::<HtmlElement>()?;
+
     let file_input = document.get_element_by_id("file-input").unwrap();
     let file_input: HtmlElement = file_input.dyn_into::<HtmlElement>()?;
 
@@ -458,8 +692,67 @@ fn init_menu(pa: RefArea) -> Result<(), JsValue> {
         file_input_clone.click();
     }) as Box<dyn FnMut()>);
 
+    let pa_cloned = pa.clone();
     let on_save = Closure::wrap(Box::new(move || {
-        // Your save action here
+        let pa_ref = pa_cloned.borrow();
+        let settings = ProjectSettings {
+            working_area: pa_ref.working_area,
+            visual_grid: pa_ref.working_area_visual_grid,
+            snap_grid: pa_ref.working_area_snap_grid,
+            cam_parameters: pa_ref.cam_parameters.clone(),
+        };
+        let content = match get_project_document(
+            pa_ref.data_pools.get_all_shapes(),
+            pa_ref.data_pools.get_all_groups(),
+            &settings,
+        ) {
+            Ok(content) => content,
+            Err(e) => {
+                log!("Failed to save the project: {}", e);
+                return;
+            }
+        };
+        let file_name = format!("drawing.{}", DOCUMENT_EXTENSION);
+        if let Err(e) = download_file(&pa_ref.document, &file_name, &content) {
+            log!("Failed to save the project: {:?}", e);
+        }
+    }) as Box<dyn FnMut()>);
+
+    let pa_cloned = pa.clone();
+    let on_export_svg = Closure::wrap(Box::new(move || {
+        let pa_ref = pa_cloned.borrow();
+        let content = get_svg_from_pools(
+            pa_ref.data_pools.get_all_shapes(),
+            pa_ref.data_pools.get_all_groups(),
+            &pa_ref.working_area,
+        );
+        if let Err(e) = download_file(&pa_ref.document, "drawing.svg", &content) {
+            log!("Failed to export SVG: {:?}", e);
+        }
+    }) as Box<dyn FnMut()>);
+
+    let pa_cloned = pa.clone();
+    let on_export_gcode = Closure::wrap(Box::new(move || {
+        let pa_ref = pa_cloned.borrow();
+        match PostProcessor::from_dialect(&pa_ref.cam_parameters.dialect) {
+            Ok(mut post_processor) => {
+                let gcode = get_gcode_from_pool(
+                    pa_ref.data_pools.get_all_shapes(),
+                    &pa_ref.working_area,
+                    &pa_ref.cam_parameters,
+                    &mut post_processor,
+                );
+                let file_name = format!("drawing.{}", post_processor.extension);
+                if let Err(e) = download_file(&pa_ref.document, &file_name, &gcode) {
+                    log!("Failed to save G-code: {:?}", e);
+                }
+            }
+            Err(e) => {
+                let _ = pa_ref
+                    .window
+                    .alert_with_message(&format!("G-code post processor: {}", e));
+            }
+        }
     }) as Box<dyn FnMut()>);
 
     load_element.add_event_listener_with_callback("click", on_load.as_ref().unchecked_ref())?;
@@ -468,6 +761,65 @@ fn init_menu(pa: RefArea) -> Result<(), JsValue> {
     save_element.add_event_listener_with_callback("click", on_save.as_ref().unchecked_ref())?;
     on_save.forget(); // Leaks memory, but we need to do this to keep the callback alive
 
+    export_svg_element
+        .add_event_listener_with_callback("click", on_export_svg.as_ref().unchecked_ref())?;
+    on_export_svg.forget(); // Leaks memory, but we need to do this to keep the callback alive
+
+    export_gcode_element
+        .add_event_listener_with_callback("click", on_export_gcode.as_ref().unchecked_ref())?;
+    on_export_gcode.forget(); // Leaks memory, but we need to do this to keep the callback alive
+
+    let backplot_to_shapes_element = document
+        .get_element_by_id("backplot-to-shapes-option")
+        .unwrap();
+    let backplot_to_shapes_element: HtmlElement =
+        backplot_to_shapes_element.dyn_into::<HtmlElement>()?;
+    let pa_cloned = pa.clone();
+    let on_backplot_to_shapes = Closure::wrap(Box::new(move || {
+        convert_backplot(pa_cloned.clone());
+        render(pa_cloned.clone());
+    }) as Box<dyn FnMut()>);
+    backplot_to_shapes_element.add_event_listener_with_callback(
+        "click",
+        on_backplot_to_shapes.as_ref().unchecked_ref(),
+    )?;
+    on_backplot_to_shapes.forget(); // Leaks memory, but we need to do this to keep the callback alive
+
+    let clear_backplot_element = document.get_element_by_id("clear-backplot-option").unwrap();
+    let clear_backplot_element: HtmlElement = clear_backplot_element.dyn_into::<HtmlElement>()?;
+    let pa_cloned = pa.clone();
+    let on_clear_backplot = Closure::wrap(Box::new(move || {
+        pa_cloned.borrow_mut().backplot.clear();
+        render(pa_cloned.clone());
+    }) as Box<dyn FnMut()>);
+    clear_backplot_element
+        .add_event_listener_with_callback("click", on_clear_backplot.as_ref().unchecked_ref())?;
+    on_clear_backplot.forget(); // Leaks memory, but we need to do this to keep the callback alive
+
+    for (element_id, version) in [
+        ("export-dxf-r12-option", DxfVersion::R12),
+        ("export-dxf-r2000-option", DxfVersion::R2000),
+    ] {
+        let export_dxf_element = document.get_element_by_id(element_id).unwrap();
+        let export_dxf_element: HtmlElement = export_dxf_element.dyn_into::<HtmlElement>()?;
+        let pa_cloned = pa.clone();
+        let on_export_dxf = Closure::wrap(Box::new(move || {
+            let pa_ref = pa_cloned.borrow();
+            let content = get_dxf_from_pools(
+                pa_ref.data_pools.get_all_shapes(),
+                pa_ref.data_pools.get_all_groups(),
+                &pa_ref.working_area,
+                version,
+            );
+            if let Err(e) = download_file(&pa_ref.document, "drawing.dxf", &content) {
+                log!("Failed to export DXF: {:?}", e);
+            }
+        }) as Box<dyn FnMut()>);
+        export_dxf_element
+            .add_event_listener_with_callback("click", on_export_dxf.as_ref().unchecked_ref())?;
+        on_export_dxf.forget(); // Leaks memory, but we need to do this to keep the callback alive
+    }
+
     drop(pa_mut);
     // Set up an event listener to handle file selection
     let on_file_select = Closure::wrap(Box::new(move || {
@@ -480,7 +832,7 @@ fn init_menu(pa: RefArea) -> Result<(), JsValue> {
                 .dyn_into::<FileList>()
                 .unwrap();
             if let Some(file) = files.get(0) {
-                // let file_name = file.name();
+                let file_name = file.name().to_lowercase();
                 let file_reader = FileReader::new().unwrap();
 
                 let on_load = Closure::wrap(Box::new(move |event: Event| {
@@ -488,7 +840,18 @@ fn init_menu(pa: RefArea) -> Result<(), JsValue> {
                     let file_reader: FileReader = target.dyn_into().unwrap();
                     let result = file_reader.result().unwrap();
                     if let Some(content) = result.as_string() {
-                        convert_svg_to_shapes(pa_clone.clone(), content);
+                        if is_project_document(&content) {
+                            load_project(pa_clone.clone(), &content);
+                        } else if GCODE_EXTENSIONS
+                            .iter()
+                            .any(|extension| file_name.ends_with(&format!(".{}", extension)))
+                        {
+                            load_backplot(pa_clone.clone(), &content);
+                        } else if file_name.ends_with(".dxf") {
+                            open_import_panel(pa_clone.clone(), ImportedFile::Dxf(content));
+                        } else {
+                            open_import_panel(pa_clone.clone(), ImportedFile::Svg(content));
+                        }
                         drop(pa_clone.borrow_mut());
                         render(pa_clone.clone());
                     }
@@ -546,279 +909,102 @@ fn set_callback(
 
     Ok(())
 }
-fn convert_svg_to_shapes(pa: RefArea, svg_data: String) {
+// The drawing and the settings are replaced by the ones of the project
+fn load_project(pa: RefArea, content: &str) {
+    let project = match get_project_from_document(content) {
+        Ok(project) => project,
+        Err(e) => {
+            let _ = pa
+                .borrow()
+                .window
+                .alert_with_message(&format!("Loading the project: {}", e));
+            return;
+        }
+    };
+
+    let mut pa_mut = pa.borrow_mut();
+    pa_mut.data_pools.load(project.shapes, &project.groups);
+    pa_mut.working_area = project.settings.working_area;
+    pa_mut.working_area_visual_grid = project.settings.visual_grid;
+    pa_mut.working_area_snap_grid = project.settings.snap_grid;
+    pa_mut.cam_parameters = project.settings.cam_parameters;
+    set_settings_inputs(&pa_mut);
+    drop(pa_mut);
+    resize_area(pa.clone());
+}
+// The file is imported once the options are chosen in the import panel
+fn open_import_panel(pa: RefArea, file: ImportedFile) {
+    let mut pa_mut = pa.borrow_mut();
+    pa_mut.file_to_import = Some(file);
+    pa_mut
+        .import_panel
+        .style()
+        .set_property("display", "block")
+        .unwrap();
+    pa_mut
+        .modal_backdrop
+        .style()
+        .set_property("display", "block")
+        .unwrap();
+}
+// The imported shapes are selected, a SVG file makes a single group
+fn import_file(pa: RefArea, file: ImportedFile, options: &ImportOptions) {
     let mut pa_mut = pa.borrow_mut();
-    let grp_id = pa_mut.data_pools.create_group_id();
     pa_mut.data_pools.clear_shapes_selection();
+    pa_mut.data_pools.begin_command();
 
-    for event in svg::parser::Parser::new(&svg_data).into_iter() {
-        match event {
-            svg::parser::Event::Tag(svg::node::element::tag::Path, _, attributes) => {
-                let data = attributes.get("d").unwrap();
-                let data = svg::node::element::path::Data::parse(data).unwrap();
-                let mut current_position = WPos::default();
-                let mut start_position = WPos::default();
-                let mut last_quad_control_point: Option<WPos> = None;
-                let mut last_cubic_control_point: Option<WPos> = None;
-                for command in data.iter() {
-                    let command_clone = command.clone();
-                    use svg::node::element::path::*;
-                    match command_clone {
-                        Command::Move(postype, params) => {
-                            if params.len() == 2 {
-                                current_position = match postype {
-                                    Position::Absolute => WPos {
-                                        wx: params[0] as f64,
-                                        wy: params[1] as f64,
-                                    },
-                                    Position::Relative => WPos {
-                                        wx: params[0] as f64 + current_position.wx,
-                                        wy: params[1] as f64 + current_position.wy,
-                                    },
-                                };
-                                start_position = current_position;
-                                last_quad_control_point = None;
-                                last_cubic_control_point = None;
-                            }
-                        }
-                        Command::Line(postype, params) => {
-                            if params.len() % 2 == 0 {
-                                let nb_curves = params.len() / 2;
-                                for curve in 0..nb_curves {
-                                    let end_point = WPos {
-                                        wx: params[2 * curve] as f64,
-                                        wy: params[2 * curve + 1] as f64,
-                                    };
-                                    let new_position = match postype {
-                                        Position::Absolute => end_point,
-                                        Position::Relative => current_position + end_point,
-                                    };
-                                    if let Some(shape) = Line::new(&current_position, &new_position)
-                                    {
-                                        let sh_id = pa_mut.data_pools.insert_shape(Box::new(shape));
-                                        pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                        pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
-                                    }
-
-                                    current_position = new_position;
-                                    last_quad_control_point = None;
-                                    last_cubic_control_point = None;
-                                }
-                            }
-                        }
-                        Command::HorizontalLine(postype, params) => {
-                            for curve in 0..params.len() {
-                                let end_point = WPos {
-                                    wx: params[curve] as f64,
-                                    wy: current_position.wy,
-                                };
-                                let new_position = match postype {
-                                    Position::Absolute => end_point,
-                                    Position::Relative => current_position + end_point,
-                                };
-                                if let Some(shape) = Line::new(&current_position, &new_position) {
-                                    let sh_id = pa_mut.data_pools.insert_shape(Box::new(shape));
-                                    pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                    pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
-                                }
-
-                                current_position = new_position;
-                                last_quad_control_point = None;
-                                last_cubic_control_point = None;
-                            }
-                        }
-                        Command::VerticalLine(postype, params) => {
-                            for curve in 0..params.len() {
-                                let end_point = WPos {
-                                    wx: current_position.wx,
-                                    wy: params[curve] as f64,
-                                };
-                                let new_position = match postype {
-                                    Position::Absolute => end_point,
-                                    Position::Relative => current_position + end_point,
-                                };
-                                if let Some(shape) = Line::new(&current_position, &new_position) {
-                                    let sh_id = pa_mut.data_pools.insert_shape(Box::new(shape));
-                                    pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                    pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
-                                }
-
-                                current_position = new_position;
-                                last_quad_control_point = None;
-                                last_cubic_control_point = None;
-                            }
-                        }
-                        Command::QuadraticCurve(postype, params) => {
-                            if params.len() % 4 == 0 {
-                                let nb_curves = params.len() / 4;
-                                for curve in 0..nb_curves {
-                                    let mut control_point = WPos {
-                                        wx: params[4 * curve] as f64,
-                                        wy: params[4 * curve + 1] as f64,
-                                    };
-                                    let end_point = WPos {
-                                        wx: params[4 * curve + 2] as f64,
-                                        wy: params[4 * curve + 3] as f64,
-                                    };
-                                    let new_position = match postype {
-                                        Position::Absolute => end_point,
-                                        Position::Relative => {
-                                            control_point += current_position;
-                                            current_position + end_point
-                                        }
-                                    };
-                                    if let Some(shape) = QuadBezier::new(
-                                        &current_position,
-                                        &control_point,
-                                        &new_position,
-                                    ) {
-                                        let sh_id = pa_mut.data_pools.insert_shape(Box::new(shape));
-                                        pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                        pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
-                                    }
-
-                                    current_position = new_position;
-                                    last_quad_control_point = Some(control_point);
-                                    last_cubic_control_point = None;
-                                }
-                            }
-                        }
-                        Command::SmoothQuadraticCurve(postype, params) => {
-                            if params.len() % 2 == 0 {
-                                let nb_curves = params.len() / 2;
-                                for curve in 0..nb_curves {
-                                    let control_point =
-                                        if let Some(last_ctrl_pt) = last_quad_control_point {
-                                            current_position + (current_position - last_ctrl_pt)
-                                        } else {
-                                            current_position
-                                        };
-                                    let end_point = WPos {
-                                        wx: params[2 * curve] as f64,
-                                        wy: params[2 * curve + 1] as f64,
-                                    };
-                                    let new_position = match postype {
-                                        Position::Absolute => end_point,
-                                        Position::Relative => current_position + end_point,
-                                    };
-                                    if let Some(shape) = QuadBezier::new(
-                                        &current_position,
-                                        &control_point,
-                                        &new_position,
-                                    ) {
-                                        let sh_id = pa_mut.data_pools.insert_shape(Box::new(shape));
-                                        pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                        pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
-                                    }
-
-                                    current_position = new_position;
-                                    last_quad_control_point = Some(control_point);
-                                    last_cubic_control_point = None;
-                                }
-                            }
-                        }
-                        Command::CubicCurve(postype, params) => {
-                            if params.len() % 6 == 0 {
-                                let nb_curves = params.len() / 6;
-                                for curve in 0..nb_curves {
-                                    let mut control_point1 = WPos {
-                                        wx: params[6 * curve] as f64,
-                                        wy: params[6 * curve + 1] as f64,
-                                    };
-                                    let mut control_point2 = WPos {
-                                        wx: params[6 * curve + 2] as f64,
-                                        wy: params[6 * curve + 3] as f64,
-                                    };
-                                    let end_point = WPos {
-                                        wx: params[6 * curve + 4] as f64,
-                                        wy: params[6 * curve + 5] as f64,
-                                    };
-                                    let new_position = match postype {
-                                        Position::Absolute => end_point,
-                                        Position::Relative => {
-                                            control_point1 += current_position;
-                                            control_point2 += current_position;
-                                            current_position + end_point
-                                        }
-                                    };
-                                    if let Some(shape) = CubicBezier::new(
-                                        &current_position,
-                                        &control_point1,
-                                        &control_point2,
-                                        &new_position,
-                                    ) {
-                                        let sh_id = pa_mut.data_pools.insert_shape(Box::new(shape));
-                                        pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                        pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                        pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
-                                    }
-                                    current_position = new_position;
-                                    last_quad_control_point = None;
-                                    last_cubic_control_point = Some(control_point2);
-                                }
-                            }
-                        }
-                        Command::SmoothCubicCurve(postype, params) => {
-                            if params.len() % 4 == 0 {
-                                let nb_curves = params.len() / 4;
-                                for curve in 0..nb_curves {
-                                    let control_point1 =
-                                        if let Some(last_ctrl_pt) = last_cubic_control_point {
-                                            current_position + (current_position - last_ctrl_pt)
-                                        } else {
-                                            current_position
-                                        };
-                                    let mut control_point2 = WPos {
-                                        wx: params[4 * curve] as f64,
-                                        wy: params[4 * curve + 1] as f64,
-                                    };
-                                    let end_point = WPos {
-                                        wx: params[4 * curve + 2] as f64,
-                                        wy: params[4 * curve + 3] as f64,
-                                    };
-                                    let new_position = match postype {
-                                        Position::Absolute => end_point,
-                                        Position::Relative => {
-                                            control_point2 += current_position;
-                                            current_position + end_point
-                                        }
-                                    };
-                                    if let Some(shape) = CubicBezier::new(
-                                        &current_position,
-                                        &control_point1,
-                                        &control_point2,
-                                        &new_position,
-                                    ) {
-                                        let sh_id = pa_mut.data_pools.insert_shape(Box::new(shape));
-                                        pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                        pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                        pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
-                                    }
-
-                                    current_position = new_position;
-                                    last_quad_control_point = None;
-                                    last_cubic_control_point = Some(control_point2);
-                                }
-                            }
-                        }
-                        Command::EllipticalArc(_postype, _params) => {}
-                        Command::Close => {
-                            if let Some(shape) = Line::new(&current_position, &start_position) {
-                                let sh_id = pa_mut.data_pools.insert_shape(Box::new(shape));
-                                pa_mut.data_pools.set_shape_selected(&sh_id, true);
-                                pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
-                            }
-
-                            current_position = start_position;
-                            last_quad_control_point = None;
-                            last_cubic_control_point = None;
-                        }
-                    }
-                }
-            }
-            _ => {}
+    let (format_name, groups, report) = match file {
+        ImportedFile::Svg(svg_data) => {
+            let (shapes, report) = get_shapes_from_svg(&svg_data, options);
+            ("SVG", vec![shapes], report)
+        }
+        ImportedFile::Dxf(dxf_data) => {
+            let (groups, report) = get_shapes_from_dxf(&dxf_data, options);
+            ("DXF", groups, report)
+        }
+    };
+    for shapes in groups {
+        let grp_id = pa_mut.data_pools.create_group_id();
+        for shape in shapes {
+            let sh_id = pa_mut.data_pools.insert_shape(shape);
+            pa_mut.data_pools.set_shape_selected(&sh_id, true);
+            pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
         }
     }
+    pa_mut.data_pools.end_command();
+    if !report.is_clean() {
+        let _ = pa_mut.window.alert_with_message(&format!(
+            "{} import: {}",
+            format_name,
+            report.get_summary()
+        ));
+    }
+}
+
+// The program replaces the previous backplot
+fn load_backplot(pa: RefArea, gcode: &str) {
+    let mut pa_mut = pa.borrow_mut();
+    let (backplot, report) = get_backplot_from_gcode(gcode, &pa_mut.working_area);
+    pa_mut.backplot = backplot;
+    if !report.is_clean() {
+        let _ = pa_mut
+            .window
+            .alert_with_message(&format!("G-code backplot: {}", report.get_summary()));
+    }
+}
+// The cut moves make a group, they are selected
+fn convert_backplot(pa: RefArea) {
+    let mut pa_mut = pa.borrow_mut();
+    pa_mut.data_pools.clear_shapes_selection();
+    let shapes = get_shapes_from_backplot(&pa_mut.backplot);
+    pa_mut.data_pools.begin_command();
+    let grp_id = pa_mut.data_pools.create_group_id();
+    for shape in shapes {
+        let sh_id = pa_mut.data_pools.insert_shape(shape);
+        pa_mut.data_pools.set_shape_selected(&sh_id, true);
+        pa_mut.data_pools.set_shape_group(&grp_id, &sh_id);
+    }
+    pa_mut.data_pools.end_command();
 }
 
 ///////////////
@@ -841,6 +1027,8 @@ fn on_mouse_down(pa: RefArea, event: Event) {
             }
 
             pa_mut.mouse_state = MouseState::LeftDown;
+            // Everything done until the button is released is undone at once
+            pa_mut.data_pools.begin_command();
 
             let scale = pa_mut.global_scale;
             let offset = pa_mut.canvas_offset;
@@ -870,6 +1058,7 @@ fn on_mouse_down(pa: RefArea, event: Event) {
                         shift_pressed,
                         grab_handle_precision,
                     );
+                    pa_mut.data_pools.record_shapes_selected();
                 }
                 "icon-selection" => pa_mut.selection_area = Some([pick_pos, pick_pos]),
                 "icon-line" => {
@@ -916,15 +1105,49 @@ fn on_mouse_down(pa: RefArea, event: Event) {
                     let sh_id = pa_mut.data_pools.insert_shape(Box::new(shape));
                     pa_mut.data_pools.set_shape_selected(&sh_id, true);
                 }
+                "icon-transform" => {
+                    let handle = get_transform_handle(&pa_mut, &pick_pos);
+                    pa_mut.transform_handle = Some(handle.unwrap_or(TransformHandle::Mirror));
+                }
                 "icon-scissors" => {
                     if let Some(sh_id) = pa_mut
                         .data_pools
                         .pick_first_shape(&pick_pos, grab_handle_precision)
                     {
                         log!("Picked some shape id: {:?}", sh_id);
-                        pa_mut
-                            .data_pools
-                            .cut_shape(&sh_id, &pick_pos, grab_handle_precision);
+                        pa_mut.data_pools.cut_shape(&sh_id, &pick_pos);
+                    }
+                }
+                "icon-fillet" => {
+                    // The first shape clicked stays selected until the
+                    // second one is
+                    let picked = pa_mut
+                        .data_pools
+                        .pick_first_shape(&pick_pos, grab_handle_precision);
+                    let shapes_selected = pa_mut.data_pools.get_shapes_selected();
+                    let first = match shapes_selected.len() {
+                        1 => shapes_selected
+                            .iter()
+                            .copied()
+                            .find(|sh_id| Some(*sh_id) != picked),
+                        _ => None,
+                    };
+                    pa_mut.data_pools.clear_shapes_selection();
+                    match (first, picked) {
+                        (Some(sh_id_a), Some(sh_id_b)) => {
+                            let pa_fields = &mut *pa_mut;
+                            if let Err(e) = pa_fields.fillet_inputs.apply(
+                                &mut pa_fields.data_pools,
+                                &sh_id_a,
+                                &sh_id_b,
+                            ) {
+                                let _ = pa_fields
+                                    .window
+                                    .alert_with_message(&format!("Rounding the corner: {}", e));
+                            }
+                        }
+                        (None, Some(sh_id)) => pa_mut.data_pools.set_shape_selected(&sh_id, true),
+                        _ => (),
                     }
                 }
                 _ => (),
@@ -979,6 +1202,11 @@ fn on_mouse_move(pa: RefArea, event: Event) {
                         } else {
                             let shapes_selected = pa_mut.data_pools.get_shapes_selected().clone();
                             shapes_selected.iter().for_each(|sh_id| {
+                                pa_mut.data_pools.magnet_to_intersection(
+                                    &mut pick_pos,
+                                    Some(*sh_id),
+                                    magnet_distance,
+                                );
                                 pa_mut.data_pools.magnet_to_point(
                                     &mut pick_pos,
                                     Some(*sh_id),
@@ -998,10 +1226,23 @@ fn on_mouse_move(pa: RefArea, event: Event) {
                             sa[1] = pick_pos
                         }
                     }
+                    "icon-transform" => {
+                        pa_mut
+                            .data_pools
+                            .magnet_to_point(&mut pick_pos, None, magnet_distance);
+                        if let Some(TransformHandle::Pivot) = pa_mut.transform_handle {
+                            pa_mut.transform_pivot = Some(pick_pos);
+                        }
+                    }
                     "icon-line" | "icon-quadbezier" | "icon-cubicbezier" | "icon-ellipse"
                     | "icon-rectangle" => {
                         let shapes_selected = pa_mut.data_pools.get_shapes_selected().clone();
                         shapes_selected.iter().for_each(|sh_id| {
+                            pa_mut.data_pools.magnet_to_intersection(
+                                &mut pick_pos,
+                                Some(*sh_id),
+                                magnet_distance,
+                            );
                             pa_mut.data_pools.magnet_to_point(
                                 &mut pick_pos,
                                 Some(*sh_id),
@@ -1021,6 +1262,9 @@ fn on_mouse_move(pa: RefArea, event: Event) {
             }
         } else {
             pick_pos.snap(snap_grid);
+            pa_mut
+                .data_pools
+                .magnet_to_intersection(&mut pick_pos, None, magnet_distance);
             pa_mut
                 .data_pools
                 .magnet_to_point(&mut pick_pos, None, magnet_distance);
@@ -1069,6 +1313,21 @@ fn on_mouse_up(pa: RefArea, event: Event) {
                 }
                 pa_mut.selection_area = None;
             }
+            "icon-transform" => {
+                // A click out of the handles picks the pivot
+                match (get_drag_transform(&pa_mut), pa_mut.transform_handle) {
+                    (Some(transform), _) => {
+                        pa_mut.data_pools.transform_shapes_selected(&transform);
+                        pa_mut.transform_pivot =
+                            pa_mut.transform_pivot.map(|pivot| transform.apply(&pivot));
+                    }
+                    (None, Some(TransformHandle::Mirror)) => {
+                        pa_mut.transform_pivot = Some(pa_mut.pick_pos_ms_dwn)
+                    }
+                    _ => (),
+                }
+                pa_mut.transform_handle = None;
+            }
             "icon-line" | "icon-quadbezier" | "icon-cubicbezier" | "icon-ellipse"
             | "icon-rectangle" => {
                 if let Some(sh_sel_id) = pa_mut
@@ -1085,7 +1344,11 @@ fn on_mouse_up(pa: RefArea, event: Event) {
 
             _ => (),
         }
-        go_to_arrow_tool(&mut pa_mut);
+        pa_mut.data_pools.end_command();
+        // The transformation tool stays to chain the transformations
+        if pa_mut.icon_selected != "icon-transform" {
+            go_to_arrow_tool(&mut pa_mut);
+        }
         drop(pa_mut);
         render(pa.clone());
     }
@@ -1135,6 +1398,8 @@ fn on_mouse_enter(pa: RefArea, _event: Event) {
 fn on_mouse_leave(pa: RefArea, _event: Event) {
     let mut pa_ref = pa.borrow_mut();
     pa_ref.mouse_state = MouseState::NoButton;
+    pa_ref.transform_handle = None;
+    pa_ref.data_pools.end_command();
 }
 fn on_keydown(pa: RefArea, event: Event) {
     if let Ok(keyboard_event) = event.dyn_into::<KeyboardEvent>() {
@@ -1143,6 +1408,34 @@ fn on_keydown(pa: RefArea, event: Event) {
         if keyboard_event.key() == "Delete" || keyboard_event.key() == "Backspace" {
             pa_mut.data_pools.delete_shapes_selected();
         }
+        // Ctrl+Z undoes, Ctrl+Shift+Z redoes
+        if pa_mut.keys_states.crtl_pressed && keyboard_event.key().to_lowercase() == "z" {
+            keyboard_event.prevent_default();
+            if pa_mut.keys_states.shift_pressed {
+                pa_mut.data_pools.redo();
+            } else {
+                pa_mut.data_pools.undo();
+            }
+        }
+        // Ctrl+C copies, Ctrl+X cuts, Ctrl+V pastes at the cursor and Ctrl+D
+        // duplicates in place
+        if pa_mut.keys_states.crtl_pressed {
+            match keyboard_event.key().to_lowercase().as_str() {
+                "c" => pa_mut.data_pools.copy_shapes_selected(),
+                "x" => pa_mut.data_pools.cut_shapes_selected(),
+                "v" => {
+                    keyboard_event.prevent_default();
+                    let pick_pos = pa_mut.pick_pos;
+                    let snap_grid = pa_mut.working_area_snap_grid;
+                    pa_mut.data_pools.paste_at(&pick_pos, snap_grid);
+                }
+                "d" => {
+                    keyboard_event.prevent_default();
+                    pa_mut.data_pools.duplicate_shapes_selected();
+                }
+                _ => (),
+            }
+        }
         // if keyboard_event.key() == "Escape" {
         //     console::log_1(&"ddd".into());
         //     if pa_mut.icon_selected == "icon-line"
@@ -1182,17 +1475,6 @@ fn on_keydown(pa: RefArea, event: Event) {
         //         select_icon(&pa_ref, &"icon-line");
         //     }
         // }
-        // if keyboard_event.key() == "c" {
-        //     if pa_ref.ctrl_or_meta_pressed {
-        //         let copy = pa_ref
-        //             .shapes
-        //             .iter()
-        //             .filter(|shape| shape.get_handle_selected() < -1)
-        //             .cloned()
-        //             .collect();
-        //         pa_ref.shape_buffer_copy_paste = copy;
-        //     }
-        // }
         drop(pa_mut);
         render(pa.clone());
     }
@@ -1265,6 +1547,246 @@ fn on_context_menu_delete_click(pa: RefArea, _event: Event) {
         }
     }
 }
+// Rotation or mirroring of the selection around the pivot of the
+// transformation tool
+fn on_context_menu_transform_click(pa: RefArea, transform: Transform) {
+    let mut pa_mut = pa.borrow_mut();
+    if let Some(context_menu) = pa_mut.document.get_element_by_id("contextMenu") {
+        if let Some(html_element) =
+            wasm_bindgen::JsCast::dyn_ref::<web_sys::HtmlElement>(&context_menu)
+        {
+            html_element
+                .style()
+                .set_property("display", "none")
+                .unwrap();
+        }
+    }
+    if let Some(bb) = pa_mut.data_pools.get_shapes_selected_bounded_rectangle() {
+        let pivot = get_transform_pivot(&pa_mut, &bb);
+        pa_mut
+            .data_pools
+            .transform_shapes_selected(&transform.around(&pivot));
+    }
+    drop(pa_mut);
+    render(pa.clone());
+}
+
+// Type, length and angle inputs of a lead in the settings panel
+struct LeadInputs {
+    type_select: HtmlSelectElement,
+    length_input: HtmlInputElement,
+    angle_input: HtmlInputElement,
+}
+impl LeadInputs {
+    fn new(document: &Document, prefix: &str) -> Result<LeadInputs, JsValue> {
+        let get_input = |suffix: &str| -> Result<Element, JsValue> {
+            let id = format!("{}{}", prefix, suffix);
+            document
+                .get_element_by_id(&id)
+                .ok_or(JsValue::from_str(&format!(
+                    "should have {} on settingsPanel",
+                    id
+                )))
+        };
+        Ok(LeadInputs {
+            type_select: get_input("TypeSelect")?.dyn_into()?,
+            length_input: get_input("LengthInput")?.dyn_into()?,
+            angle_input: get_input("AngleInput")?.dyn_into()?,
+        })
+    }
+    fn set_lead(&self, lead: &Lead) {
+        self.type_select.set_value(lead.lead_type.get_name());
+        self.length_input.set_value(&lead.length.to_string());
+        self.angle_input.set_value(&lead.angle.to_string());
+    }
+    fn get_lead(&self) -> Option<Lead> {
+        Some(Lead {
+            lead_type: LeadType::from_name(&self.type_select.value())?,
+            length: self.length_input.value().parse::<f64>().ok()?.max(0.),
+            angle: self.angle_input.value().parse().ok()?,
+        })
+    }
+}
+
+// Inputs of the array panel, the pattern is shown while the panel is open
+struct ArrayInputs {
+    panel: HtmlElement,
+    form: HtmlElement,
+    type_select: HtmlSelectElement,
+    linear_fields: HtmlElement,
+    polar_fields: HtmlElement,
+    rows_input: HtmlInputElement,
+    columns_input: HtmlInputElement,
+    spacing_x_input: HtmlInputElement,
+    spacing_y_input: HtmlInputElement,
+    count_input: HtmlInputElement,
+    angle_input: HtmlInputElement,
+    center_x_input: HtmlInputElement,
+    center_y_input: HtmlInputElement,
+    rotate_input: HtmlInputElement,
+    apply_button: HtmlElement,
+    cancel_button: HtmlElement,
+}
+impl ArrayInputs {
+    fn new(document: &Document) -> Result<ArrayInputs, JsValue> {
+        let get_input = |id: &str| -> Result<Element, JsValue> {
+            document
+                .get_element_by_id(id)
+                .ok_or(JsValue::from_str(&format!(
+                    "should have {} on arrayPanel",
+                    id
+                )))
+        };
+        Ok(ArrayInputs {
+            panel: get_input("arrayPanel")?.dyn_into()?,
+            form: get_input("arraySettings")?.dyn_into()?,
+            type_select: get_input("arrayTypeSelect")?.dyn_into()?,
+            linear_fields: get_input("arrayLinearFields")?.dyn_into()?,
+            polar_fields: get_input("arrayPolarFields")?.dyn_into()?,
+            rows_input: get_input("arrayRowsInput")?.dyn_into()?,
+            columns_input: get_input("arrayColumnsInput")?.dyn_into()?,
+            spacing_x_input: get_input("arraySpacingXInput")?.dyn_into()?,
+            spacing_y_input: get_input("arraySpacingYInput")?.dyn_into()?,
+            count_input: get_input("arrayCountInput")?.dyn_into()?,
+            angle_input: get_input("arrayAngleInput")?.dyn_into()?,
+            center_x_input: get_input("arrayCenterXInput")?.dyn_into()?,
+            center_y_input: get_input("arrayCenterYInput")?.dyn_into()?,
+            rotate_input: get_input("arrayRotateInput")?.dyn_into()?,
+            apply_button: get_input("applyArray")?.dyn_into()?,
+            cancel_button: get_input("cancelArray")?.dyn_into()?,
+        })
+    }
+    fn is_open(&self) -> bool {
+        self.panel.style().get_property_value("display").ok() == Some("block".into())
+    }
+    // The center of the polar array starts where given
+    fn open(&self, center: &WPos) {
+        self.center_x_input.set_value(&center.wx.to_string());
+        self.center_y_input.set_value(&center.wy.to_string());
+        self.show_fields();
+        self.panel.style().set_property("display", "block").unwrap();
+    }
+    fn close(&self) {
+        self.panel.style().set_property("display", "none").unwrap();
+    }
+    // Only the inputs of the kind of array chosen are shown
+    fn show_fields(&self) {
+        let polar = self.type_select.value() == "polar";
+        let display = |shown: bool| if shown { "block" } else { "none" };
+        self.linear_fields
+            .style()
+            .set_property("display", display(!polar))
+            .unwrap();
+        self.polar_fields
+            .style()
+            .set_property("display", display(polar))
+            .unwrap();
+    }
+    // The angle is typed in degrees, counterclockwise on the screen
+    fn get_pattern(&self) -> Option<ArrayPattern> {
+        let get_count = |input: &HtmlInputElement| {
+            input
+                .value()
+                .parse::<usize>()
+                .ok()
+                .filter(|count| *count >= 1)
+        };
+        let get_value = |input: &HtmlInputElement| input.value().parse::<f64>().ok();
+        match self.type_select.value().as_str() {
+            "linear" => Some(ArrayPattern::Linear {
+                rows: get_count(&self.rows_input)?,
+                columns: get_count(&self.columns_input)?,
+                spacing: WPos::new(
+                    get_value(&self.spacing_x_input)?,
+                    get_value(&self.spacing_y_input)?,
+                ),
+            }),
+            "polar" => Some(ArrayPattern::Polar {
+                center: WPos::new(
+                    get_value(&self.center_x_input)?,
+                    get_value(&self.center_y_input)?,
+                ),
+                count: get_count(&self.count_input)?,
+                angle: -get_value(&self.angle_input)?.to_radians(),
+                rotate_items: self.rotate_input.checked(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Inputs of the panel shown with the fillet tool
+struct FilletInputs {
+    panel: HtmlElement,
+    type_select: HtmlSelectElement,
+    radius_input: HtmlInputElement,
+    distance_input: HtmlInputElement,
+    angle_input: HtmlInputElement,
+}
+impl FilletInputs {
+    fn new(document: &Document) -> Result<FilletInputs, JsValue> {
+        let get_input = |id: &str| -> Result<Element, JsValue> {
+            document
+                .get_element_by_id(id)
+                .ok_or(JsValue::from_str(&format!(
+                    "should have {} on filletPanel",
+                    id
+                )))
+        };
+        Ok(FilletInputs {
+            panel: get_input("filletPanel")?.dyn_into()?,
+            type_select: get_input("filletTypeSelect")?.dyn_into()?,
+            radius_input: get_input("filletRadiusInput")?.dyn_into()?,
+            distance_input: get_input("chamferDistanceInput")?.dyn_into()?,
+            angle_input: get_input("chamferAngleInput")?.dyn_into()?,
+        })
+    }
+    fn show(&self, shown: bool) {
+        let display = if shown { "block" } else { "none" };
+        self.panel.style().set_property("display", display).unwrap();
+    }
+    // The angle of the chamfer is typed in degrees
+    fn apply(
+        &self,
+        data_pools: &mut DataPools,
+        sh_id_a: &ShapeId,
+        sh_id_b: &ShapeId,
+    ) -> Result<(), String> {
+        let get_value = |input: &HtmlInputElement| {
+            input
+                .value()
+                .parse::<f64>()
+                .map_err(|_| format!("\"{}\" is not a number", input.value()))
+        };
+        match self.type_select.value().as_str() {
+            "chamfer" => data_pools.chamfer_shapes(
+                sh_id_a,
+                sh_id_b,
+                get_value(&self.distance_input)?,
+                get_value(&self.angle_input)?.to_radians(),
+            ),
+            _ => data_pools.fillet_shapes(sh_id_a, sh_id_b, get_value(&self.radius_input)?),
+        }
+    }
+}
+
+///////////////
+/// Array panel events
+fn on_apply_array_click(pa: RefArea, _event: Event) {
+    let mut pa_mut = pa.borrow_mut();
+    pa_mut.array_inputs.close();
+    if let Some(pattern) = pa_mut.array_inputs.get_pattern() {
+        if let Err(e) = pa_mut.data_pools.array_shapes_selected(&pattern) {
+            let _ = pa_mut.window.alert_with_message(&format!("Array: {}", e));
+        }
+    }
+    drop(pa_mut);
+    render(pa.clone());
+}
+fn on_cancel_array_click(pa: RefArea, _event: Event) {
+    pa.borrow().array_inputs.close();
+    render(pa.clone());
+}
 
 ///////////////
 /// Settings panel events
@@ -1291,28 +1813,117 @@ fn on_apply_settings_click(pa: RefArea, _event: Event) {
         wy: height,
     };
 
+    if let Ok(depth) = pa_ref.settings_undo_depth_input.value().parse::<usize>() {
+        pa_ref.data_pools.set_history_depth(depth.max(1));
+    }
+    if let Ok(kerf_width) = pa_ref.settings_kerf_width_input.value().parse::<f64>() {
+        pa_ref.cam_parameters.kerf_width = kerf_width.max(0.);
+    }
+    if let Some(lead_in) = pa_ref.settings_lead_in_inputs.get_lead() {
+        pa_ref.cam_parameters.lead_in = lead_in;
+    }
+    if let Some(lead_out) = pa_ref.settings_lead_out_inputs.get_lead() {
+        pa_ref.cam_parameters.lead_out = lead_out;
+    }
+
+    let dialect_name = pa_ref.settings_post_processor_select.value();
+    let template = pa_ref.settings_post_processor_template_input.value();
+    if let Some(dialect) = Dialect::from_name(&dialect_name, &template) {
+        if let Err(e) = PostProcessor::from_dialect(&dialect) {
+            let _ = pa_ref
+                .window
+                .alert_with_message(&format!("G-code post processor: {}", e));
+        }
+        pa_ref.cam_parameters.dialect = dialect;
+    }
+
     drop(pa_ref);
     resize_area(pa.clone());
     render(pa.clone());
 }
 fn on_modal_backdrop_click(pa: RefArea, _event: Event) {
-    let pa_ref = pa.borrow_mut();
+    let mut pa_ref = pa.borrow_mut();
     pa_ref
         .settings_panel
         .style()
         .set_property("display", "none")
         .unwrap();
+    pa_ref
+        .import_panel
+        .style()
+        .set_property("display", "none")
+        .unwrap();
+    pa_ref.file_to_import = None;
     pa_ref
         .modal_backdrop
         .style()
         .set_property("display", "none")
         .unwrap();
+    set_settings_inputs(&pa_ref);
+}
+
+///////////////
+/// Import panel events
+fn on_apply_import_click(pa: RefArea, _event: Event) {
+    let mut pa_ref = pa.borrow_mut();
+    pa_ref
+        .import_panel
+        .style()
+        .set_property("display", "none")
+        .unwrap();
+    pa_ref
+        .modal_backdrop
+        .style()
+        .set_property("display", "none")
+        .unwrap();
+    let Some(file) = pa_ref.file_to_import.take() else {
+        return;
+    };
+    let options = ImportOptions {
+        scale: pa_ref
+            .import_scale_input
+            .value()
+            .parse::<f64>()
+            .ok()
+            .filter(|scale| *scale > 0.)
+            .unwrap_or(1.),
+        flip_y: pa_ref.import_flip_y_input.checked(),
+        origin: ImportOrigin::from_name(&pa_ref.import_origin_select.value())
+            .unwrap_or(ImportOrigin::Document),
+        working_area: pa_ref.working_area,
+    };
+    drop(pa_ref);
+    import_file(pa.clone(), file, &options);
+    render(pa.clone());
+}
+// Show the current settings in the settings panel
+fn set_settings_inputs(pa_ref: &PlayingArea) {
     pa_ref
         .settings_width_input
         .set_value(&pa_ref.working_area.wx.to_string());
     pa_ref
         .settings_height_input
         .set_value(&pa_ref.working_area.wy.to_string());
+    pa_ref
+        .settings_kerf_width_input
+        .set_value(&pa_ref.cam_parameters.kerf_width.to_string());
+    pa_ref
+        .settings_undo_depth_input
+        .set_value(&pa_ref.data_pools.get_history_depth().to_string());
+    pa_ref
+        .settings_lead_in_inputs
+        .set_lead(&pa_ref.cam_parameters.lead_in);
+    pa_ref
+        .settings_lead_out_inputs
+        .set_lead(&pa_ref.cam_parameters.lead_out);
+    pa_ref
+        .settings_post_processor_select
+        .set_value(pa_ref.cam_parameters.dialect.get_name());
+    if let Dialect::Custom(template) = &pa_ref.cam_parameters.dialect {
+        pa_ref
+            .settings_post_processor_template_input
+            .set_value(template);
+    }
 }
 
 ///////////////
@@ -1417,10 +2028,24 @@ fn on_icon_click(pa: RefArea, event: Event) {
                             .style()
                             .set_property("display", "block")
                             .unwrap();
+                    } else if key == &"icon-array" {
+                        // The tool in use is kept, the panel only opens on a
+                        // selection
+                        if let Some(bb) = pa_mut.data_pools.get_shapes_selected_bounded_rectangle()
+                        {
+                            let center = get_transform_pivot(&pa_mut, &bb);
+                            pa_mut.array_inputs.open(&center);
+                            drop(pa_mut);
+                            render(pa.clone());
+                            return;
+                        }
                     } else {
                         pa_mut.icon_selected = key;
                         deselect_icons(&pa_mut);
                         select_icon(&pa_mut, &id);
+                        pa_mut.transform_pivot = None;
+                        let fillet_selected = pa_mut.icon_selected == "icon-fillet";
+                        pa_mut.fillet_inputs.show(fillet_selected);
                     }
                     match pa_mut.icon_selected {
                         "icon-line" | "icon-quadbezier" | "icon-cubicbezier" | "icon-ellipse"
@@ -1474,6 +2099,98 @@ fn go_to_arrow_tool(pa_ref: &mut RefMut<'_, PlayingArea>) {
     deselect_icons(&pa_ref);
     select_icon(&pa_ref, "icon-arrow");
 }
+// Picked with the transformation tool, else the center of the selection
+fn get_transform_pivot(pa_ref: &PlayingArea, bb: &[WPos; 2]) -> WPos {
+    match pa_ref.transform_pivot {
+        Some(pivot) if pa_ref.icon_selected == "icon-transform" => pivot,
+        _ => (bb[0] + bb[1]) / 2.,
+    }
+}
+// Scaling handles on the bounding box of the selection and the rotation one
+// above it
+fn get_transform_handles(bb: &[WPos; 2], size_handle: f64) -> Vec<(TransformHandle, WPos)> {
+    let center = (bb[0] + bb[1]) / 2.;
+    let mut handles = vec![];
+    for wx in [bb[0].wx, center.wx, bb[1].wx] {
+        for wy in [bb[0].wy, center.wy, bb[1].wy] {
+            let pos = WPos::new(wx, wy);
+            if pos != center {
+                handles.push((TransformHandle::Scale(pos), pos));
+            }
+        }
+    }
+    handles.push((
+        TransformHandle::Rotate,
+        WPos::new(center.wx, bb[0].wy - 4. * size_handle),
+    ));
+    handles
+}
+fn get_transform_handle(pa_ref: &PlayingArea, pick_pos: &WPos) -> Option<TransformHandle> {
+    let bb = pa_ref.data_pools.get_shapes_selected_bounded_rectangle()?;
+    let precision = pa_ref.grab_handle_precision;
+    if pick_pos.dist(&get_transform_pivot(pa_ref, &bb)) < precision {
+        return Some(TransformHandle::Pivot);
+    }
+    get_transform_handles(&bb, pa_ref.size_handle)
+        .into_iter()
+        .find(|(_, pos)| pick_pos.dist(pos) < precision)
+        .map(|(handle, _)| handle)
+}
+// Transformation given by the handle dragged, None while the mouse hasn't
+// moved enough
+fn get_drag_transform(pa_ref: &PlayingArea) -> Option<Transform> {
+    let bb = pa_ref.data_pools.get_shapes_selected_bounded_rectangle()?;
+    let pick_pos = pa_ref.pick_pos;
+    let pick_pos_ms_dwn = pa_ref.pick_pos_ms_dwn;
+    if pick_pos.dist(&pick_pos_ms_dwn) < pa_ref.working_area_snap_grid / 2. {
+        return None;
+    }
+    let shift_pressed = pa_ref.keys_states.shift_pressed;
+    match pa_ref.transform_handle? {
+        TransformHandle::Scale(handle_pos) => {
+            let anchor = bb[0] + bb[1] - handle_pos;
+            let get_factor = |pick: f64, handle: f64, anchor: f64| {
+                if (handle - anchor).abs() > MIN_SCALE {
+                    (pick - anchor) / (handle - anchor)
+                } else {
+                    1.
+                }
+            };
+            let mut sx = get_factor(pick_pos.wx, handle_pos.wx, anchor.wx);
+            let mut sy = get_factor(pick_pos.wy, handle_pos.wy, anchor.wy);
+            let center = (bb[0] + bb[1]) / 2.;
+            if handle_pos.wx == center.wx {
+                sx = 1.;
+            }
+            if handle_pos.wy == center.wy {
+                sy = 1.;
+            }
+            // The corners keep the proportions with Shift
+            if shift_pressed && handle_pos.wx != center.wx && handle_pos.wy != center.wy {
+                let s = if sx.abs() > sy.abs() { sx } else { sy };
+                (sx, sy) = (s, s);
+            }
+            if sx.abs() < MIN_SCALE || sy.abs() < MIN_SCALE {
+                return None;
+            }
+            Some(Transform::scale(sx, sy).around(&anchor))
+        }
+        TransformHandle::Rotate => {
+            let pivot = get_transform_pivot(pa_ref, &bb);
+            let from = pick_pos_ms_dwn - pivot;
+            let to = pick_pos - pivot;
+            let mut angle = to.wy.atan2(to.wx) - from.wy.atan2(from.wx);
+            if shift_pressed {
+                angle = (angle / ROTATION_STEP).round() * ROTATION_STEP;
+            }
+            Some(Transform::rotate(angle).around(&pivot))
+        }
+        TransformHandle::Pivot => None,
+        TransformHandle::Mirror => {
+            Some(Transform::mirror(&(pick_pos - pick_pos_ms_dwn)).around(&pick_pos_ms_dwn))
+        }
+    }
+}
 fn select_icon(pa_ref: &RefMut<'_, PlayingArea>, name: &str) {
     if let Some(element) = pa_ref.user_icons.get(name).unwrap().clone() {
         if let Ok(html_element) = element.dyn_into::<HtmlElement>() {
@@ -1495,6 +2212,21 @@ fn deselect_icons(pa_ref: &RefMut<'_, PlayingArea>) {
         }
     }
 }
+// Let the browser save a text content as a file
+fn download_file(document: &Document, file_name: &str, content: &str) -> Result<(), JsValue> {
+    let parts = Array::new();
+    parts.push(&JsValue::from_str(content));
+    let mut blob_properties = BlobPropertyBag::new();
+    blob_properties.type_("text/plain");
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &blob_properties)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    Url::revoke_object_url(&url)
+}
 fn get_element(document: &Document, element_id: &str) -> Result<Element, JsValue> {
     let element = document
         .get_element_by_id(element_id)
@@ -1517,10 +2249,16 @@ fn render(pa: RefArea) {
     draw_all(pa.clone());
 }
 fn draw_all(pa: RefArea) {
+    update_cam_preview(pa.clone());
     draw_grid(pa.clone());
     draw_working_area(pa.clone());
     draw_content(pa.clone());
+    draw_contours(pa.clone());
+    draw_toolpaths(pa.clone());
+    draw_backplot(pa.clone());
     draw_selection_area(pa.clone());
+    draw_transform_tool(pa.clone());
+    draw_array_preview(pa.clone());
 }
 fn draw_working_area(pa: RefArea) {
     use ConstructionType::*;
@@ -1617,6 +2355,141 @@ fn draw_content(pa: RefArea) {
         raw_draw(&pa_ref, &cst);
     }
 }
+// Contours of the selected shapes are highlighted, the free ends of the open
+// contours are always shown as they can't be cut with leads nor compensated
+fn draw_contours(pa: RefArea) {
+    use ConstructionType::*;
+    let pa_ref = pa.borrow();
+    let data_pools = &pa_ref.data_pools;
+    let shapes_pool = data_pools.get_all_shapes();
+    let shapes_selected = data_pools.get_shapes_selected();
+    let Some(cam_preview) = pa_ref.cam_preview.as_ref() else {
+        return;
+    };
+
+    for contour in cam_preview.shape_contours.iter() {
+        let mut cst = vec![Layer(LayerType::Contour(contour.closed))];
+        if contour
+            .get_shape_ids()
+            .iter()
+            .any(|sh_id| shapes_selected.contains(sh_id))
+        {
+            for sh_id in contour.get_shape_ids().iter() {
+                cst.extend(
+                    shapes_pool[sh_id]
+                        .get_construction()
+                        .into_iter()
+                        .filter(|prim| !matches!(prim, Layer(_))),
+                );
+            }
+        }
+        if !contour.closed {
+            let (first_sh_id, first_reversed) = contour.links[0];
+            let (last_sh_id, last_reversed) = contour.links[contour.links.len() - 1];
+            let ends = [
+                shapes_pool[&first_sh_id].get_pos_from_ratio(if first_reversed { 1. } else { 0. }),
+                shapes_pool[&last_sh_id].get_pos_from_ratio(if last_reversed { 0. } else { 1. }),
+            ];
+            for end in ends.iter() {
+                let point = Point::new(end, false, false, false);
+                push_handle(&mut cst, &point, pa_ref.size_handle);
+            }
+        }
+        raw_draw(&pa_ref, &cst);
+    }
+}
+fn update_cam_preview(pa: RefArea) {
+    let mut pa_mut = pa.borrow_mut();
+    let revision = pa_mut.data_pools.get_revision();
+    if pa_mut.cam_preview.as_ref().map_or(false, |cam_preview| {
+        cam_preview.revision == revision
+            && cam_preview.cam_parameters == pa_mut.cam_parameters
+            && cam_preview.working_area == pa_mut.working_area
+    }) {
+        return;
+    }
+    let cam_parameters = pa_mut.cam_parameters.clone();
+    let working_area = pa_mut.working_area;
+    let shape_contours = pa_mut.data_pools.get_contours(cam_parameters.tolerance);
+    let contours = get_cut_contours_from_contours(
+        pa_mut.data_pools.get_all_shapes(),
+        &shape_contours,
+        &cam_parameters,
+    );
+    let leads: Vec<Vec<WPos>> = contours
+        .iter()
+        .flat_map(|contour| {
+            let lead_in = get_lead_in(contour, &cam_parameters.lead_in);
+            let lead_out = get_lead_out(contour, &cam_parameters.lead_out);
+            lead_in.into_iter().chain(lead_out)
+        })
+        .map(|lead| lead.get_polyline(cam_parameters.tolerance))
+        .collect();
+    let toolpaths: Vec<Toolpath> = contours
+        .iter()
+        .map(|contour| get_leaded_toolpath(contour, &cam_parameters))
+        .collect();
+    let order = get_cut_order(
+        &contours,
+        &toolpaths,
+        &get_home(&working_area),
+        cam_parameters.tolerance,
+    );
+    pa_mut.cam_preview = Some(CamPreview {
+        revision,
+        cam_parameters,
+        working_area,
+        shape_contours,
+        leads,
+        toolpaths,
+        order,
+    });
+}
+// Leads and numbered rapid moves, in cutting order
+fn draw_toolpaths(pa: RefArea) {
+    use ConstructionType::*;
+    let pa_ref = pa.borrow();
+    let Some(cam_preview) = pa_ref.cam_preview.as_ref() else {
+        return;
+    };
+
+    let mut cst = vec![Layer(LayerType::Lead)];
+    for polyline in cam_preview.leads.iter() {
+        cst.push(Move(polyline[0]));
+        for pos in polyline.iter().skip(1) {
+            cst.push(Line(*pos));
+        }
+    }
+    raw_draw(&pa_ref, &cst);
+
+    let toolpaths = &cam_preview.toolpaths;
+    let mut cst = vec![Layer(LayerType::Rapid)];
+    let mut pos = get_home(&cam_preview.working_area);
+    for (nb, idx) in cam_preview.order.iter().enumerate() {
+        let start = toolpaths[*idx].start;
+        cst.push(Move(pos));
+        cst.push(Line(start));
+        cst.push(Text((pos + start) / 2., (nb + 1).to_string()));
+        pos = toolpaths[*idx].get_end();
+    }
+    raw_draw(&pa_ref, &cst);
+}
+// Moves of the G-code program opened, the rapids dashed
+fn draw_backplot(pa: RefArea) {
+    use ConstructionType::*;
+    let pa_ref = pa.borrow();
+    for cut in [false, true] {
+        let mut cst = vec![Layer(LayerType::Backplot(cut))];
+        for bp_move in pa_ref.backplot.iter().filter(|bp_move| bp_move.cut == cut) {
+            let polyline = bp_move.get_polyline(pa_ref.cam_parameters.tolerance);
+            cst.push(Move(polyline[0]));
+            for pos in polyline.iter().skip(1) {
+                cst.push(Line(*pos));
+            }
+        }
+        raw_draw(&pa_ref, &cst);
+    }
+}
 fn draw_selection_area(pa: RefArea) {
     use ConstructionType::*;
     let pa_ref = pa.borrow();
@@ -1651,6 +2524,92 @@ fn draw_selection_area(pa: RefArea) {
         }
     }
 }
+// Bounding box of the selection with the handles of the transformation tool,
+// and the selection transformed while a handle is dragged
+fn draw_transform_tool(pa: RefArea) {
+    use ConstructionType::*;
+    let pa_ref = pa.borrow();
+    if pa_ref.icon_selected != "icon-transform" {
+        return;
+    }
+    let Some(bb) = pa_ref.data_pools.get_shapes_selected_bounded_rectangle() else {
+        return;
+    };
+    let size_handle = pa_ref.size_handle;
+
+    let mut cst = vec![Layer(LayerType::SelectionTool)];
+    cst.push(Rectangle(bb[0], bb[1] - bb[0], false));
+    let center = (bb[0] + bb[1]) / 2.;
+    cst.push(Move(WPos::new(center.wx, bb[0].wy)));
+    cst.push(Line(WPos::new(center.wx, bb[0].wy - 4. * size_handle)));
+    for (_, pos) in get_transform_handles(&bb, size_handle) {
+        push_handle(
+            &mut cst,
+            &Point::new(&pos, false, false, false),
+            size_handle,
+        );
+    }
+    // The pivot is a cross
+    let pivot = get_transform_pivot(&pa_ref, &bb);
+    cst.push(Move(pivot.addxy(-size_handle, 0.)));
+    cst.push(Line(pivot.addxy(size_handle, 0.)));
+    cst.push(Move(pivot.addxy(0., -size_handle)));
+    cst.push(Line(pivot.addxy(0., size_handle)));
+    raw_draw(&pa_ref, &cst);
+
+    if let Some(transform) = get_drag_transform(&pa_ref) {
+        let mut cst = vec![Layer(LayerType::GeometryHelpers)];
+        for shape in pa_ref
+            .data_pools
+            .get_shapes_selected_transformed(&transform)
+            .iter()
+        {
+            cst.extend(
+                shape
+                    .get_construction()
+                    .into_iter()
+                    .filter(|prim| !matches!(prim, Layer(_))),
+            );
+        }
+        if let Some(TransformHandle::Mirror) = pa_ref.transform_handle {
+            cst.push(Move(pa_ref.pick_pos_ms_dwn));
+            cst.push(Line(pa_ref.pick_pos));
+        }
+        raw_draw(&pa_ref, &cst);
+    }
+}
+fn draw_array_preview(pa: RefArea) {
+    use ConstructionType::*;
+    let pa_ref = pa.borrow();
+    if !pa_ref.array_inputs.is_open() {
+        return;
+    }
+    let Some(pattern) = pa_ref.array_inputs.get_pattern() else {
+        return;
+    };
+    let mut cst = vec![Layer(LayerType::GeometryHelpers)];
+    for shape in pa_ref
+        .data_pools
+        .get_shapes_selected_arrayed(&pattern)
+        .iter()
+    {
+        cst.extend(
+            shape
+                .get_construction()
+                .into_iter()
+                .filter(|prim| !matches!(prim, Layer(_))),
+        );
+    }
+    // The center of a polar array is a cross
+    if let ArrayPattern::Polar { center, .. } = pattern {
+        let size_handle = pa_ref.size_handle;
+        cst.push(Move(center.addxy(-size_handle, 0.)));
+        cst.push(Line(center.addxy(size_handle, 0.)));
+        cst.push(Move(center.addxy(0., -size_handle)));
+        cst.push(Line(center.addxy(0., size_handle)));
+    }
+    raw_draw(&pa_ref, &cst);
+}
 fn raw_draw(pa_ref: &Ref<'_, PlayingArea>, cst: &Vec<ConstructionType>) {
     let p = Path2d::new().unwrap();
     let scale = pa_ref.global_scale;
@@ -1715,6 +2674,42 @@ fn raw_draw(pa_ref: &Ref<'_, PlayingArea>, cst: &Vec<ConstructionType>) {
                         &pa_ref.pattern_solid,
                         1.,
                     ),
+                    Lead => (
+                        &pa_ref.fill_color,
+                        &pa_ref.lead_color,
+                        &pa_ref.pattern_solid,
+                        1.,
+                    ),
+                    Rapid => (
+                        &pa_ref.fill_color,
+                        &pa_ref.rapid_color,
+                        &pa_ref.pattern_dashed,
+                        1.,
+                    ),
+                    Contour(closed) => (
+                        &pa_ref.fill_color,
+                        if *closed {
+                            &pa_ref.closed_contour_color
+                        } else {
+                            &pa_ref.open_contour_color
+                        },
+                        &pa_ref.pattern_solid,
+                        3.,
+                    ),
+                    Backplot(cut) => (
+                        &pa_ref.fill_color,
+                        if *cut {
+                            &pa_ref.backplot_cut_color
+                        } else {
+                            &pa_ref.backplot_rapid_color
+                        },
+                        if *cut {
+                            &pa_ref.pattern_solid
+                        } else {
+                            &pa_ref.pattern_dashed
+                        },
+                        if *cut { 2. } else { 1. },
+                    ),
                 };
                 pa_ref.ctx.set_line_dash(line_dash).unwrap();
                 pa_ref.ctx.set_line_width(line_width);
@@ -0,0 +1,342 @@
+use crate::math::{cross, get_sweep_angle, perp, EPSILON, MAX_ITERATIONS};
+use crate::shapes::ellipse::Ellipse;
+use crate::shapes::line::Line;
+use crate::shapes::types::{Shape, WPos};
+use std::f64::consts::PI;
+
+// Under which the Newton iterations of a fillet are considered converged
+const FILLET_PRECISION: f64 = 1e-9;
+// Samples looking for where a chamfer meets the second shape
+const NB_SAMPLES: usize = 64;
+
+// What replaces two shapes meeting at a point once their corner is rounded
+// or cut: the shapes trimmed, gone if fully eaten, and the joint between them
+pub struct Corner {
+    pub shape_a: Option<Box<dyn Shape>>,
+    pub shape_b: Option<Box<dyn Shape>>,
+    pub joint: Box<dyn Shape>,
+}
+
+// A shape seen from the corner, u going from 0 at the corner to 1 at its
+// other end
+struct Branch<'a> {
+    shape: &'a dyn Shape,
+    at_start: bool,
+}
+impl Branch<'_> {
+    fn get_ratio(&self, u: f64) -> f64 {
+        if self.at_start {
+            u
+        } else {
+            1. - u
+        }
+    }
+    fn get_pos(&self, u: f64) -> WPos {
+        self.shape.get_pos_from_ratio(self.get_ratio(u))
+    }
+    // Unit tangent going away from the corner
+    fn get_tangent(&self, u: f64) -> WPos {
+        let h = 1e-6;
+        let (u1, u2) = ((u - h).max(0.), (u + h).min(1.));
+        let dir = self.get_pos(u2) - self.get_pos(u1);
+        dir / dir.norm()
+    }
+    // First position at the distance from the corner, as the crow flies
+    fn get_u_at_dist(&self, corner: &WPos, distance: f64) -> Option<f64> {
+        let (mut u_min, mut u_max) = (0., 1.);
+        if self.get_pos(u_max).dist(corner) < distance {
+            return None;
+        }
+        for _i in 0..MAX_ITERATIONS {
+            let u = (u_min + u_max) / 2.;
+            if self.get_pos(u).dist(corner) < distance {
+                u_min = u;
+            } else {
+                u_max = u;
+            }
+        }
+        Some((u_min + u_max) / 2.)
+    }
+    // The piece left once cut at pos, on the far side of the corner. None
+    // when cut at its far end, a shape which can't be split is an error.
+    fn get_trimmed(&self, pos: &WPos) -> Result<Option<Box<dyn Shape>>, String> {
+        let (first, second) = self.shape.split(pos);
+        match if self.at_start { second } else { first } {
+            Some(mut piece) => {
+                piece.init_done();
+                Ok(Some(piece))
+            }
+            None if self.get_pos(1.).dist(pos) < EPSILON => Ok(None),
+            None => Err(format!("A {} can't be trimmed", self.shape.get_type_name())),
+        }
+    }
+}
+
+// The shapes are rounded with an arc of the radius tangent to both
+pub fn get_fillet(shape_a: &dyn Shape, shape_b: &dyn Shape, radius: f64) -> Result<Corner, String> {
+    if radius <= 0. {
+        return Err("The radius must be positive".into());
+    }
+    let (corner, branch_a, branch_b) = get_branches(shape_a, shape_b)?;
+    let (dir_a, dir_b) = (branch_a.get_tangent(0.), branch_b.get_tangent(0.));
+    if cross(&dir_a, &dir_b).abs() < FILLET_PRECISION {
+        return Err("The shapes are tangent at their common point".into());
+    }
+    // Sides of the normals turned towards the inside of the corner
+    let side_a = perp(&dir_a).dot(&dir_b).signum();
+    let side_b = perp(&dir_b).dot(&dir_a).signum();
+    let get_center = |branch: &Branch, side: f64, u: f64| {
+        branch.get_pos(u) + perp(&branch.get_tangent(u)) * (side * radius)
+    };
+    let get_gap =
+        |u: f64, v: f64| get_center(&branch_a, side_a, u) - get_center(&branch_b, side_b, v);
+
+    // Starting from the fillet of the tangents at the corner, the centers
+    // seen from both shapes are brought together by Newton iterations
+    let half_angle = dir_a.dot(&dir_b).clamp(-1., 1.).acos() / 2.;
+    let trim_distance = radius / half_angle.tan();
+    let too_small = || "The shapes are too short for this radius".to_string();
+    let mut u = branch_a
+        .get_u_at_dist(&corner, trim_distance)
+        .ok_or_else(too_small)?;
+    let mut v = branch_b
+        .get_u_at_dist(&corner, trim_distance)
+        .ok_or_else(too_small)?;
+    let h = 1e-7;
+    let mut gap = get_gap(u, v);
+    for _i in 0..MAX_ITERATIONS {
+        if gap.norm() < FILLET_PRECISION {
+            break;
+        }
+        let du = (get_gap((u + h).min(1.), v) - get_gap((u - h).max(0.), v)) / (2. * h);
+        let dv = (get_gap(u, (v + h).min(1.)) - get_gap(u, (v - h).max(0.))) / (2. * h);
+        let det = cross(&du, &dv);
+        if det == 0. {
+            break;
+        }
+        u = (u - cross(&gap, &dv) / det).clamp(0., 1.);
+        v = (v - cross(&du, &gap) / det).clamp(0., 1.);
+        gap = get_gap(u, v);
+    }
+    if gap.norm() > EPSILON {
+        return Err(too_small());
+    }
+
+    let (pos_a, pos_b) = (branch_a.get_pos(u), branch_b.get_pos(v));
+    let center = get_center(&branch_a, side_a, u);
+    let angle_a = (pos_a - center).wy.atan2((pos_a - center).wx);
+    let angle_b = (pos_b - center).wy.atan2((pos_b - center).wx);
+    // The ellipse is drawn with increasing angles, the short way round
+    let (start_angle, end_angle) = if get_sweep_angle(angle_a, angle_b) <= PI {
+        (angle_a, angle_b)
+    } else {
+        (angle_b, angle_a)
+    };
+    let arc = Ellipse::from_arc(
+        &center,
+        &WPos::new(radius, radius),
+        0.,
+        start_angle,
+        end_angle,
+    )
+    .ok_or_else(too_small)?;
+    Ok(Corner {
+        shape_a: branch_a.get_trimmed(&pos_a)?,
+        shape_b: branch_b.get_trimmed(&pos_b)?,
+        joint: Box::new(arc),
+    })
+}
+
+// The corner is cut by a line starting at the distance from the corner on
+// the first shape, making the angle with the line from there to the corner
+pub fn get_chamfer(
+    shape_a: &dyn Shape,
+    shape_b: &dyn Shape,
+    distance: f64,
+    angle: f64,
+) -> Result<Corner, String> {
+    if distance <= 0. || angle <= 0. || angle >= PI {
+        return Err("The distance must be positive and the angle between 0 and 180 degrees".into());
+    }
+    let (corner, branch_a, branch_b) = get_branches(shape_a, shape_b)?;
+    let u = branch_a
+        .get_u_at_dist(&corner, distance)
+        .ok_or("The first shape is too short for this distance")?;
+    let pos_a = branch_a.get_pos(u);
+    let to_corner = (corner - pos_a) / distance;
+    // Turned towards the second shape
+    let side = perp(&to_corner).dot(&branch_b.get_tangent(0.)).signum();
+    let (sin, cos) = angle.sin_cos();
+    let dir = to_corner * cos + perp(&to_corner) * (side * sin);
+
+    // First crossing of the second shape by the chamfer, in front of it
+    let get_side = |v: f64| cross(&dir, &(branch_b.get_pos(v) - pos_a));
+    let mut crossing = None;
+    for i in 0..NB_SAMPLES {
+        let (v1, v2) = (
+            i as f64 / NB_SAMPLES as f64,
+            (i + 1) as f64 / NB_SAMPLES as f64,
+        );
+        if get_side(v1) * get_side(v2) <= 0. {
+            let (mut v_min, mut v_max) = (v1, v2);
+            for _i in 0..MAX_ITERATIONS {
+                let v = (v_min + v_max) / 2.;
+                if get_side(v_min) * get_side(v) <= 0. {
+                    v_max = v;
+                } else {
+                    v_min = v;
+                }
+            }
+            let pos_b = branch_b.get_pos((v_min + v_max) / 2.);
+            if dir.dot(&(pos_b - pos_a)) > 0. {
+                crossing = Some(pos_b);
+                break;
+            }
+        }
+    }
+    let pos_b = crossing.ok_or("The chamfer doesn't reach the second shape")?;
+    let line = Line::new(&pos_a, &pos_b).ok_or("The chamfer is too small")?;
+    Ok(Corner {
+        shape_a: branch_a.get_trimmed(&pos_a)?,
+        shape_b: branch_b.get_trimmed(&pos_b)?,
+        joint: Box::new(line),
+    })
+}
+
+// The shapes must share an end, the closest ends are taken. A closed shape
+// has no end, the point where it starts is no corner.
+fn get_branches<'a>(
+    shape_a: &'a dyn Shape,
+    shape_b: &'a dyn Shape,
+) -> Result<(WPos, Branch<'a>, Branch<'a>), String> {
+    if shape_a.is_closed() || shape_b.is_closed() {
+        return Err("A closed shape has no corner to round or cut".into());
+    }
+    let mut best: Option<(f64, bool, bool)> = None;
+    for at_start_a in [true, false] {
+        for at_start_b in [true, false] {
+            let pos_a = shape_a.get_pos_from_ratio(if at_start_a { 0. } else { 1. });
+            let pos_b = shape_b.get_pos_from_ratio(if at_start_b { 0. } else { 1. });
+            let dist = pos_a.dist(&pos_b);
+            if dist < EPSILON && best.map_or(true, |(best_dist, _, _)| dist < best_dist) {
+                best = Some((dist, at_start_a, at_start_b));
+            }
+        }
+    }
+    let (_, at_start_a, at_start_b) = best.ok_or("The shapes don't meet at their ends")?;
+    let branch_a = Branch {
+        shape: shape_a,
+        at_start: at_start_a,
+    };
+    let branch_b = Branch {
+        shape: shape_b,
+        at_start: at_start_b,
+    };
+    Ok((branch_a.get_pos(0.), branch_a, branch_b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::quadbezier::QuadBezier;
+    use crate::shapes::rectangle::Rectangle;
+
+    fn get_line(start: (f64, f64), end: (f64, f64)) -> Line {
+        Line::new(&WPos::new(start.0, start.1), &WPos::new(end.0, end.1)).unwrap()
+    }
+
+    #[test]
+    fn test_fillet() {
+        // Square corner at the origin, the lines going in opposite ways
+        let line_a = get_line((50., 0.), (0., 0.));
+        let line_b = get_line((0., 0.), (0., 50.));
+        let corner = get_fillet(&line_a, &line_b, 10.).unwrap();
+        let shape_a = corner.shape_a.unwrap();
+        let shape_b = corner.shape_b.unwrap();
+        assert!(shape_a.get_pos_from_ratio(1.).dist(&WPos::new(10., 0.)) < 1e-6);
+        assert!(shape_b.get_pos_from_ratio(0.).dist(&WPos::new(0., 10.)) < 1e-6);
+        let arc = corner.joint;
+        assert_eq!(arc.get_type_name(), "ellipse");
+        let middle = WPos::new(10. - 50_f64.sqrt(), 10. - 50_f64.sqrt());
+        assert!(arc.get_pos_from_ratio(0.5).dist(&middle) < 1e-6);
+
+        // Against a quarter of circle, the center of the fillet is at the
+        // sum of the radii from the center of the circle
+        let arc =
+            Ellipse::from_arc(&WPos::new(0., 20.), &WPos::new(20., 20.), 0., PI / 2., PI).unwrap();
+        let line = get_line((-20., 20.), (-60., 20.));
+        let corner = get_fillet(&line, &arc, 5.).unwrap();
+        let center = WPos::new(-600_f64.sqrt(), 25.);
+        assert!((corner.joint.dist(&center) - 5.).abs() < 1e-6);
+        let pos = corner.shape_b.unwrap().get_pos_from_ratio(1.);
+        assert!((pos.dist(&WPos::new(0., 20.)) - 20.).abs() < 1e-6);
+        assert!(corner.joint.dist(&pos) < 1e-6);
+        let pos = corner.shape_a.unwrap().get_pos_from_ratio(0.);
+        assert!(pos.dist(&WPos::new(center.wx, 20.)) < 1e-6);
+        assert!(corner.joint.dist(&pos) < 1e-6);
+
+        assert!(get_fillet(&line_a, &line_b, 60.).is_err());
+        assert!(get_fillet(&line_a, &get_line((10., 10.), (20., 20.)), 5.).is_err());
+    }
+
+    #[test]
+    fn test_fillet_with_a_quadbezier() {
+        // The curve leaves the end of the line straight up
+        let line = get_line((-50., 0.), (0., 0.));
+        let bezier =
+            QuadBezier::new(&WPos::zero(), &WPos::new(0., 40.), &WPos::new(40., 40.)).unwrap();
+        let corner = get_fillet(&line, &bezier, 5.).unwrap();
+        let shape_a = corner.shape_a.unwrap();
+        let shape_b = corner.shape_b.unwrap();
+        assert_eq!(shape_b.get_type_name(), "quadbezier");
+        // Both trimmed shapes end on the fillet, the curve keeping its far end
+        let pos_a = shape_a.get_pos_from_ratio(1.);
+        let pos_b = shape_b.get_pos_from_ratio(0.);
+        assert!(corner.joint.dist(&pos_a) < 1e-6);
+        assert!(corner.joint.dist(&pos_b) < 1e-6);
+        assert!(bezier.dist(&pos_b) < 1e-6);
+        assert!(pos_b.dist(&WPos::zero()) > 1.);
+        assert!(shape_b.get_pos_from_ratio(1.).dist(&WPos::new(40., 40.)) < 1e-6);
+    }
+
+    #[test]
+    fn test_chamfer() {
+        let line_a = get_line((0., 0.), (50., 0.));
+        let line_b = get_line((0., 50.), (0., 0.));
+        let corner = get_chamfer(&line_a, &line_b, 10., PI / 4.).unwrap();
+        assert!(
+            corner
+                .joint
+                .get_pos_from_ratio(0.)
+                .dist(&WPos::new(10., 0.))
+                < 1e-6
+        );
+        assert!(
+            corner
+                .joint
+                .get_pos_from_ratio(1.)
+                .dist(&WPos::new(0., 10.))
+                < 1e-6
+        );
+        let shape_b = corner.shape_b.unwrap();
+        assert!(shape_b.get_pos_from_ratio(1.).dist(&WPos::new(0., 10.)) < 1e-6);
+
+        // A steeper chamfer goes further on the second shape
+        let corner = get_chamfer(&line_a, &line_b, 10., PI / 3.).unwrap();
+        let end = WPos::new(0., 10. * 3_f64.sqrt());
+        assert!(corner.joint.get_pos_from_ratio(1.).dist(&end) < 1e-6);
+    }
+
+    #[test]
+    fn test_closed_shapes() {
+        // The line ends where the rectangle and the circle start
+        let line = get_line((-30., 50.), (0., 50.));
+        let rectangle = Rectangle::new(&WPos::zero(), 100., 50.).unwrap();
+        assert!(get_fillet(&line, &rectangle, 5.).is_err());
+        assert!(get_chamfer(&rectangle, &line, 5., PI / 4.).is_err());
+        let circle = Ellipse::new(&WPos::new(20., 50.), &WPos::new(40., 70.), PI, PI, 1.);
+        assert!(circle.get_pos_from_ratio(0.).dist(&WPos::new(0., 50.)) < 1e-6);
+        assert!(get_fillet(&line, &circle, 5.).is_err());
+        assert!(get_chamfer(&line, &circle, 5., PI / 4.).is_err());
+    }
+}
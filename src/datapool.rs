@@ -2,10 +2,16 @@
 // #[cfg(not(test))]
 macro_rules! log {
     ( $( $t:tt )* ) => {
+        // The console is the browser one, it isn't there for native tests
+        #[cfg(target_arch = "wasm32")]
         web_sys::console::log_1(&format!( $( $t )* ).into());
     }
 }
 
+use crate::array::ArrayPattern;
+use crate::corner::{get_chamfer, get_fillet, Corner};
+use crate::history::{History, ShapeState};
+use crate::intersection::{get_shapes_intersections, Intersection};
 use crate::math::*;
 use crate::shapes::types::{GroupId, Shape, ShapeId, WPos};
 use std::collections::HashMap;
@@ -18,6 +24,12 @@ pub struct DataPools {
     shapes_pool: ShapesPool,
     groups_pool: GroupsPool,
     shapes_selected: HashSet<ShapeId>,
+    history: History,
+    // Shapes copied, with the groups they were in
+    clipboard: Vec<(Box<dyn Shape>, Vec<GroupId>)>,
+    // Counts the edits, what is worked out of the drawing is up to date as
+    // long as it doesn't change
+    revision: usize,
 }
 impl DataPools {
     pub fn new() -> DataPools {
@@ -27,6 +39,111 @@ impl DataPools {
             groups_pool: GroupsPool::new(),
             shapes_pool: ShapesPool::new(),
             shapes_selected: HashSet::new(),
+            history: History::new(),
+            clipboard: vec![],
+            revision: 0,
+        }
+    }
+
+    // The edits done until the command ends are undone at once, the
+    // commands can be nested
+    pub fn begin_command(&mut self) {
+        self.history.begin();
+    }
+    pub fn end_command(&mut self) {
+        if let Some(mut command) = self.history.end() {
+            self.revision += 1;
+            for change in command.iter_mut() {
+                change.after = self.get_shape_state(&change.sh_id);
+            }
+            self.history.push(command);
+        }
+    }
+    // To be called before changing a shape
+    fn record_shape(&mut self, sh_id: &ShapeId) {
+        if self.history.is_recording() {
+            let state = self.get_shape_state(sh_id);
+            self.history.record(sh_id, state);
+        }
+    }
+    // The selected shapes are about to be moved
+    pub fn record_shapes_selected(&mut self) {
+        for sh_id in self.shapes_selected.clone().iter() {
+            self.record_shape(sh_id);
+        }
+    }
+    pub fn undo(&mut self) -> bool {
+        if self.history.is_recording() {
+            return false;
+        }
+        let Some(command) = self.history.pop_undo() else {
+            return false;
+        };
+        for change in command.iter().rev() {
+            self.set_shape_state(&change.sh_id, &change.before);
+        }
+        self.revision += 1;
+        self.history.push_redo(command);
+        self.clear_shapes_selection();
+        true
+    }
+    pub fn redo(&mut self) -> bool {
+        if self.history.is_recording() {
+            return false;
+        }
+        let Some(command) = self.history.pop_redo() else {
+            return false;
+        };
+        for change in command.iter() {
+            self.set_shape_state(&change.sh_id, &change.after);
+        }
+        self.revision += 1;
+        self.history.push_undo(command);
+        self.clear_shapes_selection();
+        true
+    }
+    // Drawing of an opened project, with the groups as indexes in shapes. The
+    // history starts over, there's nothing to undo before it.
+    pub fn load(&mut self, shapes: Vec<Box<dyn Shape>>, groups: &[Vec<usize>]) {
+        let depth = self.get_history_depth();
+        let revision = self.revision;
+        *self = DataPools::new();
+        let sh_ids: Vec<ShapeId> = shapes
+            .into_iter()
+            .map(|shape| self.insert_shape(shape))
+            .collect();
+        for group in groups.iter() {
+            let grp_id = self.create_group_id();
+            for idx in group.iter() {
+                self.set_shape_group(&grp_id, &sh_ids[*idx]);
+            }
+        }
+        self.history = History::new();
+        self.set_history_depth(depth);
+        self.revision = revision + 1;
+    }
+    pub fn get_revision(&self) -> usize {
+        self.revision
+    }
+    pub fn get_history_depth(&self) -> usize {
+        self.history.get_depth()
+    }
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.set_depth(depth);
+    }
+    fn get_shape_state(&self, sh_id: &ShapeId) -> Option<ShapeState> {
+        self.shapes_pool
+            .get(sh_id)
+            .map(|shape| ShapeState::new(shape.as_ref(), self.groups_pool.get_shape_groups(sh_id)))
+    }
+    fn set_shape_state(&mut self, sh_id: &ShapeId, state: &Option<ShapeState>) {
+        self.groups_pool.delete_shape_id(sh_id);
+        self.shapes_pool.remove(sh_id);
+        if let Some(state) = state {
+            self.shapes_pool.insert(*sh_id, state.get_shape());
+            for grp_id in state.groups.iter() {
+                self.groups_pool.insert_shape_id(grp_id, sh_id);
+            }
         }
     }
 
@@ -39,6 +156,8 @@ impl DataPools {
     }
 
     pub fn delete_shapes_selected(&mut self) {
+        self.begin_command();
+        self.record_shapes_selected();
         for sh_id in self.shapes_selected.iter() {
             // Delete all references to sh_id in the group pool
             self.groups_pool.delete_shape_id(sh_id);
@@ -46,6 +165,166 @@ impl DataPools {
             self.shapes_pool.remove(sh_id);
         }
         self.shapes_selected.clear();
+        self.end_command();
+    }
+
+    pub fn copy_shapes_selected(&mut self) {
+        self.clipboard = self.get_shapes_selected_copy();
+    }
+    pub fn cut_shapes_selected(&mut self) {
+        self.copy_shapes_selected();
+        self.delete_shapes_selected();
+    }
+    pub fn get_clipboard_bounded_rectangle(&self) -> Option<[WPos; 2]> {
+        get_shapes_bounded_rectangle(self.clipboard.iter().map(|(shape, _)| shape))
+    }
+    // The copies are moved by the offset and selected, they go in new groups
+    // standing for the groups of the shapes copied
+    pub fn paste(&mut self, offset: &WPos) {
+        let shapes = self
+            .clipboard
+            .iter()
+            .map(|(shape, grp_ids)| (shape.clone_box(), grp_ids.clone()))
+            .collect();
+        self.insert_copies(shapes, offset);
+    }
+    // The corner of the copies goes on the point of the snap grid closest to
+    // pos
+    pub fn paste_at(&mut self, pos: &WPos, snap_grid: f64) {
+        if let Some(bb) = self.get_clipboard_bounded_rectangle() {
+            let mut pos = *pos;
+            pos.snap(snap_grid);
+            self.paste(&(pos - bb[0]));
+        }
+    }
+    pub fn duplicate_shapes_selected(&mut self) {
+        let shapes = self.get_shapes_selected_copy();
+        self.insert_copies(shapes, &WPos::zero());
+    }
+    fn get_shapes_selected_copy(&self) -> Vec<(Box<dyn Shape>, Vec<GroupId>)> {
+        let mut sh_ids: Vec<&ShapeId> = self.shapes_selected.iter().collect();
+        sh_ids.sort_by_key(|sh_id| ***sh_id);
+        sh_ids
+            .iter()
+            .filter_map(|sh_id| {
+                self.shapes_pool
+                    .get(sh_id)
+                    .map(|shape| (shape.clone_box(), self.groups_pool.get_shape_groups(sh_id)))
+            })
+            .collect()
+    }
+    fn insert_copies(&mut self, shapes: Vec<(Box<dyn Shape>, Vec<GroupId>)>, offset: &WPos) {
+        self.begin_command();
+        self.clear_shapes_selection();
+        let mut new_grp_ids: HashMap<usize, GroupId> = HashMap::new();
+        for (mut shape, grp_ids) in shapes {
+            // Moved like when dragged as a whole
+            shape.init_done();
+            shape.deselect_all_points();
+            shape.set_selected(true);
+            shape.save_current_position();
+            shape.move_selection(offset, &WPos::zero(), 0.);
+            let sh_id = self.insert_shape(shape);
+            self.set_shape_selected(&sh_id, true);
+            for grp_id in grp_ids.iter() {
+                let new_grp_id = *new_grp_ids
+                    .entry(**grp_id)
+                    .or_insert_with(|| self.groups_pool.create_id());
+                self.set_shape_group(&new_grp_id, &sh_id);
+            }
+        }
+        self.end_command();
+    }
+
+    pub fn get_shapes_selected_bounded_rectangle(&self) -> Option<[WPos; 2]> {
+        get_shapes_bounded_rectangle(
+            self.shapes_selected
+                .iter()
+                .filter_map(|sh_id| self.shapes_pool.get(sh_id)),
+        )
+    }
+    // Copies of the selected shapes once transformed, to show what a
+    // transformation will give
+    pub fn get_shapes_selected_transformed(&self, transform: &Transform) -> Vec<Box<dyn Shape>> {
+        self.shapes_selected
+            .iter()
+            .filter_map(|sh_id| self.shapes_pool.get(sh_id))
+            .flat_map(|shape| {
+                let mut shape = shape.clone_box();
+                shape.transform(transform).unwrap_or_else(|| vec![shape])
+            })
+            .collect()
+    }
+    // The shapes which can't keep their kind are replaced by selected shapes
+    // in the same groups
+    pub fn transform_shapes_selected(&mut self, transform: &Transform) {
+        self.begin_command();
+        self.record_shapes_selected();
+        let mut sh_ids: Vec<ShapeId> = self.shapes_selected.iter().copied().collect();
+        sh_ids.sort_by_key(|sh_id| **sh_id);
+        for sh_id in sh_ids.iter() {
+            let Some(shapes) = self
+                .shapes_pool
+                .get_mut(sh_id)
+                .and_then(|shape| shape.transform(transform))
+            else {
+                continue;
+            };
+            let grp_ids = self.groups_pool.get_shape_groups(sh_id);
+            self.groups_pool.delete_shape_id(sh_id);
+            self.shapes_pool.remove(sh_id);
+            self.shapes_selected.remove(sh_id);
+            for mut shape in shapes {
+                shape.init_done();
+                let new_sh_id = self.insert_shape(shape);
+                self.set_shape_selected(&new_sh_id, true);
+                for grp_id in grp_ids.iter() {
+                    self.set_shape_group(grp_id, &new_sh_id);
+                }
+            }
+        }
+        self.end_command();
+    }
+
+    // Copies of the selected shapes laid out by the pattern, to show what
+    // the array command will give
+    pub fn get_shapes_selected_arrayed(&self, pattern: &ArrayPattern) -> Vec<Box<dyn Shape>> {
+        self.get_array_transforms(pattern)
+            .iter()
+            .flat_map(|transform| self.get_shapes_selected_transformed(transform))
+            .collect()
+    }
+    // The originals and their copies make a new group and are selected
+    pub fn array_shapes_selected(&mut self, pattern: &ArrayPattern) -> Result<(), String> {
+        pattern.check()?;
+        // Made before any insertion, the copies being selected
+        let copies = self.get_shapes_selected_arrayed(pattern);
+        if copies.is_empty() {
+            return Ok(());
+        }
+        self.begin_command();
+        let grp_id = self.create_group_id();
+        let mut sh_ids: Vec<ShapeId> = self.shapes_selected.iter().copied().collect();
+        sh_ids.sort_by_key(|sh_id| **sh_id);
+        for sh_id in sh_ids.iter() {
+            self.set_shape_group(&grp_id, sh_id);
+        }
+        for mut shape in copies {
+            shape.init_done();
+            shape.deselect_all_points();
+            let sh_id = self.insert_shape(shape);
+            self.set_shape_selected(&sh_id, true);
+            self.set_shape_group(&grp_id, &sh_id);
+        }
+        self.end_command();
+        Ok(())
+    }
+    // The copies are placed from the center of the selection
+    fn get_array_transforms(&self, pattern: &ArrayPattern) -> Vec<Transform> {
+        match self.get_shapes_selected_bounded_rectangle() {
+            Some(bb) => pattern.get_transforms(&((bb[0] + bb[1]) / 2.)),
+            None => vec![],
+        }
     }
 
     pub fn set_shape_selected(&mut self, sh_id: &ShapeId, selected: bool) {
@@ -172,7 +451,7 @@ impl DataPools {
         // Save new selection
         if shift_pressed {
             for sh_id in self.shapes_selected.iter() {
-                let shape = self.shapes_pool.get_mut(&sh_id).unwrap();
+                let shape = self.shapes_pool.get_mut(sh_id).unwrap();
                 shape.set_selected(true);
                 new_shapes_selection.insert(*sh_id);
             }
@@ -191,7 +470,10 @@ impl DataPools {
     }
     pub fn insert_shape(&mut self, shape: Box<dyn Shape>) -> ShapeId {
         let sh_id = ShapeId(COUNTER_SHAPES.fetch_add(1, Ordering::Relaxed));
+        self.begin_command();
+        self.record_shape(&sh_id);
         self.shapes_pool.insert(sh_id, shape);
+        self.end_command();
         sh_id
     }
 
@@ -199,7 +481,10 @@ impl DataPools {
         self.groups_pool.create_id()
     }
     pub fn set_shape_group(&mut self, gr_id: &GroupId, sh_id: &ShapeId) {
+        self.begin_command();
+        self.record_shape(sh_id);
         self.groups_pool.insert_shape_id(gr_id, sh_id);
+        self.end_command();
     }
     pub fn _get_shape_group(&mut self, gr_id: &GroupId) -> Option<&Vec<ShapeId>> {
         self.groups_pool.get(gr_id)
@@ -212,12 +497,19 @@ impl DataPools {
         self.shapes_pool.get_mut(sh_id)
     }
 
+    pub fn get_contours(&self, tolerance: f64) -> Vec<Contour> {
+        self.shapes_pool.get_contours(tolerance)
+    }
+
     pub fn get_all_shapes(&self) -> &ShapesPool {
         &self.shapes_pool
     }
     pub fn get_all_shapes_mut(&mut self) -> &mut ShapesPool {
         &mut self.shapes_pool
     }
+    pub fn get_all_groups(&self) -> &GroupsPool {
+        &self.groups_pool
+    }
 
     pub fn _get_shape_position(&self, sh_id: &ShapeId) -> WPos {
         self.shapes_pool.get(sh_id).unwrap().get_pos()
@@ -244,161 +536,166 @@ impl DataPools {
             }
         }
     }
-
-    fn seek_intersection_precise(
+    pub fn magnet_to_intersection(
         &self,
-        sh_id: &ShapeId,
-        other_sh_id: &ShapeId,
-        r_a: f64,
-        r_b: f64,
-    ) -> Option<WPos> {
-        let shape = self.shapes_pool.get(&sh_id).unwrap();
-        let other_shape = self.shapes_pool.get(&other_sh_id).unwrap();
-
-        let stepping_r = shape.get_step_r(EPSILON);
-
-        // Linear search, distance are not signed, can't do a binary search
-        let mut r = r_a;
-        loop {
-            let pos = shape.get_pos_from_ratio(r);
-
-            if other_shape.dist(&pos) < EPSILON {
-                return Some(pos);
-            }
-
-            r += stepping_r;
-            if r >= r_b {
-                break;
+        pick_pos: &mut WPos,
+        excluded_sh_id: Option<ShapeId>,
+        magnet_distance: f64,
+    ) {
+        // Only the shapes passing near the pick position can intersect there
+        let shapes_near: Vec<&Box<dyn Shape>> = self
+            .shapes_pool
+            .iter()
+            .filter(|(sh_id, shape)| {
+                Some(**sh_id) != excluded_sh_id && shape.dist(pick_pos) < magnet_distance
+            })
+            .map(|(_, shape)| shape)
+            .collect();
+        for (idx, shape) in shapes_near.iter().enumerate() {
+            for other_shape in shapes_near.iter().skip(idx + 1) {
+                for int in get_shapes_intersections(shape.as_ref(), other_shape.as_ref(), EPSILON) {
+                    if pick_pos.dist(&int.pos) < magnet_distance {
+                        *pick_pos = int.pos;
+                    }
+                }
             }
         }
-        None
     }
 
-    fn seek_intersection(
+    // The two shapes are rounded where they meet, see get_fillet
+    pub fn fillet_shapes(
+        &mut self,
+        sh_id_a: &ShapeId,
+        sh_id_b: &ShapeId,
+        radius: f64,
+    ) -> Result<(), String> {
+        let (shape_a, shape_b) = self.get_corner_shapes(sh_id_a, sh_id_b)?;
+        let corner = get_fillet(shape_a, shape_b, radius)?;
+        self.replace_corner(sh_id_a, sh_id_b, corner);
+        Ok(())
+    }
+    // The corner of the two shapes is cut, see get_chamfer
+    pub fn chamfer_shapes(
+        &mut self,
+        sh_id_a: &ShapeId,
+        sh_id_b: &ShapeId,
+        distance: f64,
+        angle: f64,
+    ) -> Result<(), String> {
+        let (shape_a, shape_b) = self.get_corner_shapes(sh_id_a, sh_id_b)?;
+        let corner = get_chamfer(shape_a, shape_b, distance, angle)?;
+        self.replace_corner(sh_id_a, sh_id_b, corner);
+        Ok(())
+    }
+    fn get_corner_shapes(
         &self,
-        sh_id: &ShapeId,
-        pos_init: &WPos,
-        stepping: f64,
-        dir: bool,
-    ) -> Option<WPos> {
-        let shape = self.shapes_pool.get(&sh_id).unwrap();
-        let r_init = shape.get_ratio_from_pos(pos_init);
-        let stepping_r = shape.get_step_r(stepping);
-
-        let mut r = if dir {
-            (r_init + stepping_r + 0.0001).min(1.0)
-        } else {
-            (r_init - stepping_r - 0.0001).max(0.0)
-        };
-
-        let mut count = 0;
-        loop {
-            let pos = shape.get_pos_from_ratio(r);
-
-            log!("CURRENT r: {}", r);
-
-            for (other_sh_id, other_shape) in self.shapes_pool.iter().filter(|(id, _)| id != &sh_id)
-            {
-                log!("other_sh_id: {}, dir: {}", other_sh_id.0, dir);
-                if other_shape.dist(&pos) <= stepping {
-                    // Set the range for the precise seek
-                    let (r_a, r_b) = if dir {
-                        ((r - stepping_r).max(0.0), (r + stepping_r).min(1.0))
-                    } else {
-                        ((r - 2. * stepping_r).max(0.0), r)
-                    };
-                    log!("dir: {}, r_a: {:.2}, r_b: {:.2}", dir, r_a, r_b);
-
-                    // And search precisely
-                    // If a position is found return it
-                    if let Some(pos_int) =
-                        self.seek_intersection_precise(&sh_id, &other_sh_id, r_a, r_b)
-                    {
-                        return Some(pos_int);
-                    };
-                }
+        sh_id_a: &ShapeId,
+        sh_id_b: &ShapeId,
+    ) -> Result<(&dyn Shape, &dyn Shape), String> {
+        match (self.shapes_pool.get(sh_id_a), self.shapes_pool.get(sh_id_b)) {
+            (Some(shape_a), Some(shape_b)) if sh_id_a != sh_id_b => {
+                Ok((shape_a.as_ref(), shape_b.as_ref()))
             }
-
-            r = if dir {
-                (r + stepping_r).min(1.0)
-            } else {
-                (r - stepping_r).max(0.0)
-            };
-
-            if r == 0. || r == 1. {
-                break;
-            }
-
-            count += 1;
-            if count == 1000 {
-                log!("Couille dans l'potage");
-                break;
+            _ => Err("Two different shapes are needed".into()),
+        }
+    }
+    // The trimmed shapes keep the groups of the shapes they replace, the
+    // joint goes in the groups holding both
+    fn replace_corner(&mut self, sh_id_a: &ShapeId, sh_id_b: &ShapeId, corner: Corner) {
+        self.begin_command();
+        self.record_shape(sh_id_a);
+        self.record_shape(sh_id_b);
+        let grp_ids_a = self.groups_pool.get_shape_groups(sh_id_a);
+        let grp_ids_b = self.groups_pool.get_shape_groups(sh_id_b);
+        for (sh_id, piece, grp_ids) in [
+            (sh_id_a, corner.shape_a, &grp_ids_a),
+            (sh_id_b, corner.shape_b, &grp_ids_b),
+        ] {
+            self.groups_pool.delete_shape_id(sh_id);
+            self.shapes_pool.remove(sh_id);
+            self.shapes_selected.remove(sh_id);
+            if let Some(piece) = piece {
+                let new_sh_id = self.insert_shape(piece);
+                for grp_id in grp_ids.iter() {
+                    self.set_shape_group(grp_id, &new_sh_id);
+                }
             }
         }
-
-        None
+        let mut joint = corner.joint;
+        joint.init_done();
+        let joint_id = self.insert_shape(joint);
+        for grp_id in grp_ids_a.iter().filter(|grp_id| grp_ids_b.contains(grp_id)) {
+            self.set_shape_group(grp_id, &joint_id);
+        }
+        self.end_command();
     }
 
-    pub fn cut_shape(&mut self, sh_id: &ShapeId, pick_pos: &WPos, grab_handle_precision: f64) {
-        let pos = self
-            .shapes_pool
-            .get(&sh_id)
-            .unwrap()
-            .get_projected_pos(&pick_pos);
-        log!(
-            "pick_pos: ({:.0}, {:.0}) proj_pos: ({:.0}, {:.0})",
-            pick_pos.wx,
-            pick_pos.wy,
-            pos.wx,
-            pos.wy
-        );
-        let stepping = grab_handle_precision / 10.;
+    pub fn cut_shape(&mut self, sh_id: &ShapeId, pick_pos: &WPos) {
+        self.begin_command();
+        self.record_shape(sh_id);
+        let shape = self.shapes_pool.get(sh_id).unwrap();
+        let pos = shape.get_projected_pos(pick_pos);
+        let r = shape.get_ratio_from_pos(&pos);
 
-        // Search intersection with another shape in the first direction
-        let o_pos_p: Option<WPos> = self.seek_intersection(&sh_id, &pos, stepping, true);
-        // Search intersection with another shape in the second direction
-        let o_pos_n: Option<WPos> = self.seek_intersection(&sh_id, &pos, stepping, false);
-
-        if let Some(pos_p) = o_pos_p {
-            log!("pos_p: ({:.0},{:.0})", pos_p.wx, pos_p.wy);
-        } else {
-            log!("pos_p: None");
-        }
-        if let Some(pos_n) = o_pos_n {
-            log!("pos_n: ({:.0},{:.0})", pos_n.wx, pos_n.wy);
-        } else {
-            log!("pos_n: None");
+        // Closest intersections with other shapes on both sides of pos, a
+        // closed shape going on across its start
+        let mut ints: Vec<Intersection> = vec![];
+        for (_, other_shape) in self.shapes_pool.iter().filter(|(id, _)| *id != sh_id) {
+            ints.extend(get_shapes_intersections(
+                shape.as_ref(),
+                other_shape.as_ref(),
+                EPSILON,
+            ));
         }
+        let by_ratio =
+            |int_a: &&Intersection, int_b: &&Intersection| int_a.r_a.total_cmp(&int_b.r_a);
+        let closed = shape.is_closed();
+        let o_int_n = ints
+            .iter()
+            .filter(|int| int.r_a < r)
+            .max_by(by_ratio)
+            .or_else(|| ints.iter().filter(|_| closed).max_by(by_ratio));
+        let o_int_p = ints
+            .iter()
+            .filter(|int| int.r_a > r)
+            .min_by(by_ratio)
+            .or_else(|| ints.iter().filter(|_| closed).min_by(by_ratio));
+        let o_pos_n = o_int_n.map(|int| int.pos);
+        let o_pos_p = o_int_p.map(|int| int.pos);
 
         // inf and sup names are convention
-        match (o_pos_n, o_pos_p) {
+        let pieces = match (o_pos_n, o_pos_p) {
             (None, None) => {
                 // Nothing to do here, there are no intersections with other shapes
                 // then this shape will be simply deleted
-                ()
-            }
-            (None, Some(pos_p)) => {
-                if let Some(sh_sup) = self.shapes_pool.get(&sh_id).unwrap().split(&pos_p).1 {
-                    self.insert_shape(sh_sup);
-                }
+                vec![]
             }
-            (Some(pos_n), None) => {
-                let shape = self.shapes_pool.get(&sh_id).unwrap();
-                if let Some(sh_inf) = shape.split(&pos_n).0 {
-                    self.insert_shape(sh_inf);
+            (None, Some(pos_p)) => vec![shape.split(&pos_p).1],
+            (Some(pos_n), None) => vec![shape.split(&pos_n).0],
+            (Some(pos_n), Some(pos_p)) if closed => {
+                // A single piece remains, from one cut round to the other. Cut
+                // once only, nothing does.
+                if pos_n.dist(&pos_p) > EPSILON {
+                    vec![shape.split_closed(&pos_p, &pos_n)]
+                } else {
+                    vec![]
                 }
             }
-            (Some(pos_n), Some(pos_p)) => {
-                if let Some(shape_n) = self.shapes_pool.get(&sh_id).unwrap().split(&pos_n).0 {
-                    self.insert_shape(shape_n);
-                }
-                if let Some(shape_p) = self.shapes_pool.get(&sh_id).unwrap().split(&pos_p).1 {
-                    self.insert_shape(shape_p);
-                }
+            (Some(pos_n), Some(pos_p)) => vec![shape.split(&pos_n).0, shape.split(&pos_p).1],
+        };
+        // The pieces stay in the groups of the shape
+        let grp_ids = self.groups_pool.get_shape_groups(sh_id);
+        for piece in pieces.into_iter().flatten() {
+            let piece_id = self.insert_shape(piece);
+            for grp_id in grp_ids.iter() {
+                self.set_shape_group(grp_id, &piece_id);
             }
         }
         // Suppress the original shape from the pool
-        self.shapes_pool.remove(&sh_id);
+        self.groups_pool.delete_shape_id(sh_id);
+        self.shapes_pool.remove(sh_id);
+        self.shapes_selected.remove(sh_id);
+        self.end_command();
     }
 }
 
@@ -418,6 +715,127 @@ impl ShapesPool {
     pub fn new() -> ShapesPool {
         ShapesPool(HashMap::new())
     }
+    // Chaining of the shapes whose ends coincide within the tolerance.
+    // Shapes are taken in their drawing order and a chain is extended at
+    // its end first, then at its start, shapes being reversed when needed
+    // so that the whole contour is followed in one direction.
+    pub fn get_contours(&self, tolerance: f64) -> Vec<Contour> {
+        let mut sh_ids: Vec<ShapeId> = self.keys().cloned().collect();
+        sh_ids.sort_by_key(|sh_id| sh_id.0);
+        let ends: Vec<(ShapeId, WPos, WPos)> = sh_ids
+            .iter()
+            .map(|sh_id| {
+                let shape = &self[sh_id];
+                (
+                    *sh_id,
+                    shape.get_pos_from_ratio(0.),
+                    shape.get_pos_from_ratio(1.),
+                )
+            })
+            .collect();
+
+        let mut used = vec![false; ends.len()];
+        let mut contours = vec![];
+        for idx in 0..ends.len() {
+            if used[idx] {
+                continue;
+            }
+            used[idx] = true;
+            let (sh_id, mut start, mut end) = ends[idx];
+            let mut links = vec![(sh_id, false)];
+
+            while start.dist(&end) > tolerance {
+                let found = (0..ends.len()).find(|&i| {
+                    !used[i]
+                        && (ends[i].1.dist(&end) <= tolerance || ends[i].2.dist(&end) <= tolerance)
+                });
+                if let Some(i) = found {
+                    used[i] = true;
+                    let reversed = ends[i].1.dist(&end) > tolerance;
+                    end = if reversed { ends[i].1 } else { ends[i].2 };
+                    links.push((ends[i].0, reversed));
+                } else {
+                    break;
+                }
+            }
+            while start.dist(&end) > tolerance {
+                let found = (0..ends.len()).find(|&i| {
+                    !used[i]
+                        && (ends[i].1.dist(&start) <= tolerance
+                            || ends[i].2.dist(&start) <= tolerance)
+                });
+                if let Some(i) = found {
+                    used[i] = true;
+                    let reversed = ends[i].2.dist(&start) > tolerance;
+                    start = if reversed { ends[i].2 } else { ends[i].1 };
+                    links.insert(0, (ends[i].0, reversed));
+                } else {
+                    break;
+                }
+            }
+
+            let closed = start.dist(&end) <= tolerance;
+            let winding = if closed {
+                Some(self.get_winding(&links))
+            } else {
+                None
+            };
+            contours.push(Contour {
+                links,
+                closed,
+                winding,
+            });
+        }
+        contours
+    }
+    // Sign of the area enclosed by the contour, from a sampling of its shapes
+    fn get_winding(&self, links: &[(ShapeId, bool)]) -> Winding {
+        const NB_SAMPLES: usize = 32;
+        let mut polygon = vec![];
+        for (sh_id, reversed) in links.iter() {
+            let shape = &self[sh_id];
+            for i in 0..NB_SAMPLES {
+                let r = i as f64 / NB_SAMPLES as f64;
+                polygon.push(shape.get_pos_from_ratio(if *reversed { 1. - r } else { r }));
+            }
+        }
+        let n = polygon.len();
+        let area: f64 = (0..n)
+            .map(|i| {
+                let (p1, p2) = (polygon[i], polygon[(i + 1) % n]);
+                p1.wx * p2.wy - p2.wx * p1.wy
+            })
+            .sum();
+        if area > 0. {
+            Winding::CounterClockwise
+        } else {
+            Winding::Clockwise
+        }
+    }
+}
+
+// Direction in which a closed contour is followed, in world coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+// Shapes joined end to end. Each link is a shape of the contour, followed
+// from its end to its start when reversed.
+#[derive(Clone, Debug)]
+pub struct Contour {
+    pub links: Vec<(ShapeId, bool)>,
+    pub closed: bool,
+    pub winding: Option<Winding>,
+}
+impl Contour {
+    pub fn get_shape_ids(&self) -> Vec<ShapeId> {
+        self.links.iter().map(|(sh_id, _)| *sh_id).collect()
+    }
+    pub fn contains(&self, sh_id: &ShapeId) -> bool {
+        self.links.iter().any(|(link_sh_id, _)| link_sh_id == sh_id)
+    }
 }
 
 pub struct GroupsPool(HashMap<GroupId, Vec<ShapeId>>);
@@ -454,6 +872,545 @@ impl GroupsPool {
             sh_ids.retain(|vec_sh_id| vec_sh_id != sh_id)
         }
     }
+    // Sorted to be compared
+    pub fn get_shape_groups(&self, sh_id: &ShapeId) -> Vec<GroupId> {
+        let mut grp_ids: Vec<GroupId> = self
+            .iter()
+            .filter(|(_, sh_ids)| sh_ids.contains(sh_id))
+            .map(|(grp_id, _)| *grp_id)
+            .collect();
+        grp_ids.sort_by_key(|grp_id| **grp_id);
+        grp_ids
+    }
+    // The groups still holding shapes, their shapes sorted, ordered by their
+    // first shape
+    pub fn get_sorted_groups(&self, shapes_pool: &ShapesPool) -> Vec<Vec<ShapeId>> {
+        let mut groups: Vec<Vec<ShapeId>> = self
+            .values()
+            .map(|sh_ids| {
+                let mut sh_ids: Vec<ShapeId> = sh_ids
+                    .iter()
+                    .filter(|sh_id| shapes_pool.contains_key(sh_id))
+                    .copied()
+                    .collect();
+                sh_ids.sort_by_key(|sh_id| **sh_id);
+                sh_ids
+            })
+            .filter(|sh_ids| !sh_ids.is_empty())
+            .collect();
+        groups.sort_by_key(|sh_ids| *sh_ids[0]);
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::cubicbezier::CubicBezier;
+    use crate::shapes::ellipse::Ellipse;
+    use crate::shapes::line::Line;
+    use crate::shapes::rectangle::Rectangle;
+    use std::f64::consts::PI;
+
+    // Without the log of DataPools::new, which needs a browser
+    fn get_data_pools() -> DataPools {
+        DataPools {
+            shapes_pool: ShapesPool::new(),
+            groups_pool: GroupsPool::new(),
+            shapes_selected: HashSet::new(),
+            history: History::new(),
+            clipboard: vec![],
+            revision: 0,
+        }
+    }
+
+    #[test]
+    fn test_revision() {
+        let mut data_pools = get_data_pools();
+        let line = Line::new(&WPos::new(0., 0.), &WPos::new(10., 0.)).unwrap();
+        let sh_id = data_pools.insert_shape(Box::new(line));
+        let revision = data_pools.get_revision();
+        assert!(revision > 0);
+        // Selecting doesn't change the drawing
+        data_pools.set_shape_selected(&sh_id, true);
+        assert_eq!(data_pools.get_revision(), revision);
+        // A command changes it once it ends, undo and redo too
+        data_pools.begin_command();
+        data_pools.record_shapes_selected();
+        data_pools.get_shape_mut(&sh_id).unwrap().init_done();
+        assert_eq!(data_pools.get_revision(), revision);
+        data_pools.end_command();
+        assert_eq!(data_pools.get_revision(), revision + 1);
+        assert!(data_pools.undo());
+        assert_eq!(data_pools.get_revision(), revision + 2);
+        assert!(data_pools.redo());
+        assert_eq!(data_pools.get_revision(), revision + 3);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut data_pools = get_data_pools();
+        let mut line = Line::new(&WPos::new(0., 0.), &WPos::new(10., 0.)).unwrap();
+        line.init_done();
+        let sh_id = data_pools.insert_shape(Box::new(line));
+
+        // Grouping and moving, done with the mouse button down, are undone at
+        // once
+        data_pools.begin_command();
+        let grp_id = data_pools.create_group_id();
+        data_pools.set_shape_group(&grp_id, &sh_id);
+        data_pools.set_shape_selected(&sh_id, true);
+        data_pools.record_shapes_selected();
+        let shape = data_pools.get_shape_mut(&sh_id).unwrap();
+        shape.save_current_position();
+        shape.move_selection(&WPos::new(5., 5.), &WPos::zero(), 0.);
+        assert!(!data_pools.undo());
+        data_pools.end_command();
+        let get_start = |data_pools: &DataPools| {
+            data_pools
+                .get_all_shapes()
+                .get(&sh_id)
+                .map(|shape| shape.get_pos_from_ratio(0.))
+        };
+        assert_eq!(get_start(&data_pools), Some(WPos::new(5., 5.)));
+
+        data_pools.delete_shapes_selected();
+        assert_eq!(get_start(&data_pools), None);
+        assert!(data_pools.undo());
+        assert_eq!(get_start(&data_pools), Some(WPos::new(5., 5.)));
+        assert_eq!(
+            data_pools.get_all_groups().get_shape_groups(&sh_id).len(),
+            1
+        );
+        assert!(data_pools.undo());
+        assert_eq!(get_start(&data_pools), Some(WPos::new(0., 0.)));
+        assert!(data_pools
+            .get_all_groups()
+            .get_shape_groups(&sh_id)
+            .is_empty());
+        assert!(data_pools.undo());
+        assert_eq!(get_start(&data_pools), None);
+        assert!(!data_pools.undo());
+
+        assert!(data_pools.redo());
+        assert!(data_pools.redo());
+        assert_eq!(get_start(&data_pools), Some(WPos::new(5., 5.)));
+        assert_eq!(
+            data_pools.get_all_groups().get_shape_groups(&sh_id),
+            vec![grp_id]
+        );
+        // A new edit can't be followed by the commands undone
+        data_pools.set_shape_selected(&sh_id, true);
+        data_pools.begin_command();
+        data_pools.record_shapes_selected();
+        data_pools.end_command();
+        assert!(data_pools.redo());
+        data_pools.insert_shape(Box::new(
+            Line::new(&WPos::zero(), &WPos::new(0., 10.)).unwrap(),
+        ));
+        assert!(!data_pools.redo());
+        assert_eq!(data_pools.get_all_shapes().len(), 1);
+    }
+
+    #[test]
+    fn test_copy_paste() {
+        let mut data_pools = get_data_pools();
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Line::new(&WPos::new(0., 0.), &WPos::new(10., 0.)).unwrap()),
+            Box::new(Rectangle::new(&WPos::new(20., 0.), 10., 5.).unwrap()),
+            Box::new(Ellipse::new(
+                &WPos::new(40., 0.),
+                &WPos::new(45., 5.),
+                0.,
+                0.,
+                1.,
+            )),
+            Box::new(
+                CubicBezier::new(
+                    &WPos::new(0., 20.),
+                    &WPos::new(5., 25.),
+                    &WPos::new(10., 25.),
+                    &WPos::new(15., 20.),
+                )
+                .unwrap(),
+            ),
+        ];
+        let grp_id = data_pools.create_group_id();
+        let mut sh_ids = vec![];
+        for mut shape in shapes {
+            shape.init_done();
+            let sh_id = data_pools.insert_shape(shape);
+            data_pools.set_shape_group(&grp_id, &sh_id);
+            data_pools.set_shape_selected(&sh_id, true);
+            sh_ids.push(sh_id);
+        }
+        let get_starts = |data_pools: &DataPools, sh_ids: &[ShapeId]| -> Vec<WPos> {
+            sh_ids
+                .iter()
+                .map(|sh_id| data_pools.get_all_shapes()[sh_id].get_pos_from_ratio(0.))
+                .collect()
+        };
+        let starts = get_starts(&data_pools, &sh_ids);
+
+        data_pools.copy_shapes_selected();
+        let bb = data_pools.get_clipboard_bounded_rectangle().unwrap();
+        assert_eq!(bb[0], WPos::new(0., -5.));
+        let offset = WPos::new(100., 50.);
+        data_pools.paste(&offset);
+
+        // The copies are selected, moved and grouped apart from the originals
+        let mut pasted: Vec<ShapeId> = data_pools.get_shapes_selected().iter().copied().collect();
+        pasted.sort_by_key(|sh_id| **sh_id);
+        assert_eq!(pasted.len(), 4);
+        assert!(pasted.iter().all(|sh_id| !sh_ids.contains(sh_id)));
+        for (start, pasted_start) in starts.iter().zip(get_starts(&data_pools, &pasted)) {
+            assert!(pasted_start.dist(&(*start + offset)) < EPSILON);
+        }
+        let new_grp_ids = data_pools.get_all_groups().get_shape_groups(&pasted[0]);
+        assert_eq!(new_grp_ids.len(), 1);
+        assert_ne!(new_grp_ids[0], grp_id);
+        assert!(pasted
+            .iter()
+            .all(|sh_id| data_pools.get_all_groups().get_shape_groups(sh_id) == new_grp_ids));
+        assert_eq!(data_pools.get_all_shapes().len(), 8);
+
+        // Undone at once
+        assert!(data_pools.undo());
+        assert_eq!(data_pools.get_all_shapes().len(), 4);
+
+        // Duplicated in place, then cut
+        sh_ids
+            .iter()
+            .for_each(|sh_id| data_pools.set_shape_selected(sh_id, true));
+        data_pools.duplicate_shapes_selected();
+        let mut duplicated: Vec<ShapeId> =
+            data_pools.get_shapes_selected().iter().copied().collect();
+        duplicated.sort_by_key(|sh_id| **sh_id);
+        assert_eq!(get_starts(&data_pools, &duplicated), starts);
+        data_pools.cut_shapes_selected();
+        assert_eq!(data_pools.get_all_shapes().len(), 4);
+        assert!(data_pools.get_shapes_selected().is_empty());
+        data_pools.paste(&WPos::zero());
+        assert_eq!(data_pools.get_all_shapes().len(), 8);
+
+        // Pasted under the mouse, on the snap grid
+        assert!(data_pools.undo());
+        data_pools.paste_at(&WPos::new(12.3, 7.8), 5.);
+        data_pools.copy_shapes_selected();
+        let bb = data_pools.get_clipboard_bounded_rectangle().unwrap();
+        assert!(bb[0].dist(&WPos::new(10., 10.)) < EPSILON);
+    }
+
+    #[test]
+    fn test_load() {
+        let mut data_pools = get_data_pools();
+        data_pools.set_history_depth(3);
+        let line = Line::new(&WPos::zero(), &WPos::new(5., 0.)).unwrap();
+        data_pools.insert_shape(Box::new(line));
+
+        let shapes: Vec<Box<dyn Shape>> = (1..4)
+            .map(|i| {
+                let end = WPos::new(10. * i as f64, 0.);
+                Box::new(Line::new(&WPos::zero(), &end).unwrap()) as Box<dyn Shape>
+            })
+            .collect();
+        data_pools.load(shapes, &[vec![0, 2]]);
+        assert_eq!(data_pools.get_all_shapes().len(), 3);
+        assert_eq!(data_pools.get_all_groups().len(), 1);
+        // Neither the shapes loaded nor the drawing replaced can be undone
+        assert!(!data_pools.undo());
+        assert!(!data_pools.redo());
+        assert_eq!(data_pools.get_all_shapes().len(), 3);
+        assert_eq!(data_pools.get_history_depth(), 3);
+    }
+
+    #[test]
+    fn test_transform_selection() {
+        let mut data_pools = get_data_pools();
+        let mut line = Line::new(&WPos::new(0., 0.), &WPos::new(10., 0.)).unwrap();
+        line.init_done();
+        let line_id = data_pools.insert_shape(Box::new(line));
+        let mut rectangle = Rectangle::new(&WPos::new(0., 0.), 10., 10.).unwrap();
+        rectangle.init_done();
+        let rectangle_id = data_pools.insert_shape(Box::new(rectangle));
+        let grp_id = data_pools.create_group_id();
+        for sh_id in [line_id, rectangle_id] {
+            data_pools.set_shape_group(&grp_id, &sh_id);
+            data_pools.set_shape_selected(&sh_id, true);
+        }
+        let bb = data_pools.get_shapes_selected_bounded_rectangle().unwrap();
+        assert_eq!(bb, [WPos::new(0., 0.), WPos::new(10., 10.)]);
+
+        // Turned around the center of the selection, the rectangle becomes a
+        // polyline taking its place in the group and the selection
+        let transform = Transform::rotate(PI / 4.).around(&WPos::new(5., 5.));
+        let preview = data_pools.get_shapes_selected_transformed(&transform);
+        assert_eq!(preview.len(), 2);
+        assert_eq!(data_pools.get_all_shapes().len(), 2);
+        data_pools.transform_shapes_selected(&transform);
+        let shapes = data_pools.get_all_shapes();
+        assert_eq!(shapes.len(), 2);
+        assert!(!shapes.contains_key(&rectangle_id));
+        let start = transform.apply(&WPos::new(0., 0.));
+        assert!(shapes[&line_id].get_pos_from_ratio(0.).dist(&start) < 1e-9);
+        let polyline_id = *data_pools
+            .get_shapes_selected()
+            .iter()
+            .find(|sh_id| **sh_id != line_id)
+            .unwrap();
+        assert_eq!(shapes[&polyline_id].get_type_name(), "polyline");
+        assert_eq!(
+            data_pools.get_all_groups().get_shape_groups(&polyline_id),
+            vec![grp_id]
+        );
+
+        // Undone at once
+        assert!(data_pools.undo());
+        let shapes = data_pools.get_all_shapes();
+        assert_eq!(shapes[&rectangle_id].get_type_name(), "rectangle");
+        assert!(!shapes.contains_key(&polyline_id));
+        assert!(shapes[&line_id].get_pos_from_ratio(0.).dist(&WPos::zero()) < 1e-9);
+    }
+
+    #[test]
+    fn test_array() {
+        let mut data_pools = get_data_pools();
+        // A hole of radius 5 on the right of a flange centered on the origin
+        let mut hole = Ellipse::new(&WPos::new(40., 0.), &WPos::new(45., 5.), 0., 2. * PI, 1.);
+        hole.init_done();
+        let hole_id = data_pools.insert_shape(Box::new(hole));
+        data_pools.set_shape_selected(&hole_id, true);
+
+        let pattern = ArrayPattern::Polar {
+            center: WPos::zero(),
+            count: 6,
+            angle: 2. * PI,
+            rotate_items: true,
+        };
+        assert_eq!(data_pools.get_shapes_selected_arrayed(&pattern).len(), 5);
+        assert_eq!(data_pools.get_all_shapes().len(), 1);
+        data_pools.array_shapes_selected(&pattern).unwrap();
+
+        // The six holes are selected and make a new group
+        let shapes = data_pools.get_all_shapes();
+        assert_eq!(shapes.len(), 6);
+        assert_eq!(data_pools.get_shapes_selected().len(), 6);
+        let grp_ids = data_pools.get_all_groups().get_shape_groups(&hole_id);
+        assert_eq!(grp_ids.len(), 1);
+        let copy_pos = WPos::new(-20., 20. * 3_f64.sqrt());
+        assert!(shapes.iter().any(|(sh_id, shape)| {
+            shape.dist(&copy_pos) < 5. + 1e-6
+                && shape.dist(&copy_pos) > 5. - 1e-6
+                && data_pools.get_all_groups().get_shape_groups(sh_id) == grp_ids
+        }));
+
+        // Undone at once
+        assert!(data_pools.undo());
+        assert_eq!(data_pools.get_all_shapes().len(), 1);
+        assert!(data_pools
+            .get_all_groups()
+            .get_shape_groups(&hole_id)
+            .is_empty());
+
+        // Too many copies are refused
+        let grid = ArrayPattern::Linear {
+            rows: 1000,
+            columns: 1000,
+            spacing: WPos::new(20., 20.),
+        };
+        assert!(data_pools.array_shapes_selected(&grid).is_err());
+        assert_eq!(data_pools.get_all_shapes().len(), 1);
+    }
+
+    #[test]
+    fn test_fillet_and_chamfer() {
+        let mut data_pools = get_data_pools();
+        let grp_id = data_pools.create_group_id();
+        let mut sh_ids = vec![];
+        for (start, end) in [((0., 0.), (50., 0.)), ((50., 0.), (50., 50.))] {
+            let line = Line::new(&WPos::new(start.0, start.1), &WPos::new(end.0, end.1)).unwrap();
+            let sh_id = data_pools.insert_shape(Box::new(line));
+            data_pools.set_shape_group(&grp_id, &sh_id);
+            sh_ids.push(sh_id);
+        }
+        data_pools
+            .fillet_shapes(&sh_ids[0], &sh_ids[1], 10.)
+            .unwrap();
+
+        // The lines are trimmed and joined by an arc, all in the group
+        let shapes = data_pools.get_all_shapes();
+        assert_eq!(shapes.len(), 3);
+        assert!(sh_ids.iter().all(|sh_id| !shapes.contains_key(sh_id)));
+        let arc = shapes
+            .values()
+            .find(|shape| shape.get_type_name() == "ellipse")
+            .unwrap();
+        assert!(arc.dist(&WPos::new(40., 0.)) < 1e-6);
+        assert!(arc.dist(&WPos::new(50., 10.)) < 1e-6);
+        assert!(shapes
+            .keys()
+            .all(|sh_id| { data_pools.get_all_groups().get_shape_groups(sh_id) == vec![grp_id] }));
+
+        // Undone at once, then the corner is cut
+        assert!(data_pools.undo());
+        assert_eq!(data_pools.get_all_shapes().len(), 2);
+        data_pools
+            .chamfer_shapes(&sh_ids[0], &sh_ids[1], 5., PI / 4.)
+            .unwrap();
+        let shapes = data_pools.get_all_shapes();
+        let line = shapes
+            .values()
+            .find(|shape| shape.dist(&WPos::new(47.5, 2.5)) < 1e-6)
+            .unwrap();
+        assert!(line.get_pos_from_ratio(1.).dist(&WPos::new(50., 5.)) < 1e-6);
+
+        assert!(data_pools
+            .fillet_shapes(&sh_ids[0], &sh_ids[1], 10.)
+            .is_err());
+    }
+
+    #[test]
+    fn test_history_depth() {
+        let mut data_pools = get_data_pools();
+        data_pools.set_history_depth(2);
+        for i in 1..5 {
+            let line = Line::new(&WPos::zero(), &WPos::new(10. * i as f64, 0.)).unwrap();
+            data_pools.insert_shape(Box::new(line));
+        }
+        assert!(data_pools.undo());
+        assert!(data_pools.undo());
+        assert!(!data_pools.undo());
+        assert_eq!(data_pools.get_all_shapes().len(), 2);
+    }
+
+    #[test]
+    fn test_contours() {
+        let mut shapes_pool = ShapesPool::new();
+        let pts = [
+            WPos::new(0., 0.),
+            WPos::new(10., 0.),
+            WPos::new(10., 10.),
+            WPos::new(0., 10.),
+        ];
+        // Square drawn out of order with a reversed side, then an open polyline
+        let lines = [(2, 3), (0, 1), (2, 1), (3, 0)];
+        for (idx, (i, j)) in lines.iter().enumerate() {
+            let line = Line::new(&pts[*i], &pts[*j]).unwrap();
+            shapes_pool.insert(ShapeId(idx), Box::new(line));
+        }
+        let line = Line::new(&WPos::new(20., 0.), &WPos::new(30., 0.)).unwrap();
+        shapes_pool.insert(ShapeId(4), Box::new(line));
+        let line = Line::new(&WPos::new(30., 10.), &WPos::new(30., 0.)).unwrap();
+        shapes_pool.insert(ShapeId(5), Box::new(line));
+        let circle = Ellipse::new(&WPos::new(50., 0.), &WPos::new(55., 5.), 0., 0., 1.);
+        shapes_pool.insert(ShapeId(6), Box::new(circle));
+
+        let contours = shapes_pool.get_contours(0.01);
+        assert_eq!(contours.len(), 3);
+
+        let square = &contours[0];
+        assert!(square.closed);
+        assert_eq!(
+            square.links,
+            vec![
+                (ShapeId(0), false),
+                (ShapeId(3), false),
+                (ShapeId(1), false),
+                (ShapeId(2), true)
+            ]
+        );
+        assert_eq!(square.winding, Some(Winding::CounterClockwise));
+
+        let polyline = &contours[1];
+        assert!(!polyline.closed);
+        assert_eq!(polyline.winding, None);
+        assert_eq!(
+            polyline.links,
+            vec![(ShapeId(4), false), (ShapeId(5), true)]
+        );
+
+        assert!(contours[2].closed);
+        assert!(contours[2].contains(&ShapeId(6)));
+    }
+
+    #[test]
+    fn test_cut_shape() {
+        let cut = |shape: Box<dyn Shape>, pick_pos: WPos| {
+            let mut data_pools = get_data_pools();
+            let sh_id = data_pools.insert_shape(shape);
+            let mut line_ids = vec![];
+            for x in [30., 70.] {
+                let line = Line::new(&WPos::new(x, -60.), &WPos::new(x, 60.)).unwrap();
+                line_ids.push(data_pools.insert_shape(Box::new(line)));
+            }
+            data_pools.cut_shape(&sh_id, &pick_pos);
+            let shapes = data_pools.get_all_shapes();
+            assert!(!shapes.contains_key(&sh_id));
+            let piece_ids: Vec<ShapeId> = shapes
+                .keys()
+                .filter(|id| !line_ids.contains(id))
+                .copied()
+                .collect();
+            assert_eq!(piece_ids.len(), 1);
+            (data_pools, piece_ids[0])
+        };
+
+        // Cut on its left side, the rectangle is opened across its start
+        let rectangle = Rectangle::new(&WPos::zero(), 100., 50.).unwrap();
+        let (data_pools, piece_id) = cut(Box::new(rectangle), WPos::new(0., 25.));
+        let piece = data_pools.get_all_shapes().get(&piece_id).unwrap();
+        assert!(piece.get_pos_from_ratio(0.).dist(&WPos::new(30., 0.)) < 1e-6);
+        assert!(piece.get_pos_from_ratio(1.).dist(&WPos::new(30., 50.)) < 1e-6);
+        assert!(piece.dist(&WPos::new(100., 25.)) < 1e-6);
+        assert!(piece.dist(&WPos::new(0., 25.)) > 1.);
+
+        // Between the lines on the top side, the rest is kept as one piece
+        let rectangle = Rectangle::new(&WPos::zero(), 100., 50.).unwrap();
+        let (data_pools, piece_id) = cut(Box::new(rectangle), WPos::new(50., 0.));
+        let piece = data_pools.get_all_shapes().get(&piece_id).unwrap();
+        assert!(piece.get_pos_from_ratio(0.).dist(&WPos::new(70., 0.)) < 1e-6);
+        assert!(piece.get_pos_from_ratio(1.).dist(&WPos::new(30., 0.)) < 1e-6);
+        assert!(piece.dist(&WPos::new(0., 25.)) < 1e-6);
+        assert!(piece.dist(&WPos::new(100., 25.)) < 1e-6);
+        assert!(piece.dist(&WPos::new(50., 50.)) < 1e-6);
+        assert!(piece.dist(&WPos::new(50., 0.)) > 1.);
+
+        // Same for a whole ellipse, only its right part remains
+        let circle = Ellipse::new(&WPos::new(50., 0.), &WPos::new(100., 50.), 0., 0., 1.);
+        let (data_pools, piece_id) = cut(Box::new(circle), WPos::new(0., 0.));
+        let piece = data_pools.get_all_shapes().get(&piece_id).unwrap();
+        assert!((piece.get_pos_from_ratio(0.).wx - 30.).abs() < 1e-6);
+        assert!((piece.get_pos_from_ratio(1.).wx - 30.).abs() < 1e-6);
+        assert!(piece.dist(&WPos::new(100., 0.)) < 1e-6);
+        assert!(piece.dist(&WPos::new(0., 0.)) > 1.);
+
+        // An open shape keeps its pieces on both sides, in its group
+        let mut data_pools = get_data_pools();
+        let line = Line::new(&WPos::new(0., 0.), &WPos::new(100., 0.)).unwrap();
+        let sh_id = data_pools.insert_shape(Box::new(line));
+        let grp_id = data_pools.create_group_id();
+        data_pools.set_shape_group(&grp_id, &sh_id);
+        for x in [30., 70.] {
+            let line = Line::new(&WPos::new(x, -10.), &WPos::new(x, 10.)).unwrap();
+            data_pools.insert_shape(Box::new(line));
+        }
+        data_pools.cut_shape(&sh_id, &WPos::new(50., 0.));
+        let shapes = data_pools.get_all_shapes();
+        assert_eq!(shapes.len(), 4);
+        assert!(shapes
+            .values()
+            .any(|shape| shape.dist(&WPos::new(10., 0.)) < 1e-6));
+        assert!(shapes
+            .values()
+            .all(|shape| shape.dist(&WPos::new(50., 0.)) > 1.));
+        let group = data_pools.get_all_groups().get(&grp_id).unwrap();
+        assert_eq!(group.len(), 2);
+        assert!(!group.contains(&sh_id));
+        assert!(data_pools.undo());
+        assert_eq!(data_pools.get_all_shapes().len(), 3);
+        let group = data_pools.get_all_groups().get(&grp_id).unwrap();
+        assert_eq!(group, &vec![sh_id]);
+    }
 }
 
 // #[cfg(test)]
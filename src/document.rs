@@ -0,0 +1,809 @@
+use crate::cam::parameters::{CamParameters, Lead, LeadType};
+use crate::cam::postprocessor::Dialect;
+use crate::datapool::{GroupsPool, ShapesPool};
+use crate::shapes::cubicbezier::CubicBezier;
+use crate::shapes::ellipse::Ellipse;
+use crate::shapes::line::Line;
+use crate::shapes::polyline::Polyline;
+use crate::shapes::quadbezier::QuadBezier;
+use crate::shapes::rectangle::Rectangle;
+use crate::shapes::types::{Point, PointType, Shape, ShapeId, WPos};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// The native project file is a JSON document. The version is increased each
+// time the layout changes, older documents must still load.
+pub const DOCUMENT_FORMAT: &str = "plasmacadcam";
+pub const DOCUMENT_VERSION: usize = 1;
+pub const DOCUMENT_EXTENSION: &str = "pcam";
+
+// Everything saved along with the drawing
+#[derive(Debug, Clone)]
+pub struct ProjectSettings {
+    pub working_area: WPos,
+    pub visual_grid: f64,
+    pub snap_grid: f64,
+    pub cam_parameters: CamParameters,
+}
+
+pub struct Project {
+    pub settings: ProjectSettings,
+    pub shapes: Vec<Box<dyn Shape>>,
+    // Shapes of each group, as indexes in shapes
+    pub groups: Vec<Vec<usize>>,
+}
+
+// Projects are told apart from the other text files by their first character
+pub fn is_project_document(content: &str) -> bool {
+    content.trim_start().starts_with('{')
+}
+
+// JSON has no infinite nor NaN numbers, a drawing holding one can't be saved
+pub fn get_project_document(
+    shapes_pool: &ShapesPool,
+    groups_pool: &GroupsPool,
+    settings: &ProjectSettings,
+) -> Result<String, String> {
+    // Sorted by id, saving twice the same drawing gives the same file
+    let mut sh_ids: Vec<&ShapeId> = shapes_pool.keys().collect();
+    sh_ids.sort_by_key(|sh_id| ***sh_id);
+    let shapes = sh_ids
+        .iter()
+        .map(|sh_id| {
+            let shape = shapes_pool.get(sh_id).unwrap();
+            Json::Object(vec![
+                ("id".into(), Json::Number(***sh_id as f64)),
+                ("type".into(), Json::String(shape.get_type_name().into())),
+                ("position".into(), get_json_pos(&shape.get_pos())),
+                (
+                    "points".into(),
+                    Json::Array(
+                        shape
+                            .get_points()
+                            .iter()
+                            .map(|(point_type, point)| get_json_point(point_type, point))
+                            .collect(),
+                    ),
+                ),
+            ])
+        })
+        .collect();
+
+    let groups = groups_pool
+        .get_sorted_groups(shapes_pool)
+        .iter()
+        .map(|sh_ids| {
+            Json::Array(
+                sh_ids
+                    .iter()
+                    .map(|sh_id| Json::Number(**sh_id as f64))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    let document = Json::Object(vec![
+        ("format".into(), Json::String(DOCUMENT_FORMAT.into())),
+        ("version".into(), Json::Number(DOCUMENT_VERSION as f64)),
+        (
+            "working_area".into(),
+            Json::Object(vec![
+                ("width".into(), Json::Number(settings.working_area.wx)),
+                ("height".into(), Json::Number(settings.working_area.wy)),
+            ]),
+        ),
+        (
+            "grid".into(),
+            Json::Object(vec![
+                ("visual".into(), Json::Number(settings.visual_grid)),
+                ("snap".into(), Json::Number(settings.snap_grid)),
+            ]),
+        ),
+        ("cam".into(), get_json_cam(&settings.cam_parameters)),
+        ("shapes".into(), Json::Array(shapes)),
+        ("groups".into(), Json::Array(groups)),
+    ]);
+    if !document.is_finite() {
+        return Err("the drawing holds a value which is not a finite number".into());
+    }
+    let mut content = String::new();
+    document.write(&mut content, 0);
+    content.push('\n');
+    Ok(content)
+}
+
+pub fn get_project_from_document(content: &str) -> Result<Project, String> {
+    let document = Json::parse(content)?;
+    if document.get("format").and_then(Json::as_str) != Some(DOCUMENT_FORMAT) {
+        return Err("not a project file".into());
+    }
+    let version = document.get_number("version")? as usize;
+    if version > DOCUMENT_VERSION {
+        return Err(format!(
+            "project file version {} is newer than the supported version {}",
+            version, DOCUMENT_VERSION
+        ));
+    }
+
+    let working_area = document.get_field("working_area")?;
+    let grid = document.get_field("grid")?;
+    let settings = ProjectSettings {
+        working_area: WPos::new(
+            working_area.get_number("width")?,
+            working_area.get_number("height")?,
+        ),
+        visual_grid: grid.get_number("visual")?,
+        snap_grid: grid.get_number("snap")?,
+        cam_parameters: get_cam_from_json(document.get_field("cam")?)?,
+    };
+
+    // The ids of the file are only used to rebuild the groups
+    let mut shapes: Vec<Box<dyn Shape>> = vec![];
+    let mut sh_idxs: HashMap<usize, usize> = HashMap::new();
+    for json_shape in document.get_array("shapes")? {
+        let type_name = json_shape.get_str("type")?;
+        let position = get_pos_from_json(json_shape.get_field("position")?)?;
+        let mut points = vec![];
+        let mut vertex_idx = 0;
+        for json_point in json_shape.get_array("points")? {
+            let point_type = match json_point.get_str("type")? {
+                "vertex" => {
+                    vertex_idx += 1;
+                    PointType::Vertex(vertex_idx - 1)
+                }
+                name => get_point_type_from_name(name)
+                    .ok_or_else(|| format!("unknown point type \"{}\"", name))?,
+            };
+            let point = Point::new(
+                &WPos::new(json_point.get_number("x")?, json_point.get_number("y")?),
+                json_point.get_bool("magnetic")?,
+                json_point.get_bool("draggable")?,
+                json_point.get_bool("selected")?,
+            );
+            points.push((point_type, point));
+        }
+        let shape = get_shape_from_points(type_name, &position, &points)
+            .ok_or_else(|| format!("invalid shape of type \"{}\"", type_name))?;
+        sh_idxs.insert(json_shape.get_number("id")? as usize, shapes.len());
+        shapes.push(shape);
+    }
+
+    let mut groups = vec![];
+    for json_group in document.get_array("groups")? {
+        let Json::Array(json_ids) = json_group else {
+            return Err("a group must be an array of shape ids".into());
+        };
+        let mut group = vec![];
+        for json_id in json_ids {
+            let sh_id = json_id
+                .as_number()
+                .ok_or("a group must be an array of shape ids")? as usize;
+            let idx = *sh_idxs
+                .get(&sh_id)
+                .ok_or_else(|| format!("group of the unknown shape {}", sh_id))?;
+            group.push(idx);
+        }
+        groups.push(group);
+    }
+
+    Ok(Project {
+        settings,
+        shapes,
+        groups,
+    })
+}
+
+pub fn get_shape_from_points(
+    type_name: &str,
+    position: &WPos,
+    points: &[(PointType, Point)],
+) -> Option<Box<dyn Shape>> {
+    match type_name {
+        "line" => Some(Box::new(Line::from_points(position, points)?)),
+        "quadbezier" => Some(Box::new(QuadBezier::from_points(position, points)?)),
+        "cubicbezier" => Some(Box::new(CubicBezier::from_points(position, points)?)),
+        "rectangle" => Some(Box::new(Rectangle::from_points(position, points)?)),
+        "ellipse" => Some(Box::new(Ellipse::from_points(position, points)?)),
+        "polyline" => Some(Box::new(Polyline::from_points(position, points)?)),
+        _ => None,
+    }
+}
+
+fn get_point_type_name(point_type: &PointType) -> &str {
+    match point_type {
+        PointType::Position => "position",
+        PointType::Start => "start",
+        PointType::End => "end",
+        PointType::Center => "center",
+        PointType::Radius => "radius",
+        PointType::StartAngle => "start_angle",
+        PointType::EndAngle => "end_angle",
+        PointType::BL => "bl",
+        PointType::TL => "tl",
+        PointType::TR => "tr",
+        PointType::BR => "br",
+        PointType::Ctrl => "ctrl",
+        PointType::Ctrl1 => "ctrl1",
+        PointType::Ctrl2 => "ctrl2",
+        PointType::Vertex(_) => "vertex",
+        PointType::Axis => "axis",
+    }
+}
+fn get_point_type_from_name(name: &str) -> Option<PointType> {
+    match name {
+        "position" => Some(PointType::Position),
+        "start" => Some(PointType::Start),
+        "end" => Some(PointType::End),
+        "center" => Some(PointType::Center),
+        "radius" => Some(PointType::Radius),
+        "start_angle" => Some(PointType::StartAngle),
+        "end_angle" => Some(PointType::EndAngle),
+        "bl" => Some(PointType::BL),
+        "tl" => Some(PointType::TL),
+        "tr" => Some(PointType::TR),
+        "br" => Some(PointType::BR),
+        "ctrl" => Some(PointType::Ctrl),
+        "ctrl1" => Some(PointType::Ctrl1),
+        "ctrl2" => Some(PointType::Ctrl2),
+        "axis" => Some(PointType::Axis),
+        _ => None,
+    }
+}
+
+fn get_json_pos(pos: &WPos) -> Json {
+    Json::Array(vec![Json::Number(pos.wx), Json::Number(pos.wy)])
+}
+fn get_pos_from_json(json: &Json) -> Result<WPos, String> {
+    match json {
+        Json::Array(coords) if coords.len() == 2 => {
+            match (coords[0].as_number(), coords[1].as_number()) {
+                (Some(wx), Some(wy)) => Ok(WPos::new(wx, wy)),
+                _ => Err("a position must be an array of 2 numbers".into()),
+            }
+        }
+        _ => Err("a position must be an array of 2 numbers".into()),
+    }
+}
+
+fn get_json_point(point_type: &PointType, point: &Point) -> Json {
+    Json::Object(vec![
+        (
+            "type".into(),
+            Json::String(get_point_type_name(point_type).into()),
+        ),
+        ("x".into(), Json::Number(point.wpos.wx)),
+        ("y".into(), Json::Number(point.wpos.wy)),
+        ("magnetic".into(), Json::Bool(point.magnetic)),
+        ("draggable".into(), Json::Bool(point.draggable)),
+        ("selected".into(), Json::Bool(point.selected)),
+    ])
+}
+
+fn get_json_lead(lead: &Lead) -> Json {
+    Json::Object(vec![
+        (
+            "type".into(),
+            Json::String(lead.lead_type.get_name().into()),
+        ),
+        ("length".into(), Json::Number(lead.length)),
+        ("angle".into(), Json::Number(lead.angle)),
+    ])
+}
+fn get_lead_from_json(json: &Json) -> Result<Lead, String> {
+    let name = json.get_str("type")?;
+    Ok(Lead {
+        lead_type: LeadType::from_name(name)
+            .ok_or_else(|| format!("unknown lead type \"{}\"", name))?,
+        length: json.get_number("length")?,
+        angle: json.get_number("angle")?,
+    })
+}
+
+fn get_json_cam(cam_parameters: &CamParameters) -> Json {
+    let mut fields = vec![
+        ("feed_rate".into(), Json::Number(cam_parameters.feed_rate)),
+        (
+            "pierce_delay".into(),
+            Json::Number(cam_parameters.pierce_delay),
+        ),
+        ("tolerance".into(), Json::Number(cam_parameters.tolerance)),
+        ("kerf_width".into(), Json::Number(cam_parameters.kerf_width)),
+        ("lead_in".into(), get_json_lead(&cam_parameters.lead_in)),
+        ("lead_out".into(), get_json_lead(&cam_parameters.lead_out)),
+        (
+            "dialect".into(),
+            Json::String(cam_parameters.dialect.get_name().into()),
+        ),
+    ];
+    if let Dialect::Custom(template) = &cam_parameters.dialect {
+        fields.push(("template".into(), Json::String(template.clone())));
+    }
+    Json::Object(fields)
+}
+fn get_cam_from_json(json: &Json) -> Result<CamParameters, String> {
+    let name = json.get_str("dialect")?;
+    let template = json.get("template").and_then(Json::as_str).unwrap_or("");
+    Ok(CamParameters {
+        feed_rate: json.get_number("feed_rate")?,
+        pierce_delay: json.get_number("pierce_delay")?,
+        tolerance: json.get_number("tolerance")?,
+        kerf_width: json.get_number("kerf_width")?,
+        lead_in: get_lead_from_json(json.get_field("lead_in")?)?,
+        lead_out: get_lead_from_json(json.get_field("lead_out")?)?,
+        dialect: Dialect::from_name(name, template)
+            .ok_or_else(|| format!("unknown G-code dialect \"{}\"", name))?,
+    })
+}
+
+// Just enough JSON for the project files. The numbers are written with the
+// shortest representation that reads back to the same f64.
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+    fn get_field(&self, key: &str) -> Result<&Json, String> {
+        self.get(key)
+            .ok_or_else(|| format!("missing field \"{}\"", key))
+    }
+    fn get_number(&self, key: &str) -> Result<f64, String> {
+        self.get_field(key)?
+            .as_number()
+            .ok_or_else(|| format!("field \"{}\" must be a number", key))
+    }
+    fn get_str(&self, key: &str) -> Result<&str, String> {
+        self.get_field(key)?
+            .as_str()
+            .ok_or_else(|| format!("field \"{}\" must be a string", key))
+    }
+    fn get_bool(&self, key: &str) -> Result<bool, String> {
+        match self.get_field(key)? {
+            Json::Bool(value) => Ok(*value),
+            _ => Err(format!("field \"{}\" must be a boolean", key)),
+        }
+    }
+    fn get_array(&self, key: &str) -> Result<&Vec<Json>, String> {
+        match self.get_field(key)? {
+            Json::Array(values) => Ok(values),
+            _ => Err(format!("field \"{}\" must be an array", key)),
+        }
+    }
+
+    fn is_finite(&self) -> bool {
+        match self {
+            Json::Number(number) => number.is_finite(),
+            Json::Array(values) => values.iter().all(Json::is_finite),
+            Json::Object(fields) => fields.iter().all(|(_, value)| value.is_finite()),
+            _ => true,
+        }
+    }
+
+    // Objects are written one field per line, arrays of simple values on a
+    // single line, the numbers must be finite
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Json::Number(number) => {
+                let _ = write!(out, "{}", number);
+            }
+            Json::String(string) => write_json_string(out, string),
+            Json::Array(values) => {
+                let nested = values
+                    .iter()
+                    .any(|value| matches!(value, Json::Array(_) | Json::Object(_)));
+                out.push('[');
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                        if !nested {
+                            out.push(' ');
+                        }
+                    }
+                    if nested {
+                        out.push('\n');
+                        out.push_str(&"  ".repeat(indent + 1));
+                    }
+                    value.write(out, indent + 1);
+                }
+                if nested {
+                    out.push('\n');
+                    out.push_str(&"  ".repeat(indent));
+                }
+                out.push(']');
+            }
+            Json::Object(fields) => {
+                out.push('{');
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    out.push('\n');
+                    out.push_str(&"  ".repeat(indent + 1));
+                    write_json_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                if !fields.is_empty() {
+                    out.push('\n');
+                    out.push_str(&"  ".repeat(indent));
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    fn parse(content: &str) -> Result<Json, String> {
+        let mut parser = JsonParser {
+            chars: content.chars().collect(),
+            idx: 0,
+        };
+        let value = parser.parse_value()?;
+        parser.skip_whitespaces();
+        if parser.idx < parser.chars.len() {
+            return Err(parser.error("unexpected characters after the document"));
+        }
+        Ok(value)
+    }
+}
+
+fn write_json_string(out: &mut String, string: &str) {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    idx: usize,
+}
+impl JsonParser {
+    fn error(&self, message: &str) -> String {
+        format!(
+            "invalid project file at character {}: {}",
+            self.idx, message
+        )
+    }
+    fn skip_whitespaces(&mut self) {
+        while self.idx < self.chars.len() && self.chars[self.idx].is_whitespace() {
+            self.idx += 1;
+        }
+    }
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespaces();
+        self.chars.get(self.idx).copied()
+    }
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.idx += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{}' expected", c)))
+        }
+    }
+    fn parse_keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        let end = self.idx + keyword.len();
+        if end <= self.chars.len()
+            && self.chars[self.idx..end]
+                .iter()
+                .copied()
+                .eq(keyword.chars())
+        {
+            self.idx = end;
+            Ok(value)
+        } else {
+            Err(self.error("unknown value"))
+        }
+    }
+    fn parse_value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => Ok(Json::String(self.parse_string()?)),
+            Some('t') => self.parse_keyword("true", Json::Bool(true)),
+            Some('f') => self.parse_keyword("false", Json::Bool(false)),
+            Some('n') => self.parse_keyword("null", Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(_) => Err(self.error("unknown value")),
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+    fn parse_object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = vec![];
+        if self.peek() == Some('}') {
+            self.idx += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            if self.peek() != Some('"') {
+                return Err(self.error("field name expected"));
+            }
+            let key = self.parse_string()?;
+            self.expect(':')?;
+            fields.push((key, self.parse_value()?));
+            match self.peek() {
+                Some(',') => self.idx += 1,
+                Some('}') => {
+                    self.idx += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("',' or '}' expected")),
+            }
+        }
+    }
+    fn parse_array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut values = vec![];
+        if self.peek() == Some(']') {
+            self.idx += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.parse_value()?);
+            match self.peek() {
+                Some(',') => self.idx += 1,
+                Some(']') => {
+                    self.idx += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(self.error("',' or ']' expected")),
+            }
+        }
+    }
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            let Some(c) = self.chars.get(self.idx).copied() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.idx += 1;
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let Some(escaped) = self.chars.get(self.idx).copied() else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.idx += 1;
+                    match escaped {
+                        '"' | '\\' | '/' => string.push(escaped),
+                        'b' => string.push('\u{8}'),
+                        'f' => string.push('\u{c}'),
+                        'n' => string.push('\n'),
+                        'r' => string.push('\r'),
+                        't' => string.push('\t'),
+                        'u' => {
+                            let code = self.parse_hex4()?;
+                            // Characters outside the basic plane are split in
+                            // two surrogates
+                            let code = if (0xd800..0xdc00).contains(&code)
+                                && self.chars.get(self.idx) == Some(&'\\')
+                                && self.chars.get(self.idx + 1) == Some(&'u')
+                            {
+                                self.idx += 2;
+                                let low = self.parse_hex4()?;
+                                0x10000
+                                    + ((code - 0xd800) << 10)
+                                    + (low.wrapping_sub(0xdc00) & 0x3ff)
+                            } else {
+                                code
+                            };
+                            string.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        _ => return Err(self.error("unknown escape sequence")),
+                    }
+                }
+                c => string.push(c),
+            }
+        }
+    }
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let end = self.idx + 4;
+        if end > self.chars.len() {
+            return Err(self.error("unterminated string"));
+        }
+        let hex: String = self.chars[self.idx..end].iter().collect();
+        self.idx = end;
+        u32::from_str_radix(&hex, 16).map_err(|_| self.error("invalid unicode escape"))
+    }
+    fn parse_number(&mut self) -> Result<Json, String> {
+        let start = self.idx;
+        while self.idx < self.chars.len()
+            && matches!(
+                self.chars[self.idx],
+                '-' | '+' | '.' | 'e' | 'E' | '0'..='9'
+            )
+        {
+            self.idx += 1;
+        }
+        let number: String = self.chars[start..self.idx].iter().collect();
+        number
+            .parse::<f64>()
+            .map(Json::Number)
+            .map_err(|_| self.error("invalid number"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Transform;
+    use crate::shapes::types::GroupId;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_json_round_trip() {
+        let json = Json::Object(vec![
+            ("text".into(), Json::String("a \"quoted\"\n\\ é 😀".into())),
+            (
+                "numbers".into(),
+                Json::Array(vec![
+                    Json::Number(0.1 + 0.2),
+                    Json::Number(-1e-300),
+                    Json::Number(12.),
+                ]),
+            ),
+            ("empty".into(), Json::Object(vec![])),
+            (
+                "flags".into(),
+                Json::Array(vec![Json::Bool(true), Json::Null]),
+            ),
+        ]);
+        let mut content = String::new();
+        json.write(&mut content, 0);
+        assert_eq!(Json::parse(&content).unwrap(), json);
+        assert_eq!(Json::parse(r#""é😀""#).unwrap(), Json::String("é😀".into()));
+        assert!(Json::parse("{\"a\": 1,}").is_err());
+        assert!(Json::parse("[1, 2] 3").is_err());
+    }
+
+    #[test]
+    fn test_project_round_trip() {
+        let mut shapes_pool = ShapesPool(std::collections::HashMap::new());
+        let mut ellipse = Ellipse::new(&WPos::new(10., 20.), &WPos::new(25., 27.), 0.3, PI, 1.);
+        ellipse.init_done();
+        // The rotation is kept through the axis point
+        ellipse.transform(&Transform::rotate(0.5).around(&WPos::new(10., 20.)));
+        // A selected point must keep its flag
+        ellipse.select_point_type(&PointType::Radius);
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Line::new(&WPos::new(0.1, 0.2), &WPos::new(10.3, 1. / 3.)).unwrap()),
+            Box::new(
+                QuadBezier::new(&WPos::new(0., 0.), &WPos::new(5., 10.), &WPos::new(10., 0.))
+                    .unwrap(),
+            ),
+            Box::new(
+                CubicBezier::new(
+                    &WPos::new(0., 0.),
+                    &WPos::new(0., 10.),
+                    &WPos::new(10., 10.),
+                    &WPos::new(10., 0.),
+                )
+                .unwrap(),
+            ),
+            Box::new(Rectangle::new(&WPos::new(-5., 7.), 30., 12.5).unwrap()),
+            Box::new(ellipse),
+            Box::new(
+                Polyline::new(&[WPos::new(0., 0.), WPos::new(3., 4.), WPos::new(-2., 9.)]).unwrap(),
+            ),
+        ];
+        for (idx, shape) in shapes.into_iter().enumerate() {
+            shapes_pool.insert(ShapeId(100 + idx), shape);
+        }
+        let mut groups_pool = GroupsPool::new();
+        let grp_id = GroupId::new_id();
+        groups_pool.insert_shape_id(&grp_id, &ShapeId(101));
+        groups_pool.insert_shape_id(&grp_id, &ShapeId(104));
+
+        let settings = ProjectSettings {
+            working_area: WPos::new(1000., 500.),
+            visual_grid: 10.,
+            snap_grid: 0.5,
+            cam_parameters: CamParameters {
+                kerf_width: 1.2,
+                dialect: Dialect::Custom("[header]\nG21\n".into()),
+                ..Default::default()
+            },
+        };
+        let content = get_project_document(&shapes_pool, &groups_pool, &settings).unwrap();
+        let project = get_project_from_document(&content).unwrap();
+
+        assert_eq!(project.settings.working_area, settings.working_area);
+        assert_eq!(project.settings.snap_grid, 0.5);
+        assert_eq!(project.settings.cam_parameters.kerf_width, 1.2);
+        assert_eq!(
+            project.settings.cam_parameters.dialect,
+            settings.cam_parameters.dialect
+        );
+        assert_eq!(project.groups, vec![vec![1, 4]]);
+        assert_eq!(project.shapes.len(), 6);
+        for (idx, shape) in project.shapes.iter().enumerate() {
+            let original = shapes_pool.get(&ShapeId(100 + idx)).unwrap();
+            assert_eq!(shape.get_type_name(), original.get_type_name());
+            assert_eq!(shape.get_pos(), original.get_pos());
+            let points = shape.get_points();
+            let original_points = original.get_points();
+            assert_eq!(points.len(), original_points.len());
+            for ((pt, point), (original_pt, original_point)) in
+                points.iter().zip(original_points.iter())
+            {
+                assert_eq!(pt, original_pt);
+                assert_eq!(point.wpos, original_point.wpos);
+                assert_eq!(point.magnetic, original_point.magnetic);
+                assert_eq!(point.draggable, original_point.draggable);
+                assert_eq!(point.selected, original_point.selected);
+            }
+        }
+        // Saving the loaded project gives the same file
+        let mut shapes_pool_loaded = ShapesPool(std::collections::HashMap::new());
+        for (idx, shape) in project.shapes.into_iter().enumerate() {
+            shapes_pool_loaded.insert(ShapeId(100 + idx), shape);
+        }
+        assert_eq!(
+            get_project_document(&shapes_pool_loaded, &groups_pool, &project.settings).unwrap(),
+            content
+        );
+    }
+
+    #[test]
+    fn test_project_errors() {
+        assert!(get_project_from_document("{\"format\": \"other\"}").is_err());
+        let newer = format!(
+            "{{\"format\": \"{}\", \"version\": {}}}",
+            DOCUMENT_FORMAT,
+            DOCUMENT_VERSION + 1
+        );
+        assert!(get_project_from_document(&newer)
+            .err()
+            .unwrap()
+            .contains("newer"));
+        assert!(is_project_document("  {\"format\""));
+        assert!(!is_project_document("<svg>"));
+
+        // A value which can't be read back is not saved
+        let settings = ProjectSettings {
+            working_area: WPos::new(1000., 500.),
+            visual_grid: 10.,
+            snap_grid: f64::NAN,
+            cam_parameters: CamParameters::default(),
+        };
+        let shapes_pool = ShapesPool(std::collections::HashMap::new());
+        assert!(get_project_document(&shapes_pool, &GroupsPool::new(), &settings).is_err());
+    }
+}
@@ -0,0 +1,666 @@
+use crate::datapool::{GroupsPool, ShapesPool};
+use crate::math::*;
+use crate::shapes::types::{ConstructionType, Shape, ShapeId, WPos};
+use std::collections::HashSet;
+use std::f64::consts::PI;
+use std::fmt::Display;
+
+// R12 has neither ELLIPSE nor SPLINE, they are drawn with polylines having
+// this number of segments for a whole ellipse or a Bezier
+const NB_CURVE_SEGMENTS: usize = 64;
+
+// Layer of the shapes which are not in a group
+const DEFAULT_LAYER: &str = "0";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DxfVersion {
+    R12,
+    R2000,
+}
+impl DxfVersion {
+    fn get_acad_version(&self) -> &str {
+        match self {
+            DxfVersion::R12 => "AC1009",
+            DxfVersion::R2000 => "AC1015",
+        }
+    }
+}
+
+// The drawing is in millimeters, the bottom left corner of the working area
+// is the origin and the y axis goes up, every group has its own layer
+pub fn get_dxf_from_pools(
+    shapes_pool: &ShapesPool,
+    groups_pool: &GroupsPool,
+    working_area: &WPos,
+    version: DxfVersion,
+) -> String {
+    let groups = groups_pool.get_sorted_groups(shapes_pool);
+    // A shape in several groups is written once, on the layer of its first
+    // group
+    let mut written: HashSet<ShapeId> = HashSet::new();
+    let mut layers: Vec<(String, Vec<ShapeId>)> = groups
+        .into_iter()
+        .enumerate()
+        .map(|(idx, mut sh_ids)| {
+            sh_ids.retain(|sh_id| written.insert(*sh_id));
+            (format!("GROUP{}", idx + 1), sh_ids)
+        })
+        .collect();
+    let mut sh_ids: Vec<ShapeId> = shapes_pool
+        .keys()
+        .filter(|sh_id| !written.contains(sh_id))
+        .copied()
+        .collect();
+    sh_ids.sort_by_key(|sh_id| **sh_id);
+    layers.insert(0, (DEFAULT_LAYER.to_string(), sh_ids));
+
+    let mut writer = DxfWriter::new(version, working_area.wy);
+    if version == DxfVersion::R2000 {
+        writer.push(0, "SECTION");
+        writer.push(2, "CLASSES");
+        writer.push(0, "ENDSEC");
+    }
+    writer.push_tables(&layers);
+    writer.push_blocks();
+    writer.push(0, "SECTION");
+    writer.push(2, "ENTITIES");
+    for (layer, sh_ids) in layers.iter() {
+        for sh_id in sh_ids.iter() {
+            writer.push_shape(shapes_pool.get(sh_id).unwrap().as_ref(), layer);
+        }
+    }
+    writer.push(0, "ENDSEC");
+    if version == DxfVersion::R2000 {
+        writer.push_objects();
+    }
+    writer.push(0, "EOF");
+    let body = std::mem::take(&mut writer.content);
+
+    // The header comes last as it holds the next free handle
+    writer.push(0, "SECTION");
+    writer.push(2, "HEADER");
+    writer.push(9, "$ACADVER");
+    writer.push(1, version.get_acad_version());
+    if version == DxfVersion::R2000 {
+        writer.push(9, "$HANDSEED");
+        writer.push(5, format!("{:X}", writer.handle));
+        writer.push(9, "$INSUNITS");
+        writer.push(70, 4);
+    }
+    writer.push(9, "$MEASUREMENT");
+    writer.push(70, 1);
+    writer.push(9, "$INSBASE");
+    writer.push_pos(10, &WPos::zero());
+    writer.push(9, "$EXTMIN");
+    writer.push_pos(10, &WPos::zero());
+    writer.push(9, "$EXTMAX");
+    writer.push_pos(10, &WPos::new(working_area.wx, working_area.wy));
+    writer.push(0, "ENDSEC");
+    writer.content + &body
+}
+
+struct DxfWriter {
+    version: DxfVersion,
+    content: String,
+    // Next free handle, handles only exist since R13
+    handle: usize,
+    // Names and handles of the model and paper spaces, the model space owns
+    // the entities
+    block_records: Vec<(String, String)>,
+    height: f64,
+}
+impl DxfWriter {
+    fn new(version: DxfVersion, height: f64) -> DxfWriter {
+        DxfWriter {
+            version,
+            content: String::new(),
+            handle: 1,
+            block_records: vec![],
+            height,
+        }
+    }
+    fn has_handles(&self) -> bool {
+        self.version != DxfVersion::R12
+    }
+    fn new_handle(&mut self) -> String {
+        let handle = format!("{:X}", self.handle);
+        self.handle += 1;
+        handle
+    }
+    fn push(&mut self, code: i32, value: impl Display) {
+        self.content += &format!("{:>3}\n{}\n", code, value);
+    }
+    fn push_number(&mut self, code: i32, value: f64) {
+        // No negative zero
+        self.push(code, value + 0.);
+    }
+    fn push_pos(&mut self, code: i32, pos: &WPos) {
+        self.push_number(code, pos.wx);
+        self.push_number(code + 10, pos.wy);
+        self.push_number(code + 20, 0.);
+    }
+    // The drawing is in the xy plane
+    fn push_normal(&mut self) {
+        self.push_number(210, 0.);
+        self.push_number(220, 0.);
+        self.push_number(230, 1.);
+    }
+    // The y axis goes up in DXF
+    fn get_dxf_pos(&self, pos: &WPos) -> WPos {
+        WPos::new(pos.wx, self.height - pos.wy)
+    }
+
+    fn push_tables(&mut self, layers: &[(String, Vec<ShapeId>)]) {
+        self.push(0, "SECTION");
+        self.push(2, "TABLES");
+        if self.has_handles() {
+            self.push_table("VPORT", 0);
+            self.push_table_end();
+        }
+
+        let line_types: &[&str] = if self.has_handles() {
+            &["ByBlock", "ByLayer", "Continuous"]
+        } else {
+            &["CONTINUOUS"]
+        };
+        let table = self.push_table("LTYPE", line_types.len());
+        for line_type in line_types.iter() {
+            self.push_table_entry("LTYPE", &table, "AcDbLinetypeTableRecord");
+            self.push(2, line_type);
+            self.push(70, 0);
+            self.push(
+                3,
+                if *line_type == "ByBlock" || *line_type == "ByLayer" {
+                    ""
+                } else {
+                    "Solid line"
+                },
+            );
+            self.push(72, 65);
+            self.push(73, 0);
+            self.push_number(40, 0.);
+        }
+        self.push_table_end();
+
+        let table = self.push_table("LAYER", layers.len());
+        for (layer, _) in layers.iter() {
+            self.push_table_entry("LAYER", &table, "AcDbLayerTableRecord");
+            self.push(2, layer);
+            self.push(70, 0);
+            self.push(62, 7);
+            self.push(6, line_types[line_types.len() - 1]);
+        }
+        self.push_table_end();
+
+        if self.has_handles() {
+            let table = self.push_table("STYLE", 1);
+            self.push_table_entry("STYLE", &table, "AcDbTextStyleTableRecord");
+            self.push(2, "Standard");
+            self.push(70, 0);
+            self.push_number(40, 0.);
+            self.push_number(41, 1.);
+            self.push_number(50, 0.);
+            self.push(71, 0);
+            self.push_number(42, 2.5);
+            self.push(3, "txt");
+            self.push(4, "");
+            self.push_table_end();
+
+            for name in ["VIEW", "UCS"] {
+                self.push_table(name, 0);
+                self.push_table_end();
+            }
+
+            let table = self.push_table("APPID", 1);
+            self.push_table_entry("APPID", &table, "AcDbRegAppTableRecord");
+            self.push(2, "ACAD");
+            self.push(70, 0);
+            self.push_table_end();
+
+            self.push_table("DIMSTYLE", 0);
+            self.push_table_end();
+
+            let table = self.push_table("BLOCK_RECORD", 2);
+            for name in ["*Model_Space", "*Paper_Space"] {
+                let handle = self.push_table_entry("BLOCK_RECORD", &table, "AcDbBlockTableRecord");
+                self.push(2, name);
+                self.block_records.push((name.to_string(), handle));
+            }
+            self.push_table_end();
+        }
+        self.push(0, "ENDSEC");
+    }
+    fn push_table(&mut self, name: &str, nb_entries: usize) -> String {
+        self.push(0, "TABLE");
+        self.push(2, name);
+        let mut handle = String::new();
+        if self.has_handles() {
+            handle = self.new_handle();
+            self.push(5, &handle);
+            self.push(330, 0);
+            self.push(100, "AcDbSymbolTable");
+        }
+        self.push(70, nb_entries);
+        if self.has_handles() && name == "DIMSTYLE" {
+            self.push(100, "AcDbDimStyleTable");
+            self.push(71, 0);
+        }
+        handle
+    }
+    fn push_table_entry(&mut self, kind: &str, table: &str, subclass: &str) -> String {
+        self.push(0, kind);
+        let mut handle = String::new();
+        if self.has_handles() {
+            handle = self.new_handle();
+            self.push(5, &handle);
+            self.push(330, table);
+            self.push(100, "AcDbSymbolTableRecord");
+            self.push(100, subclass);
+        }
+        handle
+    }
+    fn push_table_end(&mut self) {
+        self.push(0, "ENDTAB");
+    }
+
+    fn push_blocks(&mut self) {
+        self.push(0, "SECTION");
+        self.push(2, "BLOCKS");
+        if self.has_handles() {
+            for (idx, (name, owner)) in self.block_records.clone().iter().enumerate() {
+                self.push(0, "BLOCK");
+                let handle = self.new_handle();
+                self.push(5, handle);
+                self.push(330, owner);
+                self.push(100, "AcDbEntity");
+                if idx == 1 {
+                    self.push(67, 1);
+                }
+                self.push(8, DEFAULT_LAYER);
+                self.push(100, "AcDbBlockBegin");
+                self.push(2, name);
+                self.push(70, 0);
+                self.push_pos(10, &WPos::zero());
+                self.push(3, name);
+                self.push(1, "");
+                self.push(0, "ENDBLK");
+                let handle = self.new_handle();
+                self.push(5, handle);
+                self.push(330, owner);
+                self.push(100, "AcDbEntity");
+                if idx == 1 {
+                    self.push(67, 1);
+                }
+                self.push(8, DEFAULT_LAYER);
+                self.push(100, "AcDbBlockEnd");
+            }
+        }
+        self.push(0, "ENDSEC");
+    }
+
+    fn push_objects(&mut self) {
+        let root = self.new_handle();
+        let groups = self.new_handle();
+        self.push(0, "SECTION");
+        self.push(2, "OBJECTS");
+        self.push(0, "DICTIONARY");
+        self.push(5, &root);
+        self.push(330, 0);
+        self.push(100, "AcDbDictionary");
+        self.push(281, 1);
+        self.push(3, "ACAD_GROUP");
+        self.push(350, &groups);
+        self.push(0, "DICTIONARY");
+        self.push(5, &groups);
+        self.push(330, &root);
+        self.push(100, "AcDbDictionary");
+        self.push(281, 1);
+        self.push(0, "ENDSEC");
+    }
+
+    fn push_entity(&mut self, kind: &str, layer: &str, subclass: &str) {
+        self.push(0, kind);
+        if self.has_handles() {
+            let handle = self.new_handle();
+            self.push(5, handle);
+            self.push(330, self.block_records[0].1.clone());
+            self.push(100, "AcDbEntity");
+        }
+        self.push(8, layer);
+        if self.has_handles() && !subclass.is_empty() {
+            self.push(100, subclass);
+        }
+    }
+
+    fn push_shape(&mut self, shape: &dyn Shape, layer: &str) {
+        let mut pos = WPos::zero();
+        for cst in shape.get_construction().iter() {
+            match cst {
+                ConstructionType::Move(end) => pos = *end,
+                ConstructionType::Line(end) => {
+                    self.push_line(layer, &pos, end);
+                    pos = *end;
+                }
+                ConstructionType::QuadBezier(ctrl, end) => {
+                    self.push_bezier(layer, &[pos, *ctrl, *end]);
+                    pos = *end;
+                }
+                ConstructionType::CubicBezier(ctrl1, ctrl2, end) => {
+                    self.push_bezier(layer, &[pos, *ctrl1, *ctrl2, *end]);
+                    pos = *end;
+                }
+                ConstructionType::Ellipse(center, radius, rotation, start_angle, end_angle, _) => {
+                    let sweep = get_sweep_angle(*start_angle, *end_angle);
+                    self.push_ellipse(layer, center, radius, *rotation, *start_angle, sweep);
+                    pos = get_ellipse_pos(center, radius, *rotation, start_angle + sweep);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn push_line(&mut self, layer: &str, start: &WPos, end: &WPos) {
+        self.push_entity("LINE", layer, "AcDbLine");
+        self.push_pos(10, &self.get_dxf_pos(start));
+        self.push_pos(11, &self.get_dxf_pos(end));
+    }
+
+    fn push_polyline(&mut self, layer: &str, pts: &[WPos], closed: bool) {
+        self.push_entity("POLYLINE", layer, "AcDb2dPolyline");
+        self.push(66, 1);
+        self.push_pos(10, &WPos::zero());
+        self.push(70, closed as u8);
+        for pt in pts.iter() {
+            self.push_entity("VERTEX", layer, "AcDbVertex");
+            if self.has_handles() {
+                self.push(100, "AcDb2dVertex");
+            }
+            self.push_pos(10, &self.get_dxf_pos(pt));
+        }
+        self.push_entity("SEQEND", layer, "");
+    }
+
+    // A clamped spline with the same control points, in R12 a polyline
+    fn push_bezier(&mut self, layer: &str, ctrl_pts: &[WPos]) {
+        let get_bezier_pos = |t: f64| match ctrl_pts {
+            [start, ctrl, end] => get_point_on_quad_bezier(t, start, ctrl, end),
+            [start, ctrl1, ctrl2, end] => get_point_on_cubic_bezier(t, start, ctrl1, ctrl2, end),
+            _ => unreachable!(),
+        };
+        if !self.has_handles() {
+            let pts: Vec<WPos> = (0..=NB_CURVE_SEGMENTS)
+                .map(|i| get_bezier_pos(i as f64 / NB_CURVE_SEGMENTS as f64))
+                .collect();
+            self.push_polyline(layer, &pts, false);
+            return;
+        }
+        let degree = ctrl_pts.len() - 1;
+        self.push_entity("SPLINE", layer, "AcDbSpline");
+        self.push_normal();
+        self.push(70, 8);
+        self.push(71, degree);
+        self.push(72, 2 * ctrl_pts.len());
+        self.push(73, ctrl_pts.len());
+        self.push(74, 0);
+        self.push_number(42, 1e-10);
+        self.push_number(43, 1e-10);
+        for knot in 0..2 * ctrl_pts.len() {
+            self.push_number(40, (knot >= ctrl_pts.len()) as u8 as f64);
+        }
+        for pt in ctrl_pts.iter() {
+            self.push_pos(10, &self.get_dxf_pos(pt));
+        }
+    }
+
+    // The sweep goes from the start angle, it is negative when the angle
+    // decreases
+    fn push_ellipse(
+        &mut self,
+        layer: &str,
+        center: &WPos,
+        radius: &WPos,
+        rotation: f64,
+        start_angle: f64,
+        sweep: f64,
+    ) {
+        let full = sweep.abs() > 2. * PI - 1e-6;
+        let (rx, ry) = (radius.wx.abs(), radius.wy.abs());
+        let is_circle = (rx - ry).abs() < 1e-9 * rx.max(ry);
+        if !self.has_handles() && !is_circle {
+            let nb_segments =
+                ((NB_CURVE_SEGMENTS as f64 * sweep.abs() / (2. * PI)).ceil() as usize).max(1);
+            let mut pts: Vec<WPos> = (0..=nb_segments)
+                .map(|i| {
+                    let angle = start_angle + sweep * i as f64 / nb_segments as f64;
+                    get_ellipse_pos(center, radius, rotation, angle)
+                })
+                .collect();
+            if full {
+                pts.pop();
+            }
+            self.push_polyline(layer, &pts, full);
+            return;
+        }
+
+        // Flipping the y axis turns the angles the other way round, in DXF
+        // the arcs go counterclockwise from their start to their end
+        let center = self.get_dxf_pos(center);
+        let rotation = -rotation;
+        let (from, to) = (-start_angle, -start_angle - sweep);
+        let (start_angle, end_angle) = if to > from { (from, to) } else { (to, from) };
+        if is_circle {
+            if full {
+                self.push_entity("CIRCLE", layer, "AcDbCircle");
+                self.push_pos(10, &center);
+                self.push_number(40, rx);
+            } else {
+                self.push_entity("ARC", layer, "AcDbCircle");
+                self.push_pos(10, &center);
+                self.push_number(40, rx);
+                if self.has_handles() {
+                    self.push(100, "AcDbArc");
+                }
+                self.push_number(50, (start_angle + rotation).to_degrees().rem_euclid(360.));
+                self.push_number(51, (end_angle + rotation).to_degrees().rem_euclid(360.));
+            }
+            return;
+        }
+
+        // The major axis is along the largest radius
+        let (major_angle, ratio, shift) = if rx >= ry {
+            (rotation, ry / rx, 0.)
+        } else {
+            (rotation + PI / 2., rx / ry, -PI / 2.)
+        };
+        let (sin, cos) = major_angle.sin_cos();
+        let major_radius = rx.max(ry);
+        let (start_param, end_param) = if full {
+            (0., 2. * PI)
+        } else {
+            (
+                (start_angle + shift).rem_euclid(2. * PI),
+                (end_angle + shift).rem_euclid(2. * PI),
+            )
+        };
+        self.push_entity("ELLIPSE", layer, "AcDbEllipse");
+        self.push_pos(10, &center);
+        self.push_pos(11, &WPos::new(major_radius * cos, major_radius * sin));
+        self.push_normal();
+        self.push_number(40, ratio);
+        self.push_number(41, start_param);
+        self.push_number(42, end_param);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dxfimport::get_shapes_from_dxf;
+    use crate::import::ImportOptions;
+    use crate::shapes::cubicbezier::CubicBezier;
+    use crate::shapes::ellipse::Ellipse;
+    use crate::shapes::line::Line;
+    use crate::shapes::rectangle::Rectangle;
+    use crate::shapes::types::GroupId;
+    use std::collections::HashMap;
+
+    fn get_pools() -> (ShapesPool, GroupsPool) {
+        let mut shapes_pool = ShapesPool(HashMap::new());
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Rectangle::new(&WPos::new(10., 10.), 30., 20.).unwrap()),
+            Box::new(
+                Ellipse::from_arc(&WPos::new(50., 50.), &WPos::new(10., 10.), 0., 0., 0.).unwrap(),
+            ),
+            Box::new(Line::new(&WPos::new(0., 0.), &WPos::new(100., 100.)).unwrap()),
+            Box::new(
+                Ellipse::from_arc(&WPos::new(200., 100.), &WPos::new(40., 20.), 0., 0.5, 2.)
+                    .unwrap(),
+            ),
+            Box::new(
+                Ellipse::from_arc(&WPos::new(200., 200.), &WPos::new(20., 40.), 0., 5., 1.)
+                    .unwrap(),
+            ),
+            Box::new(
+                CubicBezier::new(
+                    &WPos::new(0., 200.),
+                    &WPos::new(20., 150.),
+                    &WPos::new(60., 250.),
+                    &WPos::new(80., 200.),
+                )
+                .unwrap(),
+            ),
+            Box::new(
+                Ellipse::from_arc(&WPos::new(300., 100.), &WPos::new(10., 10.), 0., 6., 1.)
+                    .unwrap(),
+            ),
+        ];
+        for (idx, shape) in shapes.into_iter().enumerate() {
+            shapes_pool.insert(ShapeId(idx + 1), shape);
+        }
+        let mut groups_pool = GroupsPool::new();
+        let grp_id = GroupId::new_id();
+        groups_pool.insert_shape_id(&grp_id, &ShapeId(1));
+        groups_pool.insert_shape_id(&grp_id, &ShapeId(2));
+        (shapes_pool, groups_pool)
+    }
+
+    // Every point of the shapes of a side is on a shape of the other side
+    fn assert_same_drawing(shapes: &[&dyn Shape], imported_shapes: &[&dyn Shape], precision: f64) {
+        for (from, to) in [(shapes, imported_shapes), (imported_shapes, shapes)] {
+            for shape in from.iter() {
+                for i in 0..=20 {
+                    let pos = shape.get_pos_from_ratio(i as f64 / 20.);
+                    let dist = to
+                        .iter()
+                        .map(|shape| shape.dist(&pos))
+                        .fold(f64::MAX, f64::min);
+                    assert!(
+                        dist < precision,
+                        "{} {:?} {}",
+                        shape.get_type_name(),
+                        pos,
+                        dist
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dxf_export_r2000() {
+        let (shapes_pool, groups_pool) = get_pools();
+        let working_area = WPos::new(1000., 500.);
+        let content =
+            get_dxf_from_pools(&shapes_pool, &groups_pool, &working_area, DxfVersion::R2000);
+        assert!(content.contains("AC1015"));
+
+        // The handles are unique and below the seed
+        let lines: Vec<&str> = content.lines().map(|line| line.trim()).collect();
+        let mut handles: Vec<usize> = lines
+            .chunks(2)
+            .filter(|pair| pair[0] == "5")
+            .map(|pair| usize::from_str_radix(pair[1], 16).unwrap())
+            .collect();
+        let seed = handles.remove(0);
+        let nb_handles = handles.len();
+        handles.sort();
+        handles.dedup();
+        assert_eq!(handles.len(), nb_handles);
+        assert!(handles.iter().all(|handle| *handle < seed));
+
+        let options = ImportOptions {
+            working_area,
+            ..ImportOptions::default()
+        };
+        let (groups, report) = get_shapes_from_dxf(&content, &options);
+        assert!(report.is_clean(), "{}", report.get_summary());
+        let names: Vec<Vec<&str>> = groups
+            .iter()
+            .map(|group| group.iter().map(|shape| shape.get_type_name()).collect())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                vec!["line", "ellipse", "ellipse", "cubicbezier", "ellipse"],
+                vec!["line", "line", "line", "line", "ellipse"],
+            ]
+        );
+        let shapes: Vec<&dyn Shape> = shapes_pool.values().map(|shape| shape.as_ref()).collect();
+        let imported_shapes: Vec<&dyn Shape> = groups
+            .iter()
+            .flat_map(|group| group.iter().map(|shape| shape.as_ref()))
+            .collect();
+        // Imported back on the same sheet, the drawing is where it was
+        assert_same_drawing(&shapes, &imported_shapes, 1e-3);
+    }
+
+    #[test]
+    fn test_dxf_export_r12() {
+        let (shapes_pool, groups_pool) = get_pools();
+        let working_area = WPos::new(1000., 500.);
+        let content =
+            get_dxf_from_pools(&shapes_pool, &groups_pool, &working_area, DxfVersion::R12);
+        assert!(content.contains("AC1009"));
+        assert!(content.contains("GROUP1"));
+        assert!(!content.contains("ELLIPSE") && !content.contains("SPLINE"));
+
+        let options = ImportOptions {
+            working_area,
+            ..ImportOptions::default()
+        };
+        let (groups, report) = get_shapes_from_dxf(&content, &options);
+        assert!(report.is_clean(), "{}", report.get_summary());
+        assert_eq!(groups.len(), 2);
+        assert!(groups
+            .iter()
+            .flatten()
+            .all(|shape| ["line", "ellipse"].contains(&shape.get_type_name())));
+        let shapes: Vec<&dyn Shape> = shapes_pool.values().map(|shape| shape.as_ref()).collect();
+        let imported_shapes: Vec<&dyn Shape> = groups
+            .iter()
+            .flat_map(|group| group.iter().map(|shape| shape.as_ref()))
+            .collect();
+        assert_same_drawing(&shapes, &imported_shapes, 0.1);
+    }
+
+    #[test]
+    fn test_dxf_export_shape_in_several_groups() {
+        let (shapes_pool, mut groups_pool) = get_pools();
+        let grp_id = GroupId::new_id();
+        groups_pool.insert_shape_id(&grp_id, &ShapeId(1));
+        groups_pool.insert_shape_id(&grp_id, &ShapeId(3));
+        let working_area = WPos::new(1000., 500.);
+        let content =
+            get_dxf_from_pools(&shapes_pool, &groups_pool, &working_area, DxfVersion::R2000);
+
+        let options = ImportOptions {
+            working_area,
+            ..ImportOptions::default()
+        };
+        let (groups, report) = get_shapes_from_dxf(&content, &options);
+        assert!(report.is_clean(), "{}", report.get_summary());
+        // The rectangle is written with its first group only
+        let nb_shapes: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+        assert_eq!(nb_shapes.iter().sum::<usize>(), 10);
+    }
+}
@@ -0,0 +1,736 @@
+use crate::import::{
+    get_placement, push_cubic_bezier, push_ellipse_arc, push_line, push_quad_bezier, ImportOptions,
+    ImportReport,
+};
+use crate::math::Transform;
+use crate::shapes::types::{Shape, WPos};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+// Deeper block references are taken for a block inserting itself
+const MAX_INSERT_DEPTH: usize = 16;
+
+// Code and value of a group, with its line in the file
+#[derive(Clone, Copy, Debug)]
+struct DxfPair<'a> {
+    code: i32,
+    value: &'a str,
+    line: usize,
+}
+
+// An entity, or any other record started by a group of code 0, with its
+// groups in the order of the file
+struct DxfEntity<'a> {
+    kind: &'a str,
+    line: usize,
+    pairs: Vec<DxfPair<'a>>,
+}
+impl<'a> DxfEntity<'a> {
+    fn get_str(&self, code: i32) -> Option<&'a str> {
+        self.pairs
+            .iter()
+            .find(|pair| pair.code == code)
+            .map(|pair| pair.value)
+    }
+    fn get_f64(&self, code: i32) -> Option<f64> {
+        self.get_str(code).and_then(|value| value.parse().ok())
+    }
+    fn get_i64(&self, code: i32) -> Option<i64> {
+        self.get_str(code).and_then(|value| value.parse().ok())
+    }
+    fn get_all_f64(&self, code: i32) -> Vec<f64> {
+        self.pairs
+            .iter()
+            .filter(|pair| pair.code == code)
+            .filter_map(|pair| pair.value.parse().ok())
+            .collect()
+    }
+    // The y coordinate has the code of the x one plus 10
+    fn get_pos(&self, code: i32) -> WPos {
+        WPos::new(
+            self.get_f64(code).unwrap_or(0.),
+            self.get_f64(code + 10).unwrap_or(0.),
+        )
+    }
+    // Positions of the repeated coordinates, a new one starts on each x
+    fn get_all_pos(&self, code: i32) -> Vec<WPos> {
+        let mut positions: Vec<WPos> = vec![];
+        for pair in self.pairs.iter() {
+            let Ok(value) = pair.value.parse::<f64>() else {
+                continue;
+            };
+            if pair.code == code {
+                positions.push(WPos::new(value, 0.));
+            } else if pair.code == code + 10 {
+                if let Some(pos) = positions.last_mut() {
+                    pos.wy = value;
+                }
+            }
+        }
+        positions
+    }
+    // Entity coordinate system of the planar entities. Seen from the sheet,
+    // an extrusion toward -z mirrors the x axis.
+    fn get_ocs(&self) -> Transform {
+        if self.get_f64(230).unwrap_or(1.) < 0. {
+            Transform::scale(-1., 1.)
+        } else {
+            Transform::identity()
+        }
+    }
+}
+
+struct DxfDocument<'a> {
+    insunits: Option<i64>,
+    // Base point and entities of the blocks, by name
+    blocks: HashMap<&'a str, (WPos, Vec<DxfEntity<'a>>)>,
+    entities: Vec<DxfEntity<'a>>,
+}
+
+// Shapes of an ASCII DXF file, in millimeters and placed on the working sheet,
+// with the report of the import. The entities of the model space are grouped
+// by layer, the ones of each block reference are grouped together.
+pub fn get_shapes_from_dxf(
+    content: &str,
+    options: &ImportOptions,
+) -> (Vec<Vec<Box<dyn Shape>>>, ImportReport) {
+    let mut report = ImportReport::new();
+    if content.starts_with("AutoCAD Binary DXF") {
+        report.add_error(0, "binary DXF files are not supported".into());
+        return (vec![], report);
+    }
+    let pairs = get_pairs(content, &mut report);
+    let document = get_document(&pairs);
+
+    let mm_per_unit = match document.insunits {
+        None | Some(0) => 1.,
+        Some(insunits) => get_mm_per_unit(insunits).unwrap_or_else(|| {
+            report.add_warning(
+                0,
+                format!("unknown $INSUNITS {}, millimeters are used", insunits),
+            );
+            1.
+        }),
+    };
+    // The y axis of the DXF goes up from the bottom left of the sheet, the
+    // one of the sheet goes down from its top left
+    let flip = if options.flip_y {
+        Transform::identity()
+    } else {
+        Transform::translate(0., options.working_area.wy) * Transform::scale(1., -1.)
+    };
+    let transform = flip
+        * Transform::scale(options.scale, options.scale)
+        * Transform::scale(mm_per_unit, mm_per_unit);
+    let mut groups = get_groups(&document, &transform, &mut report);
+    if let Some(placement) = get_placement(groups.iter().flatten(), options) {
+        // The entities are read again with the offset, the report is the same
+        groups = get_groups(
+            &document,
+            &(placement * transform),
+            &mut ImportReport::new(),
+        );
+    }
+    report.nb_shapes = groups.iter().map(|group| group.len()).sum();
+    (groups, report)
+}
+
+// Millimeters in the unit of the $INSUNITS header variable
+fn get_mm_per_unit(insunits: i64) -> Option<f64> {
+    match insunits {
+        1 => Some(25.4),
+        2 => Some(304.8),
+        3 => Some(1_609_344.),
+        4 => Some(1.),
+        5 => Some(10.),
+        6 => Some(1000.),
+        7 => Some(1_000_000.),
+        8 => Some(25.4e-6),
+        9 => Some(25.4e-3),
+        10 => Some(914.4),
+        11 => Some(1e-7),
+        12 => Some(1e-6),
+        13 => Some(1e-3),
+        14 => Some(100.),
+        _ => None,
+    }
+}
+
+// A group is a line with its code followed by a line with its value
+fn get_pairs<'a>(content: &'a str, report: &mut ImportReport) -> Vec<DxfPair<'a>> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut pairs = vec![];
+    for (idx, group) in lines.chunks_exact(2).enumerate() {
+        let line = 2 * idx + 1;
+        match group[0].trim().parse::<i32>() {
+            Ok(code) => pairs.push(DxfPair {
+                code,
+                value: group[1].trim(),
+                line,
+            }),
+            Err(_) => {
+                // The codes and the values are mixed up from here
+                report.add_error(line, format!("invalid group code \"{}\"", group[0].trim()));
+                break;
+            }
+        }
+    }
+    pairs
+}
+
+fn get_document<'a>(pairs: &[DxfPair<'a>]) -> DxfDocument<'a> {
+    let mut records: Vec<DxfEntity<'a>> = vec![];
+    for pair in pairs.iter() {
+        if pair.code == 0 {
+            records.push(DxfEntity {
+                kind: pair.value,
+                line: pair.line,
+                pairs: vec![],
+            });
+        } else if let Some(record) = records.last_mut() {
+            record.pairs.push(*pair);
+        }
+    }
+
+    let mut document = DxfDocument {
+        insunits: None,
+        blocks: HashMap::new(),
+        entities: vec![],
+    };
+    let mut section = "";
+    let mut block: Option<(&str, WPos, Vec<DxfEntity>)> = None;
+    for record in records {
+        match record.kind {
+            "SECTION" => {
+                section = record.get_str(2).unwrap_or("");
+                if section == "HEADER" {
+                    // The variables are all in the record of the section
+                    document.insunits = record
+                        .pairs
+                        .windows(2)
+                        .find(|pairs| pairs[0].value == "$INSUNITS" && pairs[1].code == 70)
+                        .and_then(|pairs| pairs[1].value.parse().ok());
+                }
+            }
+            "ENDSEC" => section = "",
+            "BLOCK" if section == "BLOCKS" => {
+                block = Some((record.get_str(2).unwrap_or(""), record.get_pos(10), vec![]));
+            }
+            "ENDBLK" => {
+                if let Some((name, base, entities)) = block.take() {
+                    document.blocks.insert(name, (base, entities));
+                }
+            }
+            _ => match section {
+                "BLOCKS" => {
+                    if let Some((_, _, entities)) = block.as_mut() {
+                        entities.push(record);
+                    }
+                }
+                "ENTITIES" => document.entities.push(record),
+                _ => (),
+            },
+        }
+    }
+    document
+}
+
+fn get_groups(
+    document: &DxfDocument,
+    transform: &Transform,
+    report: &mut ImportReport,
+) -> Vec<Vec<Box<dyn Shape>>> {
+    let mut groups: Vec<Vec<Box<dyn Shape>>> = vec![];
+    let mut layers: HashMap<&str, usize> = HashMap::new();
+    let entities = &document.entities;
+    let mut idx = 0;
+    while idx < entities.len() {
+        let entity = &entities[idx];
+        let group_idx = if entity.kind == "INSERT" {
+            groups.push(vec![]);
+            groups.len() - 1
+        } else {
+            let layer = entity.get_str(8).unwrap_or("0");
+            *layers.entry(layer).or_insert_with(|| {
+                groups.push(vec![]);
+                groups.len() - 1
+            })
+        };
+        idx = push_entity_shapes(
+            document,
+            entities,
+            idx,
+            transform,
+            0,
+            &mut groups[group_idx],
+            report,
+        );
+    }
+    groups.retain(|group| !group.is_empty());
+    groups
+}
+
+// Shapes of the entity at the index, returns the index of the next entity.
+// The vertices of a polyline are entities following it.
+fn push_entity_shapes(
+    document: &DxfDocument,
+    entities: &[DxfEntity],
+    idx: usize,
+    transform: &Transform,
+    depth: usize,
+    shapes: &mut Vec<Box<dyn Shape>>,
+    report: &mut ImportReport,
+) -> usize {
+    let entity = &entities[idx];
+    if entity.get_i64(67) == Some(1) {
+        report.add_skipped(entity.line, format!("{} in paper space", entity.kind));
+        return idx + 1;
+    }
+    let ocs_transform = *transform * entity.get_ocs();
+    match entity.kind {
+        "LINE" => push_line(&entity.get_pos(10), &entity.get_pos(11), transform, shapes),
+        "CIRCLE" | "ARC" => {
+            let radius = entity.get_f64(40).unwrap_or(0.);
+            if radius <= 0. {
+                report.add_skipped(entity.line, format!("{} without radius", entity.kind));
+                return idx + 1;
+            }
+            let (start_angle, sweep_angle) = if entity.kind == "ARC" {
+                let start_angle = entity.get_f64(50).unwrap_or(0.).to_radians();
+                let end_angle = entity.get_f64(51).unwrap_or(0.).to_radians();
+                (start_angle, get_ccw_sweep(start_angle, end_angle))
+            } else {
+                (0., 2. * PI)
+            };
+            push_ellipse_arc(
+                &entity.get_pos(10),
+                &WPos::new(radius, radius),
+                0.,
+                start_angle,
+                sweep_angle,
+                &ocs_transform,
+                shapes,
+            );
+        }
+        "ELLIPSE" => {
+            // From the unit circle to the ellipse, the minor axis is the major
+            // one turned by a quarter around the extrusion
+            let center = entity.get_pos(10);
+            let major = entity.get_pos(11);
+            let ratio = entity.get_f64(40).unwrap_or(1.);
+            let minor = if entity.get_f64(230).unwrap_or(1.) < 0. {
+                WPos::new(major.wy, -major.wx) * ratio
+            } else {
+                WPos::new(-major.wy, major.wx) * ratio
+            };
+            let ellipse_transform =
+                Transform::new(major.wx, major.wy, minor.wx, minor.wy, center.wx, center.wy);
+            let start_param = entity.get_f64(41).unwrap_or(0.);
+            let end_param = entity.get_f64(42).unwrap_or(2. * PI);
+            push_ellipse_arc(
+                &WPos::zero(),
+                &WPos::new(1., 1.),
+                0.,
+                start_param,
+                get_ccw_sweep(start_param, end_param),
+                &(*transform * ellipse_transform),
+                shapes,
+            );
+        }
+        "LWPOLYLINE" => {
+            let vertices = get_lwpolyline_vertices(entity);
+            let closed = entity.get_i64(70).unwrap_or(0) & 1 != 0;
+            push_polyline_shapes(&vertices, closed, &ocs_transform, shapes);
+        }
+        "POLYLINE" => {
+            let mut vertices = vec![];
+            let mut next_idx = idx + 1;
+            while next_idx < entities.len() && entities[next_idx].kind == "VERTEX" {
+                let vertex = &entities[next_idx];
+                // Frame points of a spline fitted polyline aren't on it
+                if vertex.get_i64(70).unwrap_or(0) & 16 == 0 {
+                    vertices.push((vertex.get_pos(10), vertex.get_f64(42).unwrap_or(0.)));
+                }
+                next_idx += 1;
+            }
+            if next_idx < entities.len() && entities[next_idx].kind == "SEQEND" {
+                next_idx += 1;
+            }
+            let flags = entity.get_i64(70).unwrap_or(0);
+            if flags & (16 | 64) != 0 {
+                report.add_skipped(entity.line, "polygon mesh".into());
+            } else {
+                push_polyline_shapes(&vertices, flags & 1 != 0, &ocs_transform, shapes);
+            }
+            return next_idx;
+        }
+        "SPLINE" => push_spline_shapes(entity, transform, shapes, report),
+        "INSERT" => {
+            let name = entity.get_str(2).unwrap_or("");
+            let Some((base, block_entities)) = document.blocks.get(name) else {
+                report.add_error(entity.line, format!("unknown block \"{}\"", name));
+                return idx + 1;
+            };
+            if depth >= MAX_INSERT_DEPTH {
+                report.add_error(
+                    entity.line,
+                    format!("block \"{}\" inserted in itself", name),
+                );
+                return idx + 1;
+            }
+            if entity.get_i64(70).unwrap_or(1) > 1 || entity.get_i64(71).unwrap_or(1) > 1 {
+                report.add_warning(
+                    entity.line,
+                    format!("only the first of the array of \"{}\" is inserted", name),
+                );
+            }
+            let insert_pos = entity.get_pos(10);
+            let insert_transform = ocs_transform
+                * Transform::translate(insert_pos.wx, insert_pos.wy)
+                * Transform::rotate(entity.get_f64(50).unwrap_or(0.).to_radians())
+                * Transform::scale(
+                    entity.get_f64(41).unwrap_or(1.),
+                    entity.get_f64(42).unwrap_or(1.),
+                )
+                * Transform::translate(-base.wx, -base.wy);
+            let mut block_idx = 0;
+            while block_idx < block_entities.len() {
+                block_idx = push_entity_shapes(
+                    document,
+                    block_entities,
+                    block_idx,
+                    &insert_transform,
+                    depth + 1,
+                    shapes,
+                    report,
+                );
+            }
+        }
+        // Parts of the entities already read
+        "VERTEX" | "SEQEND" | "ATTRIB" => (),
+        kind => report.add_skipped(entity.line, format!("{} is not supported", kind)),
+    }
+    idx + 1
+}
+
+// The angles of the DXF go counterclockwise, an arc ending where it starts is
+// a whole turn
+fn get_ccw_sweep(start_angle: f64, end_angle: f64) -> f64 {
+    let sweep = (end_angle - start_angle).rem_euclid(2. * PI);
+    if sweep < 1e-9 {
+        2. * PI
+    } else {
+        sweep
+    }
+}
+
+// Vertices with the bulge of the segment they start
+fn get_lwpolyline_vertices(entity: &DxfEntity) -> Vec<(WPos, f64)> {
+    let mut vertices: Vec<(WPos, f64)> = vec![];
+    for pair in entity.pairs.iter() {
+        let Ok(value) = pair.value.parse::<f64>() else {
+            continue;
+        };
+        match pair.code {
+            10 => vertices.push((WPos::new(value, 0.), 0.)),
+            20 => {
+                if let Some(vertex) = vertices.last_mut() {
+                    vertex.0.wy = value;
+                }
+            }
+            42 => {
+                if let Some(vertex) = vertices.last_mut() {
+                    vertex.1 = value;
+                }
+            }
+            _ => (),
+        }
+    }
+    vertices
+}
+
+// The bulge of a segment is the tangent of a quarter of the angle of its arc,
+// positive counterclockwise
+fn push_polyline_shapes(
+    vertices: &[(WPos, f64)],
+    closed: bool,
+    transform: &Transform,
+    shapes: &mut Vec<Box<dyn Shape>>,
+) {
+    if vertices.len() < 2 {
+        return;
+    }
+    let nb_segments = if closed {
+        vertices.len()
+    } else {
+        vertices.len() - 1
+    };
+    for idx in 0..nb_segments {
+        let (start, bulge) = vertices[idx];
+        let end = vertices[(idx + 1) % vertices.len()].0;
+        let chord = end - start;
+        let length = chord.norm();
+        if bulge.abs() < 1e-9 || length == 0. {
+            push_line(&start, &end, transform, shapes);
+            continue;
+        }
+        let angle = 4. * bulge.atan();
+        let normal = WPos::new(-chord.wy, chord.wx) / length;
+        let center = (start + end) / 2. + normal * (length / 2. / (angle / 2.).tan());
+        let radius = length / 2. / (angle / 2.).sin().abs();
+        let start_angle = (start.wy - center.wy).atan2(start.wx - center.wx);
+        push_ellipse_arc(
+            &center,
+            &WPos::new(radius, radius),
+            0.,
+            start_angle,
+            angle,
+            transform,
+            shapes,
+        );
+    }
+}
+
+// A B-spline is a Bezier on each span of its knots, the control points of the
+// Bezier are blossoms of the ends of the span
+fn push_spline_shapes(
+    entity: &DxfEntity,
+    transform: &Transform,
+    shapes: &mut Vec<Box<dyn Shape>>,
+    report: &mut ImportReport,
+) {
+    let degree = entity.get_i64(71).unwrap_or(3);
+    let knots = entity.get_all_f64(40);
+    let ctrl_points = entity.get_all_pos(10);
+    if ctrl_points.is_empty() {
+        let fit_points = entity.get_all_pos(11);
+        report.add_warning(
+            entity.line,
+            "SPLINE without control points, its fit points are joined by lines".into(),
+        );
+        for points in fit_points.windows(2) {
+            push_line(&points[0], &points[1], transform, shapes);
+        }
+        return;
+    }
+    if !(1..=3).contains(&degree) {
+        report.add_skipped(
+            entity.line,
+            format!("SPLINE of degree {} is not supported", degree),
+        );
+        return;
+    }
+    let degree = degree as usize;
+    if knots.len() != ctrl_points.len() + degree + 1 {
+        report.add_error(entity.line, "SPLINE with a wrong number of knots".into());
+        return;
+    }
+    let weights = entity.get_all_f64(41);
+    if weights
+        .iter()
+        .any(|weight| (weight - weights[0]).abs() > 1e-9)
+    {
+        report.add_warning(
+            entity.line,
+            "the weights of a rational SPLINE are ignored".into(),
+        );
+    }
+
+    for span in degree..ctrl_points.len() {
+        let (u0, u1) = (knots[span], knots[span + 1]);
+        if u1 - u0 <= 1e-12 {
+            continue;
+        }
+        let points: Vec<WPos> = (0..=degree)
+            .map(|j| {
+                let args: Vec<f64> = (0..degree)
+                    .map(|i| if i < degree - j { u0 } else { u1 })
+                    .collect();
+                get_blossom(&ctrl_points, &knots, degree, span, &args)
+            })
+            .collect();
+        match degree {
+            1 => push_line(&points[0], &points[1], transform, shapes),
+            2 => push_quad_bezier(&points[0], &points[1], &points[2], transform, shapes),
+            _ => push_cubic_bezier(
+                &points[0], &points[1], &points[2], &points[3], transform, shapes,
+            ),
+        }
+    }
+}
+
+// De Boor's algorithm with a parameter per level
+fn get_blossom(
+    ctrl_points: &[WPos],
+    knots: &[f64],
+    degree: usize,
+    span: usize,
+    args: &[f64],
+) -> WPos {
+    let mut points: Vec<WPos> = ctrl_points[span - degree..=span].to_vec();
+    for level in 1..=degree {
+        for i in (level..=degree).rev() {
+            let knot_idx = span - degree + i;
+            let (u0, u1) = (knots[knot_idx], knots[knot_idx + degree + 1 - level]);
+            let alpha = if u1 > u0 {
+                (args[level - 1] - u0) / (u1 - u0)
+            } else {
+                0.
+            };
+            points[i] = points[i - 1] * (1. - alpha) + points[i] * alpha;
+        }
+    }
+    points[degree]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::ImportOrigin;
+
+    fn get_dxf(header: &[(i32, &str)], blocks: &[(i32, &str)], entities: &[(i32, &str)]) -> String {
+        let mut pairs = vec![(0, "SECTION"), (2, "HEADER")];
+        pairs.extend_from_slice(header);
+        pairs.extend_from_slice(&[(0, "ENDSEC"), (0, "SECTION"), (2, "BLOCKS")]);
+        pairs.extend_from_slice(blocks);
+        pairs.extend_from_slice(&[(0, "ENDSEC"), (0, "SECTION"), (2, "ENTITIES")]);
+        pairs.extend_from_slice(entities);
+        pairs.extend_from_slice(&[(0, "ENDSEC"), (0, "EOF")]);
+        pairs
+            .iter()
+            .map(|(code, value)| format!("{:>3}\r\n{}\r\n", code, value))
+            .collect()
+    }
+
+    fn assert_pos(shape: &dyn Shape, r: f64, pos: WPos) {
+        let shape_pos = shape.get_pos_from_ratio(r);
+        assert!(shape_pos.dist(&pos) < 1e-6, "{:?} {:?}", shape_pos, pos);
+    }
+
+    #[test]
+    fn test_dxf_entities() {
+        #[rustfmt::skip]
+        let entities = [
+            (0, "LINE"), (8, "A"), (10, "0"), (20, "0"), (11, "10"), (21, "0"),
+            (0, "CIRCLE"), (8, "A"), (10, "50"), (20, "50"), (40, "10"),
+            (0, "ARC"), (8, "B"), (10, "0"), (20, "0"), (40, "10"), (50, "0"), (51, "90"),
+            (0, "LWPOLYLINE"), (8, "B"), (90, "3"), (70, "0"),
+            (10, "0"), (20, "0"), (42, "1"), (10, "20"), (20, "0"), (10, "20"), (20, "10"),
+            (0, "ELLIPSE"), (8, "C"), (10, "100"), (20, "0"), (11, "0"), (21, "20"), (40, "0.5"),
+            (41, "0"), (42, "6.283185307179586"),
+            (0, "ELLIPSE"), (8, "C"), (10, "0"), (20, "0"), (11, "20"), (21, "20"), (40, "0.5"),
+            (41, "0"), (42, "6.283185307179586"),
+            (0, "SPLINE"), (8, "D"), (71, "2"), (40, "0"), (40, "0"), (40, "0"), (40, "1"),
+            (40, "1"), (40, "1"), (10, "0"), (20, "0"), (10, "10"), (20, "20"), (10, "20"), (20, "0"),
+            (0, "TEXT"), (8, "D"), (1, "Hello"),
+            (0, "LINE"), (8, "D"), (67, "1"), (10, "0"), (20, "0"), (11, "10"), (21, "0"),
+            (0, "INSERT"), (8, "A"), (2, "B1"), (10, "100"), (20, "100"), (41, "2"), (42, "2"),
+            (50, "90"),
+        ];
+        #[rustfmt::skip]
+        let blocks = [
+            (0, "BLOCK"), (2, "B1"), (10, "5"), (20, "5"),
+            (0, "LINE"), (10, "5"), (20, "5"), (11, "15"), (21, "5"),
+            (0, "ENDBLK"),
+        ];
+        let content = get_dxf(&[(9, "$INSUNITS"), (70, "4")], &blocks, &entities);
+        let (groups, report) = get_shapes_from_dxf(&content, &ImportOptions::default());
+        let names: Vec<Vec<&str>> = groups
+            .iter()
+            .map(|group| group.iter().map(|shape| shape.get_type_name()).collect())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                vec!["line", "ellipse"],
+                vec!["ellipse", "ellipse", "line"],
+                vec!["ellipse", "ellipse"],
+                vec!["quadbezier"],
+                vec!["line"],
+            ]
+        );
+        assert_eq!(report.nb_shapes, 9);
+        assert_eq!(report.skipped.len(), 2);
+        assert!(report.errors.is_empty() && report.warnings.is_empty());
+
+        // The y axis is flipped
+        assert_pos(groups[0][0].as_ref(), 1., WPos::new(10., 0.));
+        assert!(groups[0][1].dist(&WPos::new(60., -50.)) < 1e-6);
+        let half = 50_f64.sqrt();
+        assert!(groups[1][0].dist(&WPos::new(half, -half)) < 1e-6);
+        assert!(groups[1][0].dist(&WPos::new(-half, half)) > 1.);
+        // The bulge of 1 is a half circle going counterclockwise
+        assert!(groups[1][1].dist(&WPos::new(10., 10.)) < 1e-6);
+        assert!(groups[1][1].dist(&WPos::new(10., -10.)) > 1.);
+        assert_pos(groups[1][2].as_ref(), 1., WPos::new(20., -10.));
+        // Turned by a quarter, the ellipse stays one
+        assert!(groups[2][0].dist(&WPos::new(100., -20.)) < 1e-6);
+        assert!(groups[2][0].dist(&WPos::new(110., 0.)) < 1e-6);
+        // As is the one turned by an eighth
+        assert!(groups[2][1].dist(&WPos::new(20., -20.)) < 1e-6);
+        assert!(groups[2][1].dist(&WPos::new(10., 10.)) < 1e-6);
+        assert!(groups[2][1].dist(&WPos::new(20., 20.)) > 1.);
+        assert_pos(groups[3][0].as_ref(), 0.5, WPos::new(10., -10.));
+        assert_pos(groups[4][0].as_ref(), 0., WPos::new(100., -100.));
+        assert_pos(groups[4][0].as_ref(), 1., WPos::new(100., -120.));
+    }
+
+    #[test]
+    fn test_dxf_spline() {
+        #[rustfmt::skip]
+        let entities = [
+            (0, "SPLINE"), (71, "3"), (40, "0"), (40, "0"), (40, "0"), (40, "0"), (40, "0.5"),
+            (40, "1"), (40, "1"), (40, "1"), (40, "1"), (10, "0"), (20, "0"), (10, "10"),
+            (20, "20"), (10, "20"), (20, "0"), (10, "30"), (20, "20"), (10, "40"), (20, "0"),
+        ];
+        let content = get_dxf(&[], &[], &entities);
+        let options = ImportOptions {
+            flip_y: true,
+            ..ImportOptions::default()
+        };
+        let (groups, report) = get_shapes_from_dxf(&content, &options);
+        assert!(report.is_clean());
+        assert_eq!(groups[0].len(), 2);
+        assert_pos(groups[0][0].as_ref(), 0., WPos::new(0., 0.));
+        assert_pos(groups[0][1].as_ref(), 1., WPos::new(40., 0.));
+        // Where the Beziers join is the point of the spline at the inner knot
+        let ctrl_points: Vec<WPos> = [(0., 0.), (10., 20.), (20., 0.), (30., 20.), (40., 0.)]
+            .iter()
+            .map(|(x, y)| WPos::new(*x, *y))
+            .collect();
+        let knots = [0., 0., 0., 0., 0.5, 1., 1., 1., 1.];
+        let joint = get_blossom(&ctrl_points, &knots, 3, 4, &[0.5, 0.5, 0.5]);
+        assert_pos(groups[0][0].as_ref(), 1., joint);
+        assert_pos(groups[0][1].as_ref(), 0., joint);
+        assert!((joint.wx - 20.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_dxf_units_and_errors() {
+        let line = [(0, "LINE"), (10, "0"), (20, "0"), (11, "1"), (21, "0")];
+        let content = get_dxf(&[(9, "$INSUNITS"), (70, "1")], &[], &line);
+        let options = ImportOptions {
+            origin: ImportOrigin::BottomLeft,
+            working_area: WPos::new(100., 50.),
+            ..ImportOptions::default()
+        };
+        let (groups, _) = get_shapes_from_dxf(&content, &options);
+        assert_pos(groups[0][0].as_ref(), 0., WPos::new(0., 50.));
+        assert_pos(groups[0][0].as_ref(), 1., WPos::new(25.4, 50.));
+        // Where the file put it, the origin of the DXF at the bottom left
+        let options = ImportOptions {
+            scale: 2.,
+            working_area: WPos::new(100., 50.),
+            ..ImportOptions::default()
+        };
+        let (groups, _) = get_shapes_from_dxf(&content, &options);
+        assert_pos(groups[0][0].as_ref(), 1., WPos::new(50.8, 50.));
+
+        let content = get_dxf(&[], &[], &[(0, "INSERT"), (2, "NONE")]) + "  X\nA\n";
+        let (groups, report) = get_shapes_from_dxf(&content, &ImportOptions::default());
+        assert!(groups.is_empty());
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.errors[0].line, 25);
+        assert_eq!(report.errors[1].line, 17);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::shapes::types::{GroupId, Shape, ShapeId};
+use std::collections::VecDeque;
+
+// Number of commands which can be undone
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+// A copy of a shape as it was at some point, with the groups holding it
+pub struct ShapeState {
+    shape: Box<dyn Shape>,
+    pub groups: Vec<GroupId>,
+}
+impl ShapeState {
+    pub fn new(shape: &dyn Shape, groups: Vec<GroupId>) -> ShapeState {
+        ShapeState {
+            shape: shape.clone_box(),
+            groups,
+        }
+    }
+    pub fn get_shape(&self) -> Box<dyn Shape> {
+        let mut shape = self.shape.clone_box();
+        shape.init_done();
+        shape
+    }
+    // The selection of the points doesn't matter
+    fn is_same(&self, other: &ShapeState) -> bool {
+        let (points, other_points) = (self.shape.get_points(), other.shape.get_points());
+        self.shape.get_type_name() == other.shape.get_type_name()
+            && self.shape.get_pos() == other.shape.get_pos()
+            && self.groups == other.groups
+            && points.len() == other_points.len()
+            && points.iter().zip(other_points.iter()).all(
+                |((pt_type, pt), (other_pt_type, other_pt))| {
+                    pt_type == other_pt_type && pt.wpos == other_pt.wpos
+                },
+            )
+    }
+}
+
+// A shape before and after a command, it doesn't exist before being inserted
+// nor after being deleted
+pub struct ShapeChange {
+    pub sh_id: ShapeId,
+    pub before: Option<ShapeState>,
+    pub after: Option<ShapeState>,
+}
+impl ShapeChange {
+    fn is_empty(&self) -> bool {
+        match (&self.before, &self.after) {
+            (Some(before), Some(after)) => before.is_same(after),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+// Everything a user action changed, undone and redone at once
+pub type Command = Vec<ShapeChange>;
+
+// The command being recorded holds the shapes as they were when first
+// changed. Commands can be nested, only the outermost one is kept.
+pub struct History {
+    undo_commands: VecDeque<Command>,
+    redo_commands: Vec<Command>,
+    depth: usize,
+    recording: Command,
+    nesting: usize,
+}
+impl History {
+    pub fn new() -> History {
+        History {
+            undo_commands: VecDeque::new(),
+            redo_commands: vec![],
+            depth: DEFAULT_HISTORY_DEPTH,
+            recording: vec![],
+            nesting: 0,
+        }
+    }
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo_commands.len() > depth {
+            self.undo_commands.pop_front();
+        }
+    }
+
+    pub fn begin(&mut self) {
+        self.nesting += 1;
+    }
+    pub fn is_recording(&self) -> bool {
+        self.nesting > 0
+    }
+    // Only the first state of a shape is kept
+    pub fn record(&mut self, sh_id: &ShapeId, before: Option<ShapeState>) {
+        if self.is_recording() && !self.recording.iter().any(|change| change.sh_id == *sh_id) {
+            self.recording.push(ShapeChange {
+                sh_id: *sh_id,
+                before,
+                after: None,
+            });
+        }
+    }
+    // The command recorded when the outermost one ends, the states after it
+    // are still to be set
+    pub fn end(&mut self) -> Option<Command> {
+        if self.nesting == 0 {
+            return None;
+        }
+        self.nesting -= 1;
+        if self.nesting > 0 {
+            return None;
+        }
+        Some(std::mem::take(&mut self.recording))
+    }
+    // A new command can't be redone after the ones undone
+    pub fn push(&mut self, mut command: Command) {
+        command.retain(|change| !change.is_empty());
+        if command.is_empty() {
+            return;
+        }
+        self.redo_commands.clear();
+        self.push_undo(command);
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Command> {
+        self.undo_commands.pop_back()
+    }
+    pub fn push_undo(&mut self, command: Command) {
+        self.undo_commands.push_back(command);
+        while self.undo_commands.len() > self.depth {
+            self.undo_commands.pop_front();
+        }
+    }
+    pub fn pop_redo(&mut self) -> Option<Command> {
+        self.redo_commands.pop()
+    }
+    pub fn push_redo(&mut self, command: Command) {
+        self.redo_commands.push(command);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::math::{get_shapes_bounded_rectangle, Transform};
+use crate::shapes::cubicbezier::CubicBezier;
+use crate::shapes::ellipse::Ellipse;
+use crate::shapes::line::Line;
+use crate::shapes::quadbezier::QuadBezier;
+use crate::shapes::types::{Shape, WPos};
+
+// Messages listed in a summary, the others are only counted
+const MAX_SUMMARY_MESSAGES: usize = 20;
+
+// What happened while importing a file. The import goes on past the elements
+// in error, they are listed here to be shown to the user.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub nb_shapes: usize,
+    pub errors: Vec<ImportMessage>,
+    pub warnings: Vec<ImportMessage>,
+    pub skipped: Vec<ImportMessage>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportMessage {
+    // Line in the file, 0 if unknown
+    pub line: usize,
+    pub message: String,
+}
+
+impl ImportReport {
+    pub fn new() -> ImportReport {
+        ImportReport::default()
+    }
+    pub fn add_error(&mut self, line: usize, message: String) {
+        self.errors.push(ImportMessage { line, message });
+    }
+    pub fn add_warning(&mut self, line: usize, message: String) {
+        self.warnings.push(ImportMessage { line, message });
+    }
+    pub fn add_skipped(&mut self, line: usize, message: String) {
+        self.skipped.push(ImportMessage { line, message });
+    }
+    // Nothing to tell the user but the shapes created
+    pub fn is_clean(&self) -> bool {
+        self.nb_shapes > 0
+            && self.errors.is_empty()
+            && self.warnings.is_empty()
+            && self.skipped.is_empty()
+    }
+    pub fn get_summary(&self) -> String {
+        let mut summary = format!(
+            "{} shape(s) imported, {} error(s), {} warning(s), {} skipped element(s)",
+            self.nb_shapes,
+            self.errors.len(),
+            self.warnings.len(),
+            self.skipped.len()
+        );
+        let messages: Vec<(&str, &ImportMessage)> = [
+            ("Error", &self.errors),
+            ("Warning", &self.warnings),
+            ("Skipped", &self.skipped),
+        ]
+        .iter()
+        .flat_map(|(kind, messages)| messages.iter().map(move |message| (*kind, message)))
+        .collect();
+        for (kind, message) in messages.iter().take(MAX_SUMMARY_MESSAGES) {
+            if message.line > 0 {
+                summary += &format!("\n{} line {}: {}", kind, message.line, message.message);
+            } else {
+                summary += &format!("\n{}: {}", kind, message.message);
+            }
+        }
+        if messages.len() > MAX_SUMMARY_MESSAGES {
+            summary += &format!("\n... and {} more", messages.len() - MAX_SUMMARY_MESSAGES);
+        }
+        summary
+    }
+}
+
+// Where the imported drawing is put on the working sheet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportOrigin {
+    // The origin of the imported document is the one of the sheet
+    Document,
+    // The drawing is moved to the bottom left corner of the sheet
+    BottomLeft,
+    // The drawing is centered on the sheet
+    Center,
+}
+impl ImportOrigin {
+    pub fn from_name(name: &str) -> Option<ImportOrigin> {
+        match name {
+            "document" => Some(ImportOrigin::Document),
+            "bottom_left" => Some(ImportOrigin::BottomLeft),
+            "center" => Some(ImportOrigin::Center),
+            _ => None,
+        }
+    }
+}
+
+pub struct ImportOptions {
+    // Applied once the units of the file are converted to millimeters
+    pub scale: f64,
+    // The drawing is turned upside down
+    pub flip_y: bool,
+    pub origin: ImportOrigin,
+    pub working_area: WPos,
+}
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            scale: 1.,
+            flip_y: false,
+            origin: ImportOrigin::Document,
+            working_area: WPos::zero(),
+        }
+    }
+}
+
+// Translation putting the shapes where the options want them on the sheet,
+// None when they stay where the file put them
+pub fn get_placement<'a>(
+    shapes: impl IntoIterator<Item = &'a Box<dyn Shape>>,
+    options: &ImportOptions,
+) -> Option<Transform> {
+    let bounds = get_shapes_bounded_rectangle(shapes)?;
+    let working_area = options.working_area;
+    let offset = match options.origin {
+        ImportOrigin::Document => return None,
+        ImportOrigin::BottomLeft => WPos::new(-bounds[0].wx, working_area.wy - bounds[1].wy),
+        ImportOrigin::Center => (working_area - bounds[0] - bounds[1]) / 2.,
+    };
+    Some(Transform::translate(offset.wx, offset.wy))
+}
+
+// Shapes of an imported file, mapped by a transformation
+pub fn push_shape<T: Shape + 'static>(shape: Option<T>, shapes: &mut Vec<Box<dyn Shape>>) {
+    if let Some(shape) = shape {
+        shapes.push(Box::new(shape));
+    }
+}
+pub fn push_line(
+    start: &WPos,
+    end: &WPos,
+    transform: &Transform,
+    shapes: &mut Vec<Box<dyn Shape>>,
+) {
+    push_shape(
+        Line::new(&transform.apply(start), &transform.apply(end)),
+        shapes,
+    );
+}
+pub fn push_quad_bezier(
+    start: &WPos,
+    ctrl: &WPos,
+    end: &WPos,
+    transform: &Transform,
+    shapes: &mut Vec<Box<dyn Shape>>,
+) {
+    push_shape(
+        QuadBezier::new(
+            &transform.apply(start),
+            &transform.apply(ctrl),
+            &transform.apply(end),
+        ),
+        shapes,
+    );
+}
+pub fn push_cubic_bezier(
+    start: &WPos,
+    ctrl1: &WPos,
+    ctrl2: &WPos,
+    end: &WPos,
+    transform: &Transform,
+    shapes: &mut Vec<Box<dyn Shape>>,
+) {
+    push_shape(
+        CubicBezier::new(
+            &transform.apply(start),
+            &transform.apply(ctrl1),
+            &transform.apply(ctrl2),
+            &transform.apply(end),
+        ),
+        shapes,
+    );
+}
+// Arc of an ellipse turned by the rotation, the sweep angle is signed. An
+// ellipse stays an ellipse whatever the transformation.
+pub fn push_ellipse_arc(
+    center: &WPos,
+    radius: &WPos,
+    rotation: f64,
+    start_angle: f64,
+    sweep_angle: f64,
+    transform: &Transform,
+    shapes: &mut Vec<Box<dyn Shape>>,
+) {
+    let end_angle = start_angle + sweep_angle;
+    // The ellipse is always drawn with increasing angles
+    let arc = if sweep_angle >= 0. {
+        Ellipse::from_arc(center, radius, rotation, start_angle, end_angle)
+    } else {
+        Ellipse::from_arc(center, radius, rotation, end_angle, start_angle)
+    };
+    if let Some(mut arc) = arc {
+        match arc.transform(transform) {
+            None => shapes.push(Box::new(arc)),
+            Some(replacements) => shapes.extend(replacements),
+        }
+    }
+}
@@ -0,0 +1,638 @@
+use std::f64::consts::PI;
+
+use crate::math::*;
+use crate::shapes::types::{ConstructionType, Shape, WPos};
+
+// Above this count of candidates, the curves are considered overlapping
+const MAX_SUBDIVISION_HITS: usize = 10000;
+const MAX_SUBDIVISION_DEPTH: usize = 64;
+
+// Elementary curve of a shape, in world coordinates
+#[derive(Clone, Copy, Debug)]
+pub enum Curve {
+    Line(WPos, WPos),
+    QuadBezier(WPos, WPos, WPos),
+    CubicBezier(WPos, WPos, WPos, WPos),
+    // Center, radii, rotation, start angle and signed sweep, angles follow
+    // the canvas convention
+    Arc(WPos, WPos, f64, f64, f64),
+}
+impl Curve {
+    pub fn get_pos(&self, t: f64) -> WPos {
+        match self {
+            Curve::Line(start, end) => start.lerp(end, t),
+            Curve::QuadBezier(start, ctrl, end) => get_point_on_quad_bezier(t, start, ctrl, end),
+            Curve::CubicBezier(start, ctrl1, ctrl2, end) => {
+                get_point_on_cubic_bezier(t, start, ctrl1, ctrl2, end)
+            }
+            Curve::Arc(center, radius, rotation, start_angle, sweep) => {
+                get_ellipse_pos(center, radius, *rotation, start_angle + t * sweep)
+            }
+        }
+    }
+    // Conservative bounding box: control points for the Beziers, end points
+    // and extremums of the axes for the arcs
+    pub fn get_bounding_box(&self) -> [WPos; 2] {
+        let pts = match self {
+            Curve::Line(start, end) => vec![*start, *end],
+            Curve::QuadBezier(start, ctrl, end) => vec![*start, *ctrl, *end],
+            Curve::CubicBezier(start, ctrl1, ctrl2, end) => vec![*start, *ctrl1, *ctrl2, *end],
+            Curve::Arc(center, radius, rotation, start_angle, sweep) => {
+                let mut pts = vec![self.get_pos(0.), self.get_pos(1.)];
+                // Where the tangent is vertical, then horizontal
+                let (sin, cos) = rotation.sin_cos();
+                let angle_x = (-radius.wy * sin).atan2(radius.wx * cos);
+                let angle_y = (radius.wy * cos).atan2(radius.wx * sin);
+                for angle in [angle_x, angle_x + PI, angle_y, angle_y + PI] {
+                    if get_arc_param(*start_angle, *sweep, angle, 0.).is_some() {
+                        pts.push(get_ellipse_pos(center, radius, *rotation, angle));
+                    }
+                }
+                pts
+            }
+        };
+        let mut bb = [pts[0], pts[0]];
+        for pos in pts.iter() {
+            bb[0] = WPos::new(bb[0].wx.min(pos.wx), bb[0].wy.min(pos.wy));
+            bb[1] = WPos::new(bb[1].wx.max(pos.wx), bb[1].wy.max(pos.wy));
+        }
+        bb
+    }
+    pub fn split(&self, t: f64) -> (Curve, Curve) {
+        match self {
+            Curve::Line(start, end) => {
+                let pos = start.lerp(end, t);
+                (Curve::Line(*start, pos), Curve::Line(pos, *end))
+            }
+            Curve::QuadBezier(start, ctrl, end) => {
+                let p01 = start.lerp(ctrl, t);
+                let p12 = ctrl.lerp(end, t);
+                let pos = p01.lerp(&p12, t);
+                (
+                    Curve::QuadBezier(*start, p01, pos),
+                    Curve::QuadBezier(pos, p12, *end),
+                )
+            }
+            Curve::CubicBezier(start, ctrl1, ctrl2, end) => {
+                let p01 = start.lerp(ctrl1, t);
+                let p12 = ctrl1.lerp(ctrl2, t);
+                let p23 = ctrl2.lerp(end, t);
+                let p012 = p01.lerp(&p12, t);
+                let p123 = p12.lerp(&p23, t);
+                let pos = p012.lerp(&p123, t);
+                (
+                    Curve::CubicBezier(*start, p01, p012, pos),
+                    Curve::CubicBezier(pos, p123, p23, *end),
+                )
+            }
+            Curve::Arc(center, radius, rotation, start_angle, sweep) => (
+                Curve::Arc(*center, *radius, *rotation, *start_angle, sweep * t),
+                Curve::Arc(
+                    *center,
+                    *radius,
+                    *rotation,
+                    start_angle + sweep * t,
+                    sweep * (1. - t),
+                ),
+            ),
+        }
+    }
+}
+
+// Intersection of two shapes, with the ratios of the position on each shape
+#[derive(Clone, Copy, Debug)]
+pub struct Intersection {
+    pub pos: WPos,
+    pub r_a: f64,
+    pub r_b: f64,
+}
+
+pub fn get_curves(shape: &dyn Shape) -> Vec<Curve> {
+    let mut curves = vec![];
+    let mut pos = WPos::zero();
+    for cst in shape.get_construction().iter() {
+        match cst {
+            ConstructionType::Move(end) => pos = *end,
+            ConstructionType::Line(end) => {
+                curves.push(Curve::Line(pos, *end));
+                pos = *end;
+            }
+            ConstructionType::QuadBezier(ctrl, end) => {
+                curves.push(Curve::QuadBezier(pos, *ctrl, *end));
+                pos = *end;
+            }
+            ConstructionType::CubicBezier(ctrl1, ctrl2, end) => {
+                curves.push(Curve::CubicBezier(pos, *ctrl1, *ctrl2, *end));
+                pos = *end;
+            }
+            ConstructionType::Ellipse(center, radius, rotation, start_angle, end_angle, _) => {
+                let sweep = get_sweep_angle(*start_angle, *end_angle);
+                let arc = Curve::Arc(*center, *radius, *rotation, *start_angle, sweep);
+                pos = arc.get_pos(1.);
+                curves.push(arc);
+            }
+            _ => (),
+        }
+    }
+    curves
+}
+
+pub fn get_shapes_intersections(
+    shape_a: &dyn Shape,
+    shape_b: &dyn Shape,
+    tolerance: f64,
+) -> Vec<Intersection> {
+    let curves_b = get_curves(shape_b);
+    let mut intersections: Vec<Intersection> = vec![];
+    for curve_a in get_curves(shape_a).iter() {
+        for curve_b in curves_b.iter() {
+            for (t_a, _) in get_curves_intersections(curve_a, curve_b, tolerance) {
+                let pos = curve_a.get_pos(t_a);
+                // Curves joined end to end give the same position twice
+                if intersections
+                    .iter()
+                    .all(|int| int.pos.dist(&pos) > tolerance)
+                {
+                    intersections.push(Intersection {
+                        pos,
+                        r_a: shape_a.get_ratio_from_pos(&pos),
+                        r_b: shape_b.get_ratio_from_pos(&pos),
+                    });
+                }
+            }
+        }
+    }
+    intersections
+}
+
+// Parameters on both curves of their intersections
+pub fn get_curves_intersections(
+    curve_a: &Curve,
+    curve_b: &Curve,
+    tolerance: f64,
+) -> Vec<(f64, f64)> {
+    match (curve_a, curve_b) {
+        (Curve::Line(s1, e1), Curve::Line(s2, e2)) => get_line_line(s1, e1, s2, e2, tolerance),
+        (Curve::Line(start, end), Curve::Arc(..)) => get_line_arc(start, end, curve_b, tolerance),
+        (Curve::Arc(..), Curve::Line(start, end)) => {
+            swap(get_line_arc(start, end, curve_a, tolerance))
+        }
+        (Curve::Line(start, end), _) => get_line_bezier(start, end, curve_b, tolerance),
+        (_, Curve::Line(start, end)) => swap(get_line_bezier(start, end, curve_a, tolerance)),
+        (Curve::Arc(c1, r1, rot1, sa1, sw1), Curve::Arc(c2, r2, rot2, sa2, sw2))
+            if is_same_ellipse((c1, r1, *rot1), (c2, r2, *rot2), tolerance) =>
+        {
+            // Same ellipse, only the ends of the arcs are kept. The angles
+            // of one arc are shifted by the difference of rotation to be
+            // read on the other one.
+            let mut params = vec![];
+            let ang_tol = tolerance / r1.wx.abs().max(r1.wy.abs());
+            let shift = rot1 - rot2;
+            for (t1, angle) in [(0., *sa1), (1., sa1 + sw1)] {
+                if let Some(t2) = get_arc_param(*sa2, *sw2, angle + shift, ang_tol) {
+                    params.push((t1, t2));
+                }
+            }
+            for (t2, angle) in [(0., *sa2), (1., sa2 + sw2)] {
+                if let Some(t1) = get_arc_param(*sa1, *sw1, angle - shift, ang_tol) {
+                    params.push((t1, t2));
+                }
+            }
+            dedup_params(curve_a, params, tolerance)
+        }
+        _ => {
+            let mut params = vec![];
+            subdivide(
+                curve_a,
+                (0., 1.),
+                curve_b,
+                (0., 1.),
+                tolerance,
+                0,
+                &mut params,
+            );
+            dedup_params(curve_a, params, tolerance)
+        }
+    }
+}
+
+fn swap(params: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    params.into_iter().map(|(t_a, t_b)| (t_b, t_a)).collect()
+}
+
+// Parameter along a segment, None when the position is beyond its ends
+fn get_seg_param(start: &WPos, end: &WPos, pos: &WPos, tolerance: f64) -> Option<f64> {
+    let dir = *end - *start;
+    let length = dir.norm();
+    if length == 0. {
+        return None;
+    }
+    let t = (*pos - *start).dot(&dir) / (length * length);
+    let t_tol = tolerance / length;
+    if t < -t_tol || t > 1. + t_tol {
+        None
+    } else {
+        Some(t.clamp(0., 1.))
+    }
+}
+
+// Parameter along an arc of the position at angle, None when outside the arc
+fn get_arc_param(start_angle: f64, sweep: f64, angle: f64, ang_tol: f64) -> Option<f64> {
+    let delta = if sweep >= 0. {
+        (angle - start_angle).rem_euclid(2. * PI)
+    } else {
+        (start_angle - angle).rem_euclid(2. * PI)
+    };
+    let sweep = sweep.abs();
+    if sweep == 0. {
+        return None;
+    }
+    if delta <= sweep + ang_tol {
+        Some((delta / sweep).min(1.))
+    } else if delta >= 2. * PI - ang_tol {
+        // Just before the start
+        Some(0.)
+    } else {
+        None
+    }
+}
+
+fn get_line_line(s1: &WPos, e1: &WPos, s2: &WPos, e2: &WPos, tolerance: f64) -> Vec<(f64, f64)> {
+    let d1 = *e1 - *s1;
+    let d2 = *e2 - *s2;
+    let denom = cross(&d1, &d2);
+    if denom.abs() <= 1e-12 * d1.norm() * d2.norm() {
+        // Parallel, only collinear overlapping segments meet at their ends
+        if d1.norm() == 0. || (cross(&d1, &(*s2 - *s1)) / d1.norm()).abs() > tolerance {
+            return vec![];
+        }
+        let mut params = vec![];
+        for (t2, pos) in [(0., s2), (1., e2)] {
+            if let Some(t1) = get_seg_param(s1, e1, pos, tolerance) {
+                params.push((t1, t2));
+            }
+        }
+        for (t1, pos) in [(0., s1), (1., e1)] {
+            if let Some(t2) = get_seg_param(s2, e2, pos, tolerance) {
+                params.push((t1, t2));
+            }
+        }
+        return dedup_params(&Curve::Line(*s1, *e1), params, tolerance);
+    }
+    let t1 = cross(&(*s2 - *s1), &d2) / denom;
+    let t2 = cross(&(*s2 - *s1), &d1) / denom;
+    let (t1_tol, t2_tol) = (tolerance / d1.norm(), tolerance / d2.norm());
+    if t1 < -t1_tol || t1 > 1. + t1_tol || t2 < -t2_tol || t2 > 1. + t2_tol {
+        vec![]
+    } else {
+        vec![(t1.clamp(0., 1.), t2.clamp(0., 1.))]
+    }
+}
+
+// Ellipses with the same center and radii, and the same axes unless they are
+// circles
+fn is_same_ellipse(
+    (c1, r1, rot1): (&WPos, &WPos, f64),
+    (c2, r2, rot2): (&WPos, &WPos, f64),
+    tolerance: f64,
+) -> bool {
+    let (rx, ry) = (r1.wx.abs(), r1.wy.abs());
+    if c1.dist(c2) >= tolerance
+        || (rx - r2.wx.abs()).abs() >= tolerance
+        || (ry - r2.wy.abs()).abs() >= tolerance
+    {
+        return false;
+    }
+    // A half turn keeps the axes
+    let delta = (rot1 - rot2).rem_euclid(PI);
+    (rx - ry).abs() < tolerance || delta.min(PI - delta) * rx.max(ry) < tolerance
+}
+
+fn get_line_arc(start: &WPos, end: &WPos, arc: &Curve, tolerance: f64) -> Vec<(f64, f64)> {
+    let Curve::Arc(center, radius, rotation, start_angle, sweep) = arc else {
+        return vec![];
+    };
+    let (rx, ry) = (radius.wx.abs(), radius.wy.abs());
+    if rx == 0. || ry == 0. {
+        return vec![];
+    }
+    // In the space where the ellipse is the unit circle, once unturned
+    let unturn = Transform::rotate(-rotation);
+    let p = unturn.apply(&(*start - *center));
+    let q = unturn.apply(&(*end - *start));
+    let p = WPos::new(p.wx / rx, p.wy / ry);
+    let q = WPos::new(q.wx / rx, q.wy / ry);
+    let a = q.dot(&q);
+    if a == 0. {
+        return vec![];
+    }
+    let b = p.dot(&q);
+    let c = p.dot(&p) - 1.;
+    let disc = b * b - a * c;
+    let t_line = if disc >= 0. {
+        vec![(-b - disc.sqrt()) / a, (-b + disc.sqrt()) / a]
+    } else {
+        // Tangent within the tolerance
+        let t = -b / a;
+        if ((p + q * t).norm() - 1.) * rx.min(ry) <= tolerance {
+            vec![t]
+        } else {
+            vec![]
+        }
+    };
+
+    let length = start.dist(end);
+    let ang_tol = tolerance / rx.max(ry);
+    let mut params = vec![];
+    for t in t_line {
+        if t < -tolerance / length || t > 1. + tolerance / length {
+            continue;
+        }
+        let n = p + q * t;
+        if let Some(t_arc) = get_arc_param(*start_angle, *sweep, n.wy.atan2(n.wx), ang_tol) {
+            params.push((t.clamp(0., 1.), t_arc));
+        }
+    }
+    dedup_params(&Curve::Line(*start, *end), params, tolerance)
+}
+
+// Power basis coefficients of a Bezier coordinate, lowest degree first
+fn get_bezier_coefs(pts: &[f64]) -> Vec<f64> {
+    match pts.len() {
+        3 => vec![
+            pts[0],
+            2. * (pts[1] - pts[0]),
+            pts[0] - 2. * pts[1] + pts[2],
+        ],
+        4 => vec![
+            pts[0],
+            3. * (pts[1] - pts[0]),
+            3. * (pts[0] - 2. * pts[1] + pts[2]),
+            -pts[0] + 3. * pts[1] - 3. * pts[2] + pts[3],
+        ],
+        _ => vec![],
+    }
+}
+
+fn eval_poly(coefs: &[f64], t: f64) -> f64 {
+    coefs.iter().rev().fold(0., |acc, coef| acc * t + coef)
+}
+
+// Roots of a polynomial in [0, 1]: the roots of its derivative bound the
+// monotonic intervals, each one holds at most one root found by bisection.
+// Extremums within the tolerance are tangencies
+fn get_poly_roots(coefs: &[f64], tolerance: f64) -> Vec<f64> {
+    if coefs.len() < 2 {
+        return vec![];
+    }
+    let derivative: Vec<f64> = coefs
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, coef)| i as f64 * coef)
+        .collect();
+    let mut bounds = vec![0.];
+    bounds.extend(get_poly_roots(&derivative, 0.));
+    bounds.push(1.);
+
+    let mut roots: Vec<f64> = vec![];
+    let push_root = |t: f64, roots: &mut Vec<f64>| {
+        if roots.iter().all(|root| (root - t).abs() > 1e-9) {
+            roots.push(t);
+        }
+    };
+    for bound in bounds.windows(2) {
+        let (mut t0, mut t1) = (bound[0], bound[1]);
+        let (v0, v1) = (eval_poly(coefs, t0), eval_poly(coefs, t1));
+        if v0.abs() <= tolerance {
+            push_root(t0, &mut roots);
+        }
+        if v0 * v1 < 0. {
+            for _ in 0..64 {
+                let t = (t0 + t1) / 2.;
+                if eval_poly(coefs, t) * v0 > 0. {
+                    t0 = t;
+                } else {
+                    t1 = t;
+                }
+            }
+            push_root((t0 + t1) / 2., &mut roots);
+        }
+    }
+    if eval_poly(coefs, 1.).abs() <= tolerance {
+        push_root(1., &mut roots);
+    }
+    roots
+}
+
+fn get_line_bezier(start: &WPos, end: &WPos, bezier: &Curve, tolerance: f64) -> Vec<(f64, f64)> {
+    let pts = match bezier {
+        Curve::QuadBezier(start, ctrl, end) => vec![*start, *ctrl, *end],
+        Curve::CubicBezier(start, ctrl1, ctrl2, end) => vec![*start, *ctrl1, *ctrl2, *end],
+        _ => return vec![],
+    };
+    let length = start.dist(end);
+    if length == 0. {
+        return vec![];
+    }
+    // Signed distances of the control points to the line
+    let dir = (*end - *start) / length;
+    let dists: Vec<f64> = pts
+        .iter()
+        .map(|pos| cross(&dir, &(*pos - *start)))
+        .collect();
+
+    let mut params = vec![];
+    for t_bezier in get_poly_roots(&get_bezier_coefs(&dists), tolerance) {
+        let pos = bezier.get_pos(t_bezier);
+        if let Some(t_line) = get_seg_param(start, end, &pos, tolerance) {
+            params.push((t_line, t_bezier));
+        }
+    }
+    dedup_params(&Curve::Line(*start, *end), params, tolerance)
+}
+
+fn is_box_overlapping(bb_a: &[WPos; 2], bb_b: &[WPos; 2], tolerance: f64) -> bool {
+    bb_a[0].wx <= bb_b[1].wx + tolerance
+        && bb_b[0].wx <= bb_a[1].wx + tolerance
+        && bb_a[0].wy <= bb_b[1].wy + tolerance
+        && bb_b[0].wy <= bb_a[1].wy + tolerance
+}
+
+fn get_box_size(bb: &[WPos; 2]) -> f64 {
+    (bb[1].wx - bb[0].wx).max(bb[1].wy - bb[0].wy)
+}
+
+// The curves are halved, the largest one first, until their bounding boxes
+// are smaller than the tolerance or stop overlapping
+fn subdivide(
+    curve_a: &Curve,
+    range_a: (f64, f64),
+    curve_b: &Curve,
+    range_b: (f64, f64),
+    tolerance: f64,
+    depth: usize,
+    params: &mut Vec<(f64, f64)>,
+) {
+    if params.len() >= MAX_SUBDIVISION_HITS {
+        return;
+    }
+    let (bb_a, bb_b) = (curve_a.get_bounding_box(), curve_b.get_bounding_box());
+    if !is_box_overlapping(&bb_a, &bb_b, tolerance) {
+        return;
+    }
+    let (size_a, size_b) = (get_box_size(&bb_a), get_box_size(&bb_b));
+    if (size_a <= tolerance && size_b <= tolerance) || depth >= MAX_SUBDIVISION_DEPTH {
+        params.push(((range_a.0 + range_a.1) / 2., (range_b.0 + range_b.1) / 2.));
+        return;
+    }
+    if size_a >= size_b {
+        let (a1, a2) = curve_a.split(0.5);
+        let mid = (range_a.0 + range_a.1) / 2.;
+        let ranges = [(range_a.0, mid), (mid, range_a.1)];
+        for (curve, range) in [a1, a2].iter().zip(ranges) {
+            subdivide(curve, range, curve_b, range_b, tolerance, depth + 1, params);
+        }
+    } else {
+        let (b1, b2) = curve_b.split(0.5);
+        let mid = (range_b.0 + range_b.1) / 2.;
+        let ranges = [(range_b.0, mid), (mid, range_b.1)];
+        for (curve, range) in [b1, b2].iter().zip(ranges) {
+            subdivide(curve_a, range_a, curve, range, tolerance, depth + 1, params);
+        }
+    }
+}
+
+// Neighbouring hits of the same intersection are merged into their average
+fn dedup_params(curve_a: &Curve, mut params: Vec<(f64, f64)>, tolerance: f64) -> Vec<(f64, f64)> {
+    params.sort_by(|p1, p2| p1.0.partial_cmp(&p2.0).unwrap());
+    let mut clusters: Vec<(WPos, Vec<(f64, f64)>)> = vec![];
+    for param in params {
+        let pos = curve_a.get_pos(param.0);
+        match clusters.last_mut() {
+            Some((last_pos, cluster)) if last_pos.dist(&pos) <= 2. * tolerance => {
+                *last_pos = pos;
+                cluster.push(param);
+            }
+            _ => clusters.push((pos, vec![param])),
+        }
+    }
+    clusters
+        .iter()
+        .map(|(_, cluster)| {
+            let nb = cluster.len() as f64;
+            (
+                cluster.iter().map(|p| p.0).sum::<f64>() / nb,
+                cluster.iter().map(|p| p.1).sum::<f64>() / nb,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_hit(curve_a: &Curve, curve_b: &Curve, params: &[(f64, f64)], pos: &WPos) {
+        assert!(
+            params
+                .iter()
+                .any(|(t_a, t_b)| curve_a.get_pos(*t_a).dist(pos) < 1e-3
+                    && curve_b.get_pos(*t_b).dist(pos) < 1e-3),
+            "no intersection at {:?} in {:?}",
+            pos,
+            params
+        );
+    }
+
+    #[test]
+    fn test_line_line() {
+        let line1 = Curve::Line(WPos::new(0., 0.), WPos::new(10., 10.));
+        let line2 = Curve::Line(WPos::new(0., 10.), WPos::new(10., 0.));
+        let params = get_curves_intersections(&line1, &line2, 1e-6);
+        assert_eq!(params.len(), 1);
+        assert!((params[0].0 - 0.5).abs() < 1e-9 && (params[0].1 - 0.5).abs() < 1e-9);
+
+        let line3 = Curve::Line(WPos::new(20., 0.), WPos::new(30., 10.));
+        assert!(get_curves_intersections(&line1, &line3, 1e-6).is_empty());
+    }
+
+    #[test]
+    fn test_line_arc() {
+        // Circle of radius 10, the line crosses it twice and touches a
+        // second circle
+        let circle = Curve::Arc(WPos::new(0., 0.), WPos::new(10., 10.), 0., 0., 2. * PI);
+        let line = Curve::Line(WPos::new(-20., 0.), WPos::new(20., 0.));
+        let params = get_curves_intersections(&line, &circle, 1e-6);
+        assert_eq!(params.len(), 2);
+        assert_hit(&line, &circle, &params, &WPos::new(-10., 0.));
+        assert_hit(&line, &circle, &params, &WPos::new(10., 0.));
+
+        let tangent = Curve::Line(WPos::new(-20., 10.), WPos::new(20., 10.));
+        let params = get_curves_intersections(&circle, &tangent, 1e-6);
+        assert_eq!(params.len(), 1);
+        assert_hit(&circle, &tangent, &params, &WPos::new(0., 10.));
+
+        // Half of an ellipse, from 0 to PI
+        let arc = Curve::Arc(WPos::new(0., 0.), WPos::new(20., 10.), 0., 0., PI);
+        let line = Curve::Line(WPos::new(0., -20.), WPos::new(0., 20.));
+        let params = get_curves_intersections(&line, &arc, 1e-6);
+        assert_eq!(params.len(), 1);
+        assert_hit(&line, &arc, &params, &WPos::new(0., 10.));
+
+        // The same half turned by a quarter, going through (-10, 0)
+        let arc = Curve::Arc(WPos::new(0., 0.), WPos::new(20., 10.), PI / 2., 0., PI);
+        let line = Curve::Line(WPos::new(-20., 0.), WPos::new(20., 0.));
+        let params = get_curves_intersections(&line, &arc, 1e-6);
+        assert_eq!(params.len(), 1);
+        assert_hit(&line, &arc, &params, &WPos::new(-10., 0.));
+        let [min, max] = arc.get_bounding_box();
+        assert!(min.dist(&WPos::new(-10., -20.)) < 1e-9);
+        assert!(max.dist(&WPos::new(0., 20.)) < 1e-9);
+    }
+
+    #[test]
+    fn test_line_bezier() {
+        let bezier = Curve::CubicBezier(
+            WPos::new(0., 0.),
+            WPos::new(0., 10.),
+            WPos::new(10., 10.),
+            WPos::new(10., 0.),
+        );
+        let line = Curve::Line(WPos::new(-5., 5.), WPos::new(15., 5.));
+        let params = get_curves_intersections(&line, &bezier, 1e-6);
+        assert_eq!(params.len(), 2);
+        for (t_line, t_bezier) in params.iter() {
+            assert!(line.get_pos(*t_line).dist(&bezier.get_pos(*t_bezier)) < 1e-6);
+        }
+        // The top of the curve is at y = 7.5
+        let tangent = Curve::Line(WPos::new(-5., 7.5), WPos::new(15., 7.5));
+        let params = get_curves_intersections(&bezier, &tangent, 1e-6);
+        assert_eq!(params.len(), 1);
+        assert_hit(&bezier, &tangent, &params, &WPos::new(5., 7.5));
+    }
+
+    #[test]
+    fn test_curve_curve() {
+        let circle1 = Curve::Arc(WPos::new(0., 0.), WPos::new(10., 10.), 0., 0., 2. * PI);
+        let circle2 = Curve::Arc(WPos::new(10., 0.), WPos::new(10., 10.), 0., 0., 2. * PI);
+        let params = get_curves_intersections(&circle1, &circle2, 1e-6);
+        assert_eq!(params.len(), 2);
+        let y = 75_f64.sqrt();
+        assert_hit(&circle1, &circle2, &params, &WPos::new(5., y));
+        assert_hit(&circle1, &circle2, &params, &WPos::new(5., -y));
+
+        let bezier1 = Curve::QuadBezier(WPos::new(0., 0.), WPos::new(5., 10.), WPos::new(10., 0.));
+        let bezier2 = Curve::QuadBezier(WPos::new(0., 5.), WPos::new(5., -5.), WPos::new(10., 5.));
+        let params = get_curves_intersections(&bezier1, &bezier2, 1e-6);
+        assert_eq!(params.len(), 2);
+        let dx = 2.5 * 2_f64.sqrt();
+        assert_hit(&bezier1, &bezier2, &params, &WPos::new(5. - dx, 2.5));
+        assert_hit(&bezier1, &bezier2, &params, &WPos::new(5. + dx, 2.5));
+
+        // Overlapping arcs of the same circle only meet at their ends
+        let arc = Curve::Arc(WPos::new(0., 0.), WPos::new(10., 10.), 0., PI / 2., PI);
+        let params = get_curves_intersections(&circle1, &arc, 1e-6);
+        assert_eq!(params.len(), 2);
+    }
+}
@@ -1,10 +1,31 @@
+mod array;
 mod canvas;
+mod corner;
 mod datapool;
+mod document;
+mod dxfexport;
+mod dxfimport;
+mod history;
+mod import;
+pub mod intersection;
 mod math;
+mod svgexport;
+mod svgimport;
+pub mod cam {
+    pub mod backplot;
+    pub mod gcode;
+    pub mod kerf;
+    pub mod leads;
+    pub mod ordering;
+    pub mod parameters;
+    pub mod postprocessor;
+    pub mod toolpath;
+}
 pub mod shapes {
     pub mod cubicbezier;
     pub mod ellipse;
     pub mod line;
+    pub mod polyline;
     pub mod quadbezier;
     pub mod rectangle;
     pub mod types;
@@ -1,7 +1,7 @@
 #[cfg(not(test))]
 use web_sys::console;
 
-use crate::shapes::types::{ConstructionType, Point, WPos};
+use crate::shapes::types::{ConstructionType, Point, Shape, WPos};
 use std::f64::consts::PI;
 
 pub const EPSILON: f64 = 1e-2; // Some small value
@@ -146,6 +146,24 @@ pub fn is_box_inside(box_outer: &[WPos; 2], box_inner: &[WPos; 2]) -> bool {
         && tr_inner.wx <= tr_outer.wx
         && tr_inner.wy <= tr_outer.wy
 }
+// Smallest box holding all the shapes, its corners ordered
+pub fn get_shapes_bounded_rectangle<'a>(
+    shapes: impl IntoIterator<Item = &'a Box<dyn Shape>>,
+) -> Option<[WPos; 2]> {
+    shapes
+        .into_iter()
+        .map(|shape| {
+            let mut bb = shape.get_bounded_rectangle();
+            reorder_corners(&mut bb);
+            bb
+        })
+        .reduce(|bounds, bb| {
+            [
+                WPos::new(bounds[0].wx.min(bb[0].wx), bounds[0].wy.min(bb[0].wy)),
+                WPos::new(bounds[1].wx.max(bb[1].wx), bounds[1].wy.max(bb[1].wy)),
+            ]
+        })
+}
 pub fn reorder_corners(bb: &mut [WPos; 2]) {
     let pt1 = bb[0];
     let pt2 = bb[1];
@@ -483,6 +501,16 @@ pub fn push_handle(cst: &mut Vec<ConstructionType>, pt: &Point, size_handle: f64
 //     }
 // }
 
+// z of the cross product of two vectors, positive when v2 turns
+// counterclockwise from v1
+pub fn cross(v1: &WPos, v2: &WPos) -> f64 {
+    v1.wx * v2.wy - v1.wy * v2.wx
+}
+// The vector turned by a quarter of turn counterclockwise, on its left
+pub fn perp(pos: &WPos) -> WPos {
+    WPos::new(-pos.wy, pos.wx)
+}
+
 pub fn get_point_on_cubic_bezier(
     t: f64,
     start: &WPos,
@@ -503,11 +531,224 @@ pub fn get_point_on_cubic_bezier(
 
     result
 }
+pub fn get_point_on_quad_bezier(t: f64, start: &WPos, ctrl: &WPos, end: &WPos) -> WPos {
+    let u = 1.0 - t;
+    let mut result = *start * (u * u); // (1-t)^2 * start
+    result += *ctrl * 2.0 * u * t; // 2(1-t) * t * ctrl
+    result += *end * (t * t); // t^2 * end
+
+    result
+}
+// Curves parametrized from 0 to 1 by get_pos: ratio of the point closest to
+// pos, by coarse sampling then ternary search around the best sample
+pub fn get_closest_ratio(get_pos: impl Fn(f64) -> WPos, pos: &WPos, nb_samples: usize) -> f64 {
+    let dist_at = |r: f64| get_pos(r).dist(pos);
+    let mut best_r = 0.;
+    let mut best_dist = f64::MAX;
+    for i in 0..=nb_samples {
+        let r = i as f64 / nb_samples as f64;
+        let dist = dist_at(r);
+        if dist < best_dist {
+            best_dist = dist;
+            best_r = r;
+        }
+    }
+    let mut r_min = (best_r - 1. / nb_samples as f64).max(0.);
+    let mut r_max = (best_r + 1. / nb_samples as f64).min(1.);
+    for _i in 0..MAX_ITERATIONS {
+        let r1 = r_min + (r_max - r_min) / 3.;
+        let r2 = r_max - (r_max - r_min) / 3.;
+        if dist_at(r1) < dist_at(r2) {
+            r_max = r2;
+        } else {
+            r_min = r1;
+        }
+    }
+    (r_min + r_max) / 2.
+}
+pub fn get_sampled_length(get_pos: impl Fn(f64) -> WPos, nb_samples: usize) -> f64 {
+    (0..nb_samples)
+        .map(|i| {
+            let r1 = i as f64 / nb_samples as f64;
+            let r2 = (i + 1) as f64 / nb_samples as f64;
+            get_pos(r1).dist(&get_pos(r2))
+        })
+        .sum()
+}
+// Ratio covering the given distance along a shape of the given length
+pub fn get_step_ratio(step: f64, length: f64) -> f64 {
+    if length > 0. {
+        step / length
+    } else {
+        step
+    }
+}
+// Polylines are parametrized by their length, from 0 on the first vertex to
+// 1 on the last one
+pub fn get_polyline_length(pts: &[WPos]) -> f64 {
+    pts.windows(2).map(|seg| seg[0].dist(&seg[1])).sum()
+}
+pub fn get_polyline_pos_from_ratio(pts: &[WPos], r: f64) -> WPos {
+    let mut length = r.clamp(0., 1.) * get_polyline_length(pts);
+    for seg in pts.windows(2) {
+        let seg_length = seg[0].dist(&seg[1]);
+        if length <= seg_length && seg_length > 0. {
+            return seg[0].lerp(&seg[1], length / seg_length);
+        }
+        length -= seg_length;
+    }
+    pts[pts.len() - 1]
+}
+// Ratio of the point of the polyline closest to pos
+pub fn get_polyline_ratio_from_pos(pts: &[WPos], pos: &WPos) -> f64 {
+    let total_length = get_polyline_length(pts);
+    if total_length == 0. {
+        return 0.;
+    }
+    let mut best = (f64::MAX, 0.);
+    let mut length = 0.;
+    for seg in pts.windows(2) {
+        let seg_length = seg[0].dist(&seg[1]);
+        if seg_length > 0. {
+            let dir = seg[1] - seg[0];
+            let r = ((*pos - seg[0]).dot(&dir) / dir.norm2()).clamp(0., 1.);
+            let dist = pos.dist(&seg[0].lerp(&seg[1], r));
+            if dist < best.0 {
+                best = (dist, (length + r * seg_length) / total_length);
+            }
+        }
+        length += seg_length;
+    }
+    best.1
+}
+// Vertices of the polyline before and after the ratio r
+pub fn split_polyline(pts: &[WPos], r: f64) -> (Vec<WPos>, Vec<WPos>) {
+    let split_pos = get_polyline_pos_from_ratio(pts, r);
+    let split_length = r.clamp(0., 1.) * get_polyline_length(pts);
+    let mut first = vec![pts[0]];
+    let mut second = vec![split_pos];
+    let mut length = 0.;
+    for seg in pts.windows(2) {
+        length += seg[0].dist(&seg[1]);
+        if length < split_length {
+            first.push(seg[1]);
+        } else if length > split_length {
+            second.push(seg[1]);
+        }
+    }
+    first.push(split_pos);
+    (first, second)
+}
+// Vertices of a closed polyline from the ratio r_start to the ratio r_end,
+// going across its first vertex when r_end comes before r_start
+pub fn get_closed_polyline_piece(pts: &[WPos], r_start: f64, r_end: f64) -> Vec<WPos> {
+    let (_, after_start) = split_polyline(pts, r_start);
+    if r_end > r_start {
+        let r = (r_end - r_start) / (1. - r_start);
+        split_polyline(&after_start, r).0
+    } else {
+        let (before_end, _) = split_polyline(pts, r_end);
+        after_start.into_iter().chain(before_end).collect()
+    }
+}
+// Angles follow the canvas convention: the arc is swept from start_angle to
+// end_angle with increasing angles, and an empty sweep is a full turn
+pub fn get_sweep_angle(start_angle: f64, end_angle: f64) -> f64 {
+    let sweep = (end_angle - start_angle).rem_euclid(2. * PI);
+    if sweep < 1e-6 {
+        2. * PI
+    } else {
+        sweep
+    }
+}
 pub fn get_point_from_angle(radius: &WPos, angle: f64) -> WPos {
     let x = radius.wx.abs() * angle.cos();
     let y = radius.wy.abs() * angle.sin();
     WPos { wx: x, wy: y }
 }
+pub fn get_ellipse_pos(center: &WPos, radius: &WPos, rotation: f64, angle: f64) -> WPos {
+    let pos = get_point_from_angle(radius, angle);
+    let (sin, cos) = rotation.sin_cos();
+    *center + WPos::new(pos.wx * cos - pos.wy * sin, pos.wx * sin + pos.wy * cos)
+}
+
+// Affine transformation of the plane, a position is mapped to
+// (a x + c y + e, b x + d y + f) like with the SVG matrix(a b c d e f)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+impl Transform {
+    pub fn new(a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Transform {
+        Transform { a, b, c, d, e, f }
+    }
+    pub fn identity() -> Transform {
+        Transform::new(1., 0., 0., 1., 0., 0.)
+    }
+    pub fn translate(tx: f64, ty: f64) -> Transform {
+        Transform::new(1., 0., 0., 1., tx, ty)
+    }
+    pub fn scale(sx: f64, sy: f64) -> Transform {
+        Transform::new(sx, 0., 0., sy, 0., 0.)
+    }
+    // Angle following the canvas convention
+    pub fn rotate(angle: f64) -> Transform {
+        let (sin, cos) = angle.sin_cos();
+        Transform::new(cos, sin, -sin, cos, 0., 0.)
+    }
+    pub fn skew(angle_x: f64, angle_y: f64) -> Transform {
+        Transform::new(1., angle_y.tan(), angle_x.tan(), 1., 0., 0.)
+    }
+    // Mirroring about the line going through the origin along the direction
+    pub fn mirror(direction: &WPos) -> Transform {
+        let (sin, cos) = (2. * direction.wy.atan2(direction.wx)).sin_cos();
+        Transform::new(cos, sin, sin, -cos, 0., 0.)
+    }
+    // Same transformation, done around the pivot instead of the origin
+    pub fn around(self, pivot: &WPos) -> Transform {
+        Transform::translate(pivot.wx, pivot.wy) * self * Transform::translate(-pivot.wx, -pivot.wy)
+    }
+    // Without the translation, it maps the vectors
+    pub fn get_linear(&self) -> Transform {
+        Transform::new(self.a, self.b, self.c, self.d, 0., 0.)
+    }
+    pub fn apply(&self, pos: &WPos) -> WPos {
+        WPos::new(
+            self.a * pos.wx + self.c * pos.wy + self.e,
+            self.b * pos.wx + self.d * pos.wy + self.f,
+        )
+    }
+    pub fn get_determinant(&self) -> f64 {
+        self.a * self.d - self.b * self.c
+    }
+    // Rectangles with sides parallel to the axes stay so
+    pub fn is_axis_aligned(&self) -> bool {
+        self.b.abs() < 1e-9 && self.c.abs() < 1e-9
+    }
+    // The axes are mapped on the axes, maybe swapped
+    pub fn keeps_axes(&self) -> bool {
+        self.is_axis_aligned() || (self.a.abs() < 1e-9 && self.d.abs() < 1e-9)
+    }
+}
+// Transformation applying rhs first, then self
+impl std::ops::Mul for Transform {
+    type Output = Transform;
+    fn mul(self, rhs: Transform) -> Transform {
+        Transform::new(
+            self.a * rhs.a + self.c * rhs.b,
+            self.b * rhs.a + self.d * rhs.b,
+            self.a * rhs.c + self.c * rhs.d,
+            self.b * rhs.c + self.d * rhs.d,
+            self.a * rhs.e + self.c * rhs.f + self.e,
+            self.b * rhs.e + self.d * rhs.f + self.f,
+        )
+    }
+}
 
 #[inline]
 pub fn _switch_wx(point1: &mut WPos, point2: &mut WPos) {
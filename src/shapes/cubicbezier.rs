@@ -40,6 +40,24 @@ impl CubicBezier {
             init: true,
         })
     }
+    pub fn from_points(position: &WPos, points: &[(PointType, Point)]) -> Option<CubicBezier> {
+        let point = |point_type: PointType| {
+            points
+                .iter()
+                .find(|(pt, _)| *pt == point_type)
+                .map(|(_, point)| *point)
+        };
+        Some(CubicBezier {
+            start_point: point(PointType::Start)?,
+            ctrl1_point: point(PointType::Ctrl1)?,
+            ctrl2_point: point(PointType::Ctrl2)?,
+            end_point: point(PointType::End)?,
+            position: *position,
+            saved_position: *position,
+            selected: false,
+            init: false,
+        })
+    }
     pub fn get_point_on_cubic_bezier(&self, t: f64) -> WPos {
         let u = 1.0 - t;
         let tt = t * t;
@@ -79,6 +97,15 @@ impl CubicBezier {
         }
         min_dist <= precision
     }
+    // Parameter of the point of the curve closest to pos (relative to the
+    // shape position): coarse sampling, then ternary search around the best
+    // sample
+    fn get_closest_t(&self, pos: &WPos) -> f64 {
+        get_closest_ratio(|t| self.get_point_on_cubic_bezier(t), pos, 64)
+    }
+    fn get_length(&self) -> f64 {
+        get_sampled_length(|t| self.get_point_on_cubic_bezier(t), 32)
+    }
 }
 impl Shape for CubicBezier {
     fn is_init(&self) -> bool {
@@ -90,30 +117,50 @@ impl Shape for CubicBezier {
     fn get_pos(&self) -> WPos {
         self.position
     }
-    fn get_step_r(&self, grab_handle_precision: f64) -> f64 {
-        //
-        0.
+    fn get_step_r(&self, step: f64) -> f64 {
+        get_step_ratio(step, self.get_length())
     }
     fn get_pos_from_ratio(&self, r: f64) -> WPos {
-        // TODO
-        WPos::zero()
+        self.get_point_on_cubic_bezier(r) + self.position
     }
-    fn get_ratio_from_pos(&self, rpos: &WPos) -> f64 {
-        // TODO
-        0.
+    fn get_ratio_from_pos(&self, pos: &WPos) -> f64 {
+        self.get_closest_t(&(*pos - self.position))
     }
     fn get_projected_pos(&self, pick_pos: &WPos) -> WPos {
-        // TODO
-        WPos::zero()
+        self.get_pos_from_ratio(self.get_ratio_from_pos(pick_pos))
     }
     fn split(&self, pos: &WPos) -> (Option<Box<dyn Shape>>, Option<Box<dyn Shape>>) {
-        // TODO
-        (None, None)
+        // De Casteljau subdivision at the parameter of the point
+        let t = self.get_ratio_from_pos(pos);
+        let start = self.start_point.wpos + self.position;
+        let ctrl1 = self.ctrl1_point.wpos + self.position;
+        let ctrl2 = self.ctrl2_point.wpos + self.position;
+        let end = self.end_point.wpos + self.position;
+
+        let p01 = start.lerp(&ctrl1, t);
+        let p12 = ctrl1.lerp(&ctrl2, t);
+        let p23 = ctrl2.lerp(&end, t);
+        let p012 = p01.lerp(&p12, t);
+        let p123 = p12.lerp(&p23, t);
+        let split_pos = p012.lerp(&p123, t);
+        (
+            if let Some(mut bezier1) = CubicBezier::new(&start, &p01, &p012, &split_pos) {
+                bezier1.init_done();
+                Some(Box::new(bezier1))
+            } else {
+                None
+            },
+            if let Some(mut bezier2) = CubicBezier::new(&split_pos, &p123, &p23, &end) {
+                bezier2.init_done();
+                Some(Box::new(bezier2))
+            } else {
+                None
+            },
+        )
     }
 
     fn dist(&self, pick_pos: &WPos) -> f64 {
-        // TODO
-        0.
+        pick_pos.dist(&self.get_projected_pos(pick_pos))
     }
     // fn is_shape_under_pick_pos(&self, pick_pos: &WPos, grab_handle_precision: f64) -> bool {
     //     let pick_pos = *pick_pos - self.position;
@@ -253,6 +300,34 @@ impl Shape for CubicBezier {
             *pick_pos = self.position + end_pos;
         }
     }
+    fn clone_box(&self) -> Box<dyn Shape> {
+        Box::new(self.clone())
+    }
+    fn transform(&mut self, transform: &Transform) -> Option<Vec<Box<dyn Shape>>> {
+        let linear = transform.get_linear();
+        self.position = transform.apply(&self.position);
+        self.saved_position = self.position;
+        for point in [
+            &mut self.start_point,
+            &mut self.ctrl1_point,
+            &mut self.ctrl2_point,
+            &mut self.end_point,
+        ] {
+            point.wpos = linear.apply(&point.wpos);
+        }
+        None
+    }
+    fn get_type_name(&self) -> &str {
+        "cubicbezier"
+    }
+    fn get_points(&self) -> Vec<(PointType, Point)> {
+        vec![
+            (PointType::Start, self.start_point),
+            (PointType::Ctrl1, self.ctrl1_point),
+            (PointType::Ctrl2, self.ctrl2_point),
+            (PointType::End, self.end_point),
+        ]
+    }
 
     fn get_construction(&self) -> Vec<ConstructionType> {
         let mut cst: Vec<ConstructionType> = vec![];
@@ -431,3 +506,65 @@ impl Shape for CubicBezier {
     }
 }
 // impl ShapePool for CubicBezier {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_arch() -> CubicBezier {
+        CubicBezier::new(
+            &WPos::new(0., 0.),
+            &WPos::new(0., 10.),
+            &WPos::new(10., 10.),
+            &WPos::new(10., 0.),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_dist_and_projection() {
+        let bezier = get_arch();
+        // The top of the arch is at t = 0.5
+        let top = bezier.get_pos_from_ratio(0.5);
+        assert!(top.dist(&WPos::new(5., 7.5)) < 1e-9);
+        assert!(bezier.dist(&top) < 1e-6);
+        assert!((bezier.dist(&WPos::new(5., 10.)) - 2.5).abs() < 1e-6);
+        assert!(bezier.get_projected_pos(&WPos::new(5., 10.)).dist(&top) < 1e-6);
+        assert!((bezier.get_ratio_from_pos(&WPos::new(5., 10.)) - 0.5).abs() < 1e-6);
+        // Beyond the ends the closest point is an end
+        assert!(
+            bezier
+                .get_projected_pos(&WPos::new(-5., -5.))
+                .dist(&WPos::new(0., 0.))
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let bezier = get_arch();
+        let pos = bezier.get_pos_from_ratio(0.25);
+        let (first, second) = bezier.split(&pos);
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert!(first.get_pos_from_ratio(1.).dist(&pos) < 1e-6);
+        assert!(second.get_pos_from_ratio(0.).dist(&pos) < 1e-6);
+        // Both parts follow the original curve
+        for i in 0..=10 {
+            let r = i as f64 / 10.;
+            assert!(bezier.dist(&first.get_pos_from_ratio(r)) < 1e-6);
+            assert!(bezier.dist(&second.get_pos_from_ratio(r)) < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_get_step_r() {
+        let bezier = CubicBezier::new(
+            &WPos::new(0., 0.),
+            &WPos::new(10., 0.),
+            &WPos::new(20., 0.),
+            &WPos::new(30., 0.),
+        )
+        .unwrap();
+        assert!((bezier.get_step_r(3.) - 0.1).abs() < 1e-9);
+    }
+}
@@ -1,16 +1,20 @@
 use std::f64::consts::PI;
-#[cfg(not(test))]
-use web_sys::console;
 
 use super::types::{ConstructionType, LayerType, Point, PointType, Shape, WPos};
 use crate::math::*;
 
+// The points are where they are drawn, turned with the ellipse, the radius
+// point being the corner of the box holding the ellipse before it is turned
 #[derive(Clone)]
 pub struct Ellipse {
     center_point: Point,
     radius_point: Point,
     sa_point: Point,
     ea_point: Point,
+    // Angle of the first axis, following the canvas convention
+    rotation: f64,
+    // The axis handle, at the end of the second axis, turns the ellipse
+    axis_selected: bool,
     position: WPos,
     saved_position: WPos,
     selected: bool,
@@ -53,74 +57,149 @@ impl Ellipse {
             radius_point,
             sa_point,
             ea_point,
+            rotation: 0.,
+            axis_selected: false,
             position,
             saved_position: position,
             selected: false,
             init: true,
         }
     }
-    fn ellipse_line_intersection(&self, pt: &WPos) -> WPos {
-        let center_pos = self.center_point.wpos;
-        let mut radius_pos = self.radius_point.wpos;
-        radius_pos.wy = -radius_pos.wy;
-
-        let m = (pt.wy - center_pos.wy) / (pt.wx - center_pos.wx);
-        let h = center_pos.wx;
-        let k = center_pos.wy;
-        let a = radius_pos.wx;
-        let b = radius_pos.wy;
-        // Calculating y-intercept using the center of the ellipse
-        let c = k - m * h;
-        let a_coef = m.powi(2) / b.powi(2) + 1.0 / a.powi(2);
-        let b_coef = 2.0 * m * (c - k) / b.powi(2) - 2.0 * h / a.powi(2);
-        let c_coef = h.powi(2) / a.powi(2) + (c - k).powi(2) / b.powi(2) - 1.0;
-        let discriminant = b_coef.powi(2) - 4.0 * a_coef * c_coef;
-        let x1 = (-b_coef + discriminant.sqrt()) / (2.0 * a_coef);
-        let y1 = m * x1 + c;
-        let pt1 = WPos::new(x1, y1);
-        let x2 = (-b_coef - discriminant.sqrt()) / (2.0 * a_coef);
-        let y2 = m * x2 + c;
-        let pt2 = WPos::new(x2, y2);
-        if pt.dist(&pt1) < pt.dist(&pt2) {
-            pt1
-        } else {
-            pt2
+    // Arc of given radii turned by the rotation, without snapping, used by
+    // the imports
+    pub fn from_arc(
+        center_pos: &WPos,
+        radius: &WPos,
+        rotation: f64,
+        start_angle: f64,
+        end_angle: f64,
+    ) -> Option<Ellipse> {
+        if radius.wx == 0. || radius.wy == 0. {
+            return None;
         }
+        let turn = Transform::rotate(rotation);
+        let radius_pos = radius.abs();
+        let sa_pos = turn.apply(&get_point_from_angle(&radius_pos, start_angle));
+        let ea_pos = turn.apply(&get_point_from_angle(&radius_pos, end_angle));
+
+        Some(Ellipse {
+            center_point: Point::new(&WPos::zero(), true, true, false),
+            radius_point: Point::new(&turn.apply(&radius_pos), true, true, false),
+            sa_point: Point::new(&sa_pos, true, true, false),
+            ea_point: Point::new(&ea_pos, true, true, false),
+            rotation,
+            axis_selected: false,
+            position: *center_pos,
+            saved_position: *center_pos,
+            selected: false,
+            init: true,
+        })
+    }
+    // The projects saved before the rotation have no axis point
+    pub fn from_points(position: &WPos, points: &[(PointType, Point)]) -> Option<Ellipse> {
+        let point = |point_type: PointType| {
+            points
+                .iter()
+                .find(|(pt, _)| *pt == point_type)
+                .map(|(_, point)| *point)
+        };
+        let center_point = point(PointType::Center)?;
+        let axis_point = point(PointType::Axis);
+        let rotation = axis_point.map_or(0., |axis_point| {
+            let axis = axis_point.wpos - center_point.wpos;
+            axis.wy.atan2(axis.wx) + PI / 2.
+        });
+        Some(Ellipse {
+            center_point,
+            radius_point: point(PointType::Radius)?,
+            sa_point: point(PointType::StartAngle)?,
+            ea_point: point(PointType::EndAngle)?,
+            rotation,
+            axis_selected: axis_point.map_or(false, |axis_point| axis_point.selected),
+            position: *position,
+            saved_position: *position,
+            selected: false,
+            init: false,
+        })
+    }
+    // From the frame of the ellipse to the one of the sheet, and back
+    fn get_turned(&self, pos: &WPos) -> WPos {
+        Transform::rotate(self.rotation).apply(pos)
+    }
+    fn get_unturned(&self, pos: &WPos) -> WPos {
+        Transform::rotate(-self.rotation).apply(pos)
+    }
+    // Radii along the axes of the ellipse
+    fn get_radius(&self) -> WPos {
+        self.get_unturned(&(self.radius_point.wpos - self.center_point.wpos))
+            .abs()
+    }
+    // At the end of the second axis, above the center when not turned
+    fn get_axis_point(&self) -> Point {
+        let pos = self.center_point.wpos + self.get_point_from_angle(-PI / 2.);
+        Point::new(&pos, true, true, self.axis_selected)
     }
     fn angle_on_ellipse(&self, pos: &WPos) -> f64 {
-        let center_pos = self.center_point.wpos;
-        let radius_pos = self.radius_point.wpos;
-        f64::atan2(
-            (pos.wy - center_pos.wy) / radius_pos.wy.abs(),
-            (pos.wx - center_pos.wx) / radius_pos.wx.abs(),
-        )
+        let pos = self.get_unturned(&(*pos - self.center_point.wpos));
+        let radius = self.get_radius();
+        f64::atan2(pos.wy / radius.wy, pos.wx / radius.wx)
     }
+    // Relative to the center
     fn get_point_from_angle(&self, angle: f64) -> WPos {
-        let x = self.radius_point.wpos.wx.abs() * angle.cos();
-        let y = self.radius_point.wpos.wy.abs() * angle.sin();
-        WPos { wx: x, wy: y }
-    }
-    fn is_point_on_ellipse(&self, pos: &WPos, precision: f64) -> bool {
-        let pt_int = self.ellipse_line_intersection(&pos);
-        if pt_int.dist(&pos) > precision {
-            return false;
-        }
-        if self.sa_point.wpos.dist(&self.ea_point.wpos) < 1. {
-            return true;
-        }
-        let start_angle = self.angle_on_ellipse(&self.sa_point.wpos);
-        let end_angle = self.angle_on_ellipse(&self.ea_point.wpos);
-        let angle = self.angle_on_ellipse(&pos);
-
-        if end_angle > start_angle {
-            angle >= start_angle && angle <= end_angle
-        } else {
-            !(angle >= end_angle && angle <= start_angle)
-        }
+        self.get_turned(&get_point_from_angle(&self.get_radius(), angle))
     }
+    // Polar angle in the frame of the ellipse of a position relative to the
+    // center
     pub fn get_angle_from_pos(&self, pos: &WPos) -> f64 {
+        let pos = self.get_unturned(pos);
         pos.wy.atan2(pos.wx)
     }
+    // Start angle and sweep of the arc, a full turn for a whole ellipse
+    fn get_arc_angles(&self) -> (f64, f64) {
+        let start_angle = self.angle_on_ellipse(&self.sa_point.wpos);
+        let end_angle = self.angle_on_ellipse(&self.ea_point.wpos);
+        (start_angle, get_sweep_angle(start_angle, end_angle))
+    }
+    // Ratio of the point of the arc closest to pos: coarse sampling, then
+    // ternary search around the best sample
+    fn get_closest_r(&self, pos: &WPos) -> f64 {
+        get_closest_ratio(|r| self.get_pos_from_ratio(r), pos, 72)
+    }
+    fn get_length(&self) -> f64 {
+        get_sampled_length(|r| self.get_pos_from_ratio(r), 72)
+    }
+    // Same ellipse, limited to the given angles
+    fn get_arc(&self, start_angle: f64, end_angle: f64) -> Ellipse {
+        let mut arc = self.clone();
+        arc.sa_point.wpos = self.center_point.wpos + self.get_point_from_angle(start_angle);
+        arc.ea_point.wpos = self.center_point.wpos + self.get_point_from_angle(end_angle);
+        arc.deselect_all_points();
+        arc.selected = false;
+        arc.init = false;
+        arc
+    }
+    // End of the arc moved to the angle picked, magnetized in the frame of
+    // the ellipse
+    fn get_arc_end(&self, pick_pos: &WPos, magnet_distance: f64) -> WPos {
+        let pos = *pick_pos - self.position - self.center_point.wpos;
+        let radius = self.get_radius();
+        let mut angle = self.get_angle_from_pos(&pos);
+        magnet_to_45(&mut angle, &radius, magnet_distance);
+        let mut end = get_point_from_angle(&radius, angle);
+        magnet_to_x(&mut end, &WPos::zero(), magnet_distance);
+        magnet_to_y(&mut end, &WPos::zero(), magnet_distance);
+        magnet_to_xy(&mut end, &WPos::zero(), magnet_distance);
+        self.center_point.wpos + self.get_turned(&end)
+    }
+    // Radii and arc ends put back once the rotation or the radii change
+    fn set_geometry(&mut self, rotation: f64, radius: &WPos) {
+        let (start_angle, sweep) = self.get_arc_angles();
+        self.rotation = rotation;
+        self.radius_point.wpos = self.center_point.wpos + self.get_turned(radius);
+        self.sa_point.wpos = self.center_point.wpos + self.get_point_from_angle(start_angle);
+        self.ea_point.wpos =
+            self.center_point.wpos + self.get_point_from_angle(start_angle + sweep);
+    }
 }
 impl Shape for Ellipse {
     fn is_init(&self) -> bool {
@@ -132,36 +211,56 @@ impl Shape for Ellipse {
     fn get_pos(&self) -> WPos {
         self.position
     }
-    fn get_step_r(&self, grab_handle_precision: f64) -> f64 {
-        //
-        0.
+    fn get_step_r(&self, step: f64) -> f64 {
+        get_step_ratio(step, self.get_length())
     }
 
     fn get_pos_from_ratio(&self, r: f64) -> WPos {
-        // TODO
-        WPos::zero()
+        let (start_angle, sweep) = self.get_arc_angles();
+        let angle = start_angle + r * sweep;
+        self.position + self.center_point.wpos + self.get_point_from_angle(angle)
     }
-    fn get_ratio_from_pos(&self, rpos: &WPos) -> f64 {
-        // TODO
-        0.
+    fn get_ratio_from_pos(&self, pos: &WPos) -> f64 {
+        self.get_closest_r(pos)
     }
     fn get_projected_pos(&self, pick_pos: &WPos) -> WPos {
-        // TODO
-        WPos::zero()
+        self.get_pos_from_ratio(self.get_closest_r(pick_pos))
     }
     fn split(&self, pos: &WPos) -> (Option<Box<dyn Shape>>, Option<Box<dyn Shape>>) {
-        // TODO
-        (None, None)
+        // Both arcs keep the center and the radii, the split angle becomes
+        // the end of the first one and the start of the second one
+        let r = self.get_closest_r(pos);
+        let (start_angle, sweep) = self.get_arc_angles();
+        let split_angle = start_angle + r * sweep;
+        let min_sweep = 1e-6;
+        (
+            if r * sweep > min_sweep {
+                Some(Box::new(self.get_arc(start_angle, split_angle)))
+            } else {
+                None
+            },
+            if (1. - r) * sweep > min_sweep {
+                Some(Box::new(self.get_arc(split_angle, start_angle + sweep)))
+            } else {
+                None
+            },
+        )
+    }
+    fn is_closed(&self) -> bool {
+        self.get_arc_angles().1 >= 2. * PI
+    }
+    fn split_closed(&self, start: &WPos, end: &WPos) -> Option<Box<dyn Shape>> {
+        if !self.is_closed() {
+            return None;
+        }
+        let (start_angle, sweep) = self.get_arc_angles();
+        let angle = |pos: &WPos| start_angle + self.get_closest_r(pos) * sweep;
+        Some(Box::new(self.get_arc(angle(start), angle(end))))
     }
 
     fn dist(&self, pick_pos: &WPos) -> f64 {
-        // TODO
-        0.
+        pick_pos.dist(&self.get_projected_pos(pick_pos))
     }
-    // fn is_shape_under_pick_pos(&self, pick_pos: &WPos, grab_handle_precision: f64) -> bool {
-    //     let pick_pos = *pick_pos - self.position;
-    //     self.is_point_on_ellipse(&pick_pos, grab_handle_precision / 2.)
-    // }
     fn get_shape_point_type_under_pick_pos(
         &mut self,
         pick_pos: &WPos,
@@ -182,6 +281,9 @@ impl Shape for Ellipse {
         if pick_pos.dist(&self.ea_point.wpos) < grab_handle_precision {
             return Some(PointType::EndAngle);
         }
+        if pick_pos.dist(&self.get_axis_point().wpos) < grab_handle_precision {
+            return Some(PointType::Axis);
+        }
         None
     }
 
@@ -196,6 +298,7 @@ impl Shape for Ellipse {
         self.radius_point.selected = false;
         self.sa_point.selected = false;
         self.ea_point.selected = false;
+        self.axis_selected = false;
     }
     fn is_selected(&self) -> bool {
         self.selected
@@ -208,6 +311,7 @@ impl Shape for Ellipse {
             self.radius_point.selected = true;
             self.sa_point.selected = false;
             self.ea_point.selected = false;
+            self.axis_selected = false;
         }
         if self.selected {
             match (
@@ -215,69 +319,37 @@ impl Shape for Ellipse {
                 self.radius_point.selected,
                 self.sa_point.selected,
                 self.ea_point.selected,
+                self.axis_selected,
             ) {
-                (false, false, false, false) | (true, false, false, false) => {
-                    let mut pick_pos_ms_dwn = *pick_pos_ms_dwn;
-                    self.position = self.saved_position + pick_pos - pick_pos_ms_dwn;
+                (false, false, false, false, false) | (true, false, false, false, false) => {
+                    self.position = self.saved_position + pick_pos - *pick_pos_ms_dwn;
                 }
-                (false, true, false, false) => {
-                    self.radius_point.wpos = pick_pos - self.position;
-                    let pos = pick_pos - self.position;
-                    if pos.wx != self.center_point.wpos.wx || pos.wy != self.center_point.wpos.wy {
-                        self.radius_point.wpos = pos;
+                (false, true, false, false, false) => {
+                    let pos = pick_pos - self.position - self.center_point.wpos;
+                    let radius = self.get_unturned(&pos).abs();
+                    if radius.wx > 0. && radius.wy > 0. {
+                        self.set_geometry(self.rotation, &radius);
+                        self.radius_point.wpos = self.center_point.wpos + pos;
                     }
-
-                    let start_angle = self.angle_on_ellipse(&self.sa_point.wpos);
-                    self.sa_point.wpos = self.get_point_from_angle(start_angle);
-
-                    let end_angle = self.angle_on_ellipse(&self.ea_point.wpos);
-                    self.ea_point.wpos = self.get_point_from_angle(end_angle);
                 }
-                (false, false, true, false) => {
-                    let pos = pick_pos - self.position - self.center_point.wpos;
-                    let mut angle = self.get_angle_from_pos(&pos);
-
-                    magnet_to_45(&mut angle, &self.radius_point.wpos, magnet_distance);
-                    self.sa_point.wpos = get_point_from_angle(&self.radius_point.wpos, angle);
-
-                    magnet_to_x(
-                        &mut self.sa_point.wpos,
-                        &self.center_point.wpos,
-                        magnet_distance,
-                    );
-                    magnet_to_y(
-                        &mut self.sa_point.wpos,
-                        &self.center_point.wpos,
-                        magnet_distance,
-                    );
-                    magnet_to_xy(
-                        &mut self.sa_point.wpos,
-                        &self.center_point.wpos,
-                        magnet_distance,
-                    );
+                (false, false, true, false, false) => {
+                    self.sa_point.wpos = self.get_arc_end(&pick_pos, magnet_distance);
+                }
+                (false, false, false, true, false) => {
+                    self.ea_point.wpos = self.get_arc_end(&pick_pos, magnet_distance);
                 }
-                (false, false, false, true) => {
+                (false, false, false, false, true) => {
                     let pos = pick_pos - self.position - self.center_point.wpos;
-                    let mut angle = self.get_angle_from_pos(&pos);
-
-                    magnet_to_45(&mut angle, &self.radius_point.wpos, magnet_distance);
-                    self.ea_point.wpos = get_point_from_angle(&self.radius_point.wpos, angle);
-
-                    magnet_to_x(
-                        &mut self.ea_point.wpos,
-                        &self.center_point.wpos,
-                        magnet_distance,
-                    );
-                    magnet_to_y(
-                        &mut self.ea_point.wpos,
-                        &self.center_point.wpos,
-                        magnet_distance,
-                    );
-                    magnet_to_xy(
-                        &mut self.ea_point.wpos,
-                        &self.center_point.wpos,
-                        magnet_distance,
-                    );
+                    if pos.norm() > 0. {
+                        let mut rotation = pos.wy.atan2(pos.wx) + PI / 2.;
+                        // Magnetized to the quarters of turn
+                        let quarter = (rotation / (PI / 2.)).round() * PI / 2.;
+                        if (rotation - quarter).abs() * pos.norm() < magnet_distance {
+                            rotation = quarter;
+                        }
+                        let radius = self.get_radius();
+                        self.set_geometry(rotation, &radius);
+                    }
                 }
                 _ => (),
             }
@@ -289,12 +361,14 @@ impl Shape for Ellipse {
             self.radius_point.selected,
             self.sa_point.selected,
             self.ea_point.selected,
+            self.axis_selected,
         ) = match point_type {
-            PointType::Center => (true, false, false, false),
-            PointType::Radius => (false, true, false, false),
-            PointType::StartAngle => (false, false, true, false),
-            PointType::EndAngle => (false, false, false, true),
-            _ => (false, false, false, false),
+            PointType::Center => (true, false, false, false, false),
+            PointType::Radius => (false, true, false, false, false),
+            PointType::StartAngle => (false, false, true, false, false),
+            PointType::EndAngle => (false, false, false, true, false),
+            PointType::Axis => (false, false, false, false, true),
+            _ => (false, false, false, false, false),
         }
     }
 
@@ -315,6 +389,55 @@ impl Shape for Ellipse {
             *pick_pos = self.position + ea_pos;
         }
     }
+    fn clone_box(&self) -> Box<dyn Shape> {
+        Box::new(self.clone())
+    }
+    // The image of an ellipse by an affine transformation is an ellipse. Its
+    // axes come from the singular value decomposition of the linear part,
+    // written rotate(angle1) * scale(rx, ry) * rotate(angle2).
+    fn transform(&mut self, transform: &Transform) -> Option<Vec<Box<dyn Shape>>> {
+        let radius = self.get_radius();
+        let linear = transform.get_linear();
+        let m = linear * Transform::rotate(self.rotation) * Transform::scale(radius.wx, radius.wy);
+        let (e, f) = ((m.a + m.d) / 2., (m.a - m.d) / 2.);
+        let (g, h) = ((m.b + m.c) / 2., (m.b - m.c) / 2.);
+        let (q, r) = ((e * e + h * h).sqrt(), (f * f + g * g).sqrt());
+        let (rx, ry) = (q + r, (q - r).abs());
+        if rx == 0. || ry == 0. {
+            return Some(vec![]);
+        }
+        let rotation = (h.atan2(e) + g.atan2(f)) / 2.;
+
+        // The ends of the arc are mapped, a mirroring reverses the way the
+        // arc goes
+        let start = self.get_pos_from_ratio(0.);
+        let end = self.get_pos_from_ratio(1.);
+        let (start, end) = if linear.get_determinant() < 0. {
+            (end, start)
+        } else {
+            (start, end)
+        };
+        self.position = transform.apply(&self.position);
+        self.saved_position = self.position;
+        self.center_point.wpos = linear.apply(&self.center_point.wpos);
+        self.rotation = rotation;
+        self.radius_point.wpos = self.center_point.wpos + self.get_turned(&WPos::new(rx, ry));
+        self.sa_point.wpos = transform.apply(&start) - self.position;
+        self.ea_point.wpos = transform.apply(&end) - self.position;
+        None
+    }
+    fn get_type_name(&self) -> &str {
+        "ellipse"
+    }
+    fn get_points(&self) -> Vec<(PointType, Point)> {
+        vec![
+            (PointType::Center, self.center_point),
+            (PointType::Radius, self.radius_point),
+            (PointType::StartAngle, self.sa_point),
+            (PointType::EndAngle, self.ea_point),
+            (PointType::Axis, self.get_axis_point()),
+        ]
+    }
 
     fn get_construction(&self) -> Vec<ConstructionType> {
         let mut cst: Vec<ConstructionType> = vec![];
@@ -327,26 +450,11 @@ impl Shape for Ellipse {
         let start_angle = self.angle_on_ellipse(&self.sa_point.wpos);
         let end_angle = self.angle_on_ellipse(&self.ea_point.wpos);
 
-        let mut sa_point = self.sa_point;
-
-        // sa_point.wpos = match (
-        //     self.radius_point.wpos.wx < 0.,
-        //     self.radius_point.wpos.wy < 0.,
-        // ) {
-        //     (false, false) => sa_point.wpos,
-        //     (false, true) => WPos::new(sa_point.wpos.wx, -sa_point.wpos.wy),
-        //     (true, false) => WPos::new(-sa_point.wpos.wx, sa_point.wpos.wy),
-        //     (true, true) => WPos::new(-sa_point.wpos.wx, -sa_point.wpos.wy),
-        // };
-
-        cst.push(ConstructionType::Move(self.position + sa_point.wpos));
+        cst.push(ConstructionType::Move(self.position + self.sa_point.wpos));
         cst.push(ConstructionType::Ellipse(
             self.position + self.center_point.wpos,
-            WPos::new(
-                self.radius_point.wpos.wx.abs(),
-                self.radius_point.wpos.wy.abs(),
-            ),
-            0.,
+            self.get_radius(),
+            self.rotation,
             start_angle,
             end_angle,
             false,
@@ -355,44 +463,20 @@ impl Shape for Ellipse {
     }
     fn get_handles_construction(&self, size_handle: f64) -> Vec<ConstructionType> {
         let mut cst = Vec::new();
-
-        let mut center_point = self.center_point;
-        center_point.wpos += self.position;
-
-        let mut radius_point = self.radius_point;
-        radius_point.wpos += self.position;
-
-        let mut sa_point = self.sa_point;
-        let mut ea_point = self.ea_point;
-        (sa_point.wpos, ea_point.wpos) = (sa_point.wpos, ea_point.wpos);
-
-        // (sa_point.wpos, ea_point.wpos) = match (
-        //     self.radius_point.wpos.wx < 0.,
-        //     self.radius_point.wpos.wy < 0.,
-        // ) {
-        //     (false, false) => (sa_point.wpos, ea_point.wpos),
-        //     (false, true) => (
-        //         WPos::new(sa_point.wpos.wx, -sa_point.wpos.wy),
-        //         WPos::new(ea_point.wpos.wx, -ea_point.wpos.wy),
-        //     ),
-        //     (true, false) => (
-        //         WPos::new(-sa_point.wpos.wx, sa_point.wpos.wy),
-        //         WPos::new(-ea_point.wpos.wx, ea_point.wpos.wy),
-        //     ),
-        //     (true, true) => (
-        //         WPos::new(-sa_point.wpos.wx, -sa_point.wpos.wy),
-        //         WPos::new(-ea_point.wpos.wx, -ea_point.wpos.wy),
-        //     ),
-        // };
-        sa_point.wpos += self.position;
-        ea_point.wpos += self.position;
-
-        push_handle(&mut cst, &center_point, size_handle);
-        push_handle(&mut cst, &radius_point, size_handle);
-        push_handle(&mut cst, &sa_point, size_handle);
-        push_handle(&mut cst, &ea_point, size_handle);
+        for mut point in [
+            self.center_point,
+            self.radius_point,
+            self.sa_point,
+            self.ea_point,
+            self.get_axis_point(),
+        ] {
+            point.wpos += self.position;
+            push_handle(&mut cst, &point, size_handle);
+        }
         cst
     }
+    // Turned, the axes of the ellipse are shown, else the alignments of the
+    // points with the center
     fn get_helpers_construction(&self) -> Vec<ConstructionType> {
         let mut cst: Vec<ConstructionType> = vec![];
         let position = self.position;
@@ -401,6 +485,14 @@ impl Shape for Ellipse {
         let sa = self.sa_point.wpos;
         let ea = self.ea_point.wpos;
         cst.push(ConstructionType::Layer(LayerType::GeometryHelpers));
+        if self.rotation.rem_euclid(2. * PI) != 0. {
+            for angle in [0., PI / 2.] {
+                let axis = self.get_point_from_angle(angle);
+                cst.push(ConstructionType::Move(position + center - axis));
+                cst.push(ConstructionType::Line(position + center + axis));
+            }
+            return cst;
+        }
         if is_aligned_45_or_135(&center, &radius) {
             helper_45_135(&(position + center), &(position + radius), true, &mut cst);
             helper_45_135(
@@ -430,11 +522,111 @@ impl Shape for Ellipse {
         }
         cst
     }
+    // Box of the whole ellipse, turned
     fn get_bounded_rectangle(&self) -> [WPos; 2] {
+        let radius = self.get_radius();
+        let (sin, cos) = self.rotation.sin_cos();
+        let half_size = WPos::new(
+            ((radius.wx * cos).powi(2) + (radius.wy * sin).powi(2)).sqrt(),
+            ((radius.wx * sin).powi(2) + (radius.wy * cos).powi(2)).sqrt(),
+        );
         [
-            self.position + self.center_point.wpos - self.radius_point.wpos,
-            self.position + self.center_point.wpos + self.radius_point.wpos,
+            self.position + self.center_point.wpos - half_size,
+            self.position + self.center_point.wpos + half_size,
         ]
     }
 }
 // impl ShapePool for Ellipse {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dist_and_projection() {
+        // Upper half of an ellipse, y being downward on screen
+        let arc = Ellipse::new(&WPos::new(10., 10.), &WPos::new(30., 20.), 0., PI, 1.);
+        assert!(arc.get_pos_from_ratio(0.).dist(&WPos::new(30., 10.)) < 1e-9);
+        assert!(arc.get_pos_from_ratio(0.5).dist(&WPos::new(10., 20.)) < 1e-9);
+        assert!((arc.dist(&WPos::new(10., 25.)) - 5.).abs() < 1e-6);
+        assert!((arc.get_ratio_from_pos(&WPos::new(10., 25.)) - 0.5).abs() < 1e-6);
+        // Outside of the arc the closest point is an end
+        assert!(
+            arc.get_projected_pos(&WPos::new(35., 5.))
+                .dist(&WPos::new(30., 10.))
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let circle = Ellipse::new(&WPos::new(0., 0.), &WPos::new(10., 10.), 0., 0., 1.);
+        let pos = WPos::new(0., -10.);
+        let (first, second) = circle.split(&pos);
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert!(first.get_pos_from_ratio(0.).dist(&WPos::new(10., 0.)) < 1e-6);
+        assert!(first.get_pos_from_ratio(1.).dist(&pos) < 1e-6);
+        assert!(second.get_pos_from_ratio(0.).dist(&pos) < 1e-6);
+        assert!(second.get_pos_from_ratio(1.).dist(&WPos::new(10., 0.)) < 1e-6);
+        // Three quarters then one quarter of the circle
+        assert!((first.get_step_r(1.) * 15. * PI - 1.).abs() < 1e-3);
+        assert!((second.get_step_r(1.) * 5. * PI - 1.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_transform() {
+        // Upper half of an ellipse, stretched along x then mirrored about the
+        // x axis: the mirroring reverses the way the arc goes
+        let mut arc = Ellipse::new(&WPos::new(10., 10.), &WPos::new(30., 20.), 0., PI, 1.);
+        let transform = Transform::scale(1., -1.) * Transform::scale(2., 1.);
+        assert!(arc.transform(&transform).is_none());
+        assert!(arc.get_pos_from_ratio(0.).dist(&WPos::new(-20., -10.)) < 1e-9);
+        assert!(arc.get_pos_from_ratio(0.5).dist(&WPos::new(20., -20.)) < 1e-9);
+        assert!(arc.get_pos_from_ratio(1.).dist(&WPos::new(60., -10.)) < 1e-9);
+
+        // Turned by an eighth of turn, the ellipse stays one
+        let turn = Transform::rotate(PI / 4.);
+        assert!(arc.transform(&turn).is_none());
+        for (r, pos) in [(0., (-20., -10.)), (0.5, (20., -20.)), (1., (60., -10.))] {
+            let pos = turn.apply(&WPos::new(pos.0, pos.1));
+            assert!(arc.get_pos_from_ratio(r).dist(&pos) < 1e-9);
+        }
+        let [min, max] = arc.get_bounded_rectangle();
+        let half_size = (40_f64.powi(2) / 2. + 10_f64.powi(2) / 2.).sqrt();
+        let center = turn.apply(&WPos::new(20., -10.));
+        assert!(min.dist(&(center - WPos::new(half_size, half_size))) < 1e-9);
+        assert!(max.dist(&(center + WPos::new(half_size, half_size))) < 1e-9);
+
+        // While a circle stays one
+        let mut circle = Ellipse::new(&WPos::new(0., 0.), &WPos::new(10., 10.), 0., 0., 1.);
+        assert!(circle.transform(&Transform::rotate(PI / 4.)).is_none());
+        assert!((circle.dist(&WPos::new(0., 10.))).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rotation() {
+        // Radii 20 and 10 turned by a quarter: the first axis goes down
+        let mut ellipse =
+            Ellipse::from_arc(&WPos::new(0., 0.), &WPos::new(20., 10.), PI / 2., 0., 0.).unwrap();
+        assert!(ellipse.get_pos_from_ratio(0.).dist(&WPos::new(0., 20.)) < 1e-9);
+        assert!(ellipse.dist(&WPos::new(10., 0.)) < 1e-6);
+        let [min, max] = ellipse.get_bounded_rectangle();
+        assert!(min.dist(&WPos::new(-10., -20.)) < 1e-9);
+        assert!(max.dist(&WPos::new(10., 20.)) < 1e-9);
+
+        // The rotation is read back from the axis point
+        let copy = Ellipse::from_points(&ellipse.get_pos(), &ellipse.get_points()).unwrap();
+        assert!((copy.rotation - PI / 2.).abs() < 1e-9);
+        assert!(copy.get_radius().dist(&WPos::new(20., 10.)) < 1e-9);
+
+        // Dragging the axis handle turns the ellipse back, keeping its radii
+        let axis = ellipse.get_axis_point().wpos;
+        assert!(axis.dist(&WPos::new(10., 0.)) < 1e-9);
+        ellipse.set_selected(true);
+        ellipse.init_done();
+        ellipse.select_point_type(&PointType::Axis);
+        ellipse.move_selection(&WPos::new(0., -10.), &axis, 0.);
+        assert!(ellipse.rotation.abs() < 1e-9);
+        assert!(ellipse.dist(&WPos::new(20., 0.)) < 1e-6);
+    }
+}
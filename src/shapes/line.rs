@@ -40,6 +40,22 @@ impl Line {
             init: true,
         })
     }
+    pub fn from_points(position: &WPos, points: &[(PointType, Point)]) -> Option<Line> {
+        let point = |point_type: PointType| {
+            points
+                .iter()
+                .find(|(pt, _)| *pt == point_type)
+                .map(|(_, point)| *point)
+        };
+        Some(Line {
+            start_point: point(PointType::Start)?,
+            end_point: point(PointType::End)?,
+            position: *position,
+            saved_position: *position,
+            selected: false,
+            init: false,
+        })
+    }
 }
 
 impl Shape for Line {
@@ -53,12 +69,7 @@ impl Shape for Line {
         self.position
     }
     fn get_step_r(&self, step: f64) -> f64 {
-        let dist = self.start_point.wpos.dist(&self.end_point.wpos);
-        if dist > 0. {
-            step / dist
-        } else {
-            step
-        }
+        get_step_ratio(step, self.start_point.wpos.dist(&self.end_point.wpos))
     }
     fn get_pos_from_ratio(&self, r: f64) -> WPos {
         let s = self.start_point.wpos;
@@ -189,6 +200,27 @@ impl Shape for Line {
             *pick_pos = self.position + end_pos;
         }
     }
+    fn clone_box(&self) -> Box<dyn Shape> {
+        Box::new(self.clone())
+    }
+    fn transform(&mut self, transform: &Transform) -> Option<Vec<Box<dyn Shape>>> {
+        let linear = transform.get_linear();
+        self.position = transform.apply(&self.position);
+        self.saved_position = self.position;
+        for point in [&mut self.start_point, &mut self.end_point] {
+            point.wpos = linear.apply(&point.wpos);
+        }
+        None
+    }
+    fn get_type_name(&self) -> &str {
+        "line"
+    }
+    fn get_points(&self) -> Vec<(PointType, Point)> {
+        vec![
+            (PointType::Start, self.start_point),
+            (PointType::End, self.end_point),
+        ]
+    }
 
     fn get_construction(&self) -> Vec<ConstructionType> {
         let mut cst: Vec<ConstructionType> = vec![];
@@ -0,0 +1,348 @@
+use super::types::{ConstructionType, LayerType, Point, PointType, Shape, WPos};
+use crate::math::*;
+
+// Open chain of segments, it is what remains of a closed shape made of lines
+// once it has been cut
+#[derive(Clone)]
+pub struct Polyline {
+    points: Vec<Point>,
+    position: WPos,
+    saved_position: WPos,
+    selected: bool,
+    init: bool,
+}
+impl Polyline {
+    pub fn new(pts: &[WPos]) -> Option<Polyline> {
+        // Repeated vertices are dropped
+        let mut vertices: Vec<WPos> = vec![];
+        for pos in pts.iter() {
+            if vertices.last().map_or(true, |last| last.dist(pos) > 0.) {
+                vertices.push(*pos);
+            }
+        }
+        if vertices.len() < 2 {
+            return None;
+        }
+
+        let position = vertices[0];
+        let points = vertices
+            .iter()
+            .map(|pos| Point::new(&(*pos - position), false, false, false))
+            .collect();
+
+        Some(Polyline {
+            points,
+            position,
+            saved_position: position,
+            selected: false,
+            init: true,
+        })
+    }
+    pub fn from_points(position: &WPos, points: &[(PointType, Point)]) -> Option<Polyline> {
+        let mut vertices: Vec<(usize, Point)> = points
+            .iter()
+            .filter_map(|(point_type, point)| match point_type {
+                PointType::Vertex(idx) => Some((*idx, *point)),
+                _ => None,
+            })
+            .collect();
+        if vertices.len() < 2 {
+            return None;
+        }
+        vertices.sort_by_key(|(idx, _)| *idx);
+        Some(Polyline {
+            points: vertices.into_iter().map(|(_, point)| point).collect(),
+            position: *position,
+            saved_position: *position,
+            selected: false,
+            init: false,
+        })
+    }
+    fn get_vertices(&self) -> Vec<WPos> {
+        self.points
+            .iter()
+            .map(|point| point.wpos + self.position)
+            .collect()
+    }
+}
+
+impl Shape for Polyline {
+    fn is_init(&self) -> bool {
+        self.init
+    }
+    fn init_done(&mut self) {
+        self.init = false;
+    }
+    fn get_pos(&self) -> WPos {
+        self.position
+    }
+    fn get_step_r(&self, step: f64) -> f64 {
+        get_step_ratio(step, get_polyline_length(&self.get_vertices()))
+    }
+    fn get_pos_from_ratio(&self, r: f64) -> WPos {
+        get_polyline_pos_from_ratio(&self.get_vertices(), r)
+    }
+    fn get_ratio_from_pos(&self, pos: &WPos) -> f64 {
+        get_polyline_ratio_from_pos(&self.get_vertices(), pos)
+    }
+    fn get_projected_pos(&self, pick_pos: &WPos) -> WPos {
+        self.get_pos_from_ratio(self.get_ratio_from_pos(pick_pos))
+    }
+    fn split(&self, pos: &WPos) -> (Option<Box<dyn Shape>>, Option<Box<dyn Shape>>) {
+        let r = self.get_ratio_from_pos(pos);
+        let (first, second) = split_polyline(&self.get_vertices(), r);
+        (
+            if let Some(mut polyline1) = Polyline::new(&first) {
+                polyline1.init_done();
+                Some(Box::new(polyline1))
+            } else {
+                None
+            },
+            if let Some(mut polyline2) = Polyline::new(&second) {
+                polyline2.init_done();
+                Some(Box::new(polyline2))
+            } else {
+                None
+            },
+        )
+    }
+    fn is_closed(&self) -> bool {
+        // Its last vertex is back on the first one
+        self.points.len() > 3
+            && self.points[0]
+                .wpos
+                .dist(&self.points[self.points.len() - 1].wpos)
+                < 1e-9
+    }
+    fn split_closed(&self, start: &WPos, end: &WPos) -> Option<Box<dyn Shape>> {
+        if !self.is_closed() {
+            return None;
+        }
+        let vertices = self.get_vertices();
+        let piece = get_closed_polyline_piece(
+            &vertices,
+            get_polyline_ratio_from_pos(&vertices, start),
+            get_polyline_ratio_from_pos(&vertices, end),
+        );
+        Polyline::new(&piece).map(|mut polyline| {
+            polyline.init_done();
+            Box::new(polyline) as Box<dyn Shape>
+        })
+    }
+
+    fn dist(&self, pick_pos: &WPos) -> f64 {
+        pick_pos.dist(&self.get_projected_pos(pick_pos))
+    }
+
+    fn get_shape_point_type_under_pick_pos(
+        &mut self,
+        pick_pos: &WPos,
+        grab_handle_precision: f64,
+    ) -> Option<PointType> {
+        // The first point found is returned
+        let pick_pos = *pick_pos - self.position;
+        self.points
+            .iter()
+            .position(|point| pick_pos.dist(&point.wpos) < grab_handle_precision)
+            .map(PointType::Vertex)
+    }
+
+    fn clear_selection(&mut self) {
+        self.selected = false
+    }
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+    fn deselect_all_points(&mut self) {
+        for point in self.points.iter_mut() {
+            point.selected = false;
+        }
+    }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+    fn move_selection(&mut self, pick_pos: &WPos, pick_pos_ms_dwn: &WPos, _magnet_distance: f64) {
+        let pick_pos = *pick_pos;
+        let pick_pos_ms_dwn = *pick_pos_ms_dwn;
+
+        if self.selected {
+            if let Some(idx) = self.points.iter().position(|point| point.selected) {
+                self.points[idx].wpos = pick_pos - self.position;
+            } else {
+                self.position = self.saved_position + pick_pos - pick_pos_ms_dwn;
+            }
+        }
+    }
+    fn select_point_type(&mut self, point_type: &PointType) {
+        for (idx, point) in self.points.iter_mut().enumerate() {
+            point.selected = *point_type == PointType::Vertex(idx);
+        }
+    }
+
+    fn save_current_position(&mut self) {
+        self.saved_position = self.position;
+    }
+    fn get_saved_position(&self) -> WPos {
+        self.saved_position
+    }
+    fn magnet_to_point(&self, pick_pos: &mut WPos, magnet_distance: f64) {
+        for pos in self.get_vertices().iter() {
+            if pick_pos.dist(pos) < magnet_distance {
+                *pick_pos = *pos;
+            }
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Shape> {
+        Box::new(self.clone())
+    }
+    fn transform(&mut self, transform: &Transform) -> Option<Vec<Box<dyn Shape>>> {
+        let linear = transform.get_linear();
+        self.position = transform.apply(&self.position);
+        self.saved_position = self.position;
+        for point in self.points.iter_mut() {
+            point.wpos = linear.apply(&point.wpos);
+        }
+        None
+    }
+    fn get_type_name(&self) -> &str {
+        "polyline"
+    }
+    fn get_points(&self) -> Vec<(PointType, Point)> {
+        self.points
+            .iter()
+            .enumerate()
+            .map(|(idx, point)| (PointType::Vertex(idx), *point))
+            .collect()
+    }
+
+    fn get_construction(&self) -> Vec<ConstructionType> {
+        let mut cst: Vec<ConstructionType> = vec![];
+        if !self.selected {
+            cst.push(ConstructionType::Layer(LayerType::Worksheet));
+        } else {
+            cst.push(ConstructionType::Layer(LayerType::Selected));
+        }
+        let vertices = self.get_vertices();
+        cst.push(ConstructionType::Move(vertices[0]));
+        for pos in vertices.iter().skip(1) {
+            cst.push(ConstructionType::Line(*pos));
+        }
+        cst
+    }
+    fn get_handles_construction(&self, size_handle: f64) -> Vec<ConstructionType> {
+        let mut cst = Vec::new();
+        for point in self.points.iter() {
+            let mut point = *point;
+            point.wpos += self.position;
+            push_handle(&mut cst, &point, size_handle);
+        }
+        cst
+    }
+    fn get_helpers_construction(&self) -> Vec<ConstructionType> {
+        let mut cst: Vec<ConstructionType> = vec![];
+        cst.push(ConstructionType::Layer(LayerType::GeometryHelpers));
+        for seg in self.get_vertices().windows(2) {
+            if is_aligned_vert(&seg[0], &seg[1]) {
+                helper_vertical(&seg[0], &seg[1], true, &mut cst);
+            }
+            if is_aligned_hori(&seg[0], &seg[1]) {
+                helper_horizontal(&seg[0], &seg[1], true, &mut cst);
+            }
+            if is_aligned_45_or_135(&seg[0], &seg[1]) {
+                helper_45_135(&seg[0], &seg[1], true, &mut cst);
+            }
+        }
+        cst
+    }
+    fn get_bounded_rectangle(&self) -> [WPos; 2] {
+        let vertices = self.get_vertices();
+        let mut bb = [vertices[0], vertices[0]];
+        for pos in vertices.iter() {
+            bb[0] = WPos::new(bb[0].wx.min(pos.wx), bb[0].wy.min(pos.wy));
+            bb[1] = WPos::new(bb[1].wx.max(pos.wx), bb[1].wy.max(pos.wy));
+        }
+        bb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_l_polyline() -> Polyline {
+        // Length of 30, the corner at the ratio 1/3
+        Polyline::new(&[WPos::new(0., 0.), WPos::new(10., 0.), WPos::new(10., 20.)]).unwrap()
+    }
+
+    #[test]
+    fn test_new() {
+        // Repeated vertices are dropped, a single vertex is not a polyline
+        let polyline =
+            Polyline::new(&[WPos::new(0., 0.), WPos::new(0., 0.), WPos::new(10., 0.)]).unwrap();
+        assert_eq!(polyline.points.len(), 2);
+        assert!(Polyline::new(&[WPos::new(1., 1.), WPos::new(1., 1.)]).is_none());
+    }
+
+    #[test]
+    fn test_dist_and_projection() {
+        let polyline = get_l_polyline();
+        assert!(polyline.get_pos_from_ratio(0.).dist(&WPos::new(0., 0.)) < 1e-9);
+        assert!(polyline.get_pos_from_ratio(0.5).dist(&WPos::new(10., 5.)) < 1e-9);
+        assert!(polyline.get_pos_from_ratio(1.).dist(&WPos::new(10., 20.)) < 1e-9);
+        assert!((polyline.get_ratio_from_pos(&WPos::new(5., -3.)) - 1. / 6.).abs() < 1e-9);
+        assert!(
+            polyline
+                .get_projected_pos(&WPos::new(14., 10.))
+                .dist(&WPos::new(10., 10.))
+                < 1e-9
+        );
+        assert!((polyline.dist(&WPos::new(14., 10.)) - 4.).abs() < 1e-9);
+        assert!((polyline.get_step_r(3.) - 0.1).abs() < 1e-9);
+        assert!(!polyline.is_closed());
+        assert!(polyline
+            .split_closed(&WPos::new(5., 0.), &WPos::new(10., 5.))
+            .is_none());
+    }
+
+    #[test]
+    fn test_split() {
+        let polyline = get_l_polyline();
+        let pos = WPos::new(10., 5.);
+        let (first, second) = polyline.split(&pos);
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert!(first.get_pos_from_ratio(0.).dist(&WPos::new(0., 0.)) < 1e-9);
+        assert!(first.get_pos_from_ratio(1.).dist(&pos) < 1e-9);
+        assert!(first.dist(&WPos::new(10., 0.)) < 1e-9);
+        assert!(second.get_pos_from_ratio(0.).dist(&pos) < 1e-9);
+        assert!(second.get_pos_from_ratio(1.).dist(&WPos::new(10., 20.)) < 1e-9);
+        assert!((second.get_step_r(1.) * 15. - 1.).abs() < 1e-9);
+
+        // Nothing remains on the side of an end
+        let (first, second) = polyline.split(&WPos::new(0., 0.));
+        assert!(first.is_none());
+        assert!(second.is_some());
+    }
+
+    #[test]
+    fn test_split_closed() {
+        let square = Polyline::new(&[
+            WPos::new(0., 0.),
+            WPos::new(10., 0.),
+            WPos::new(10., 10.),
+            WPos::new(0., 10.),
+            WPos::new(0., 0.),
+        ])
+        .unwrap();
+        assert!(square.is_closed());
+        // From the right side round to the top one, across the first vertex
+        let piece = square
+            .split_closed(&WPos::new(10., 5.), &WPos::new(5., 0.))
+            .unwrap();
+        assert!(piece.get_pos_from_ratio(0.).dist(&WPos::new(10., 5.)) < 1e-9);
+        assert!(piece.get_pos_from_ratio(1.).dist(&WPos::new(5., 0.)) < 1e-9);
+        assert!(piece.dist(&WPos::new(0., 10.)) < 1e-9);
+        assert!(piece.dist(&WPos::new(0., 0.)) < 1e-9);
+        assert!(piece.dist(&WPos::new(10., 0.)) > 1.);
+    }
+}
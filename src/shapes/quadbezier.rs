@@ -36,6 +36,23 @@ impl QuadBezier {
             init: true,
         })
     }
+    pub fn from_points(position: &WPos, points: &[(PointType, Point)]) -> Option<QuadBezier> {
+        let point = |point_type: PointType| {
+            points
+                .iter()
+                .find(|(pt, _)| *pt == point_type)
+                .map(|(_, point)| *point)
+        };
+        Some(QuadBezier {
+            start_point: point(PointType::Start)?,
+            ctrl_point: point(PointType::Ctrl)?,
+            end_point: point(PointType::End)?,
+            position: *position,
+            saved_position: *position,
+            selected: false,
+            init: false,
+        })
+    }
     fn get_point_on_quad_bezier(&self, t: f64) -> WPos {
         let u = 1.0 - t;
         let tt = t * t;
@@ -78,6 +95,11 @@ impl QuadBezier {
         }
         min_dist <= precision
     }
+    // Parameter of the point of the curve closest to pos (relative to the
+    // shape position)
+    fn get_closest_t(&self, pos: &WPos) -> f64 {
+        get_closest_ratio(|t| self.get_point_on_quad_bezier(t), pos, 64)
+    }
 }
 
 impl Shape for QuadBezier {
@@ -90,8 +112,8 @@ impl Shape for QuadBezier {
     fn get_pos(&self) -> WPos {
         self.position
     }
-    fn get_step_r(&self, grab_handle_precision: f64) -> f64 {
-        0.
+    fn get_step_r(&self, step: f64) -> f64 {
+        get_step_ratio(step, get_sampled_length(|r| self.get_pos_from_ratio(r), 32))
     }
 
     fn get_pos_from_ratio(&self, r: f64) -> WPos {
@@ -101,39 +123,43 @@ impl Shape for QuadBezier {
         let r1 = 1.0 - r;
         let wx = r1.powi(2) * s.wx + 2.0 * r1 * r * c.wx + r.powi(2) * e.wx;
         let wy = r1.powi(2) * s.wy + 2.0 * r1 * r * c.wy + r.powi(2) * e.wy;
-        WPos { wx, wy }
+        WPos { wx, wy } + self.position
     }
 
-    fn get_ratio_from_pos(&self, rpos: &WPos) -> f64 {
-        // TODO
-        0.
+    fn get_ratio_from_pos(&self, pos: &WPos) -> f64 {
+        self.get_closest_t(&(*pos - self.position))
     }
     fn get_projected_pos(&self, pick_pos: &WPos) -> WPos {
-        // TODO
-        WPos::zero()
+        self.get_pos_from_ratio(self.get_ratio_from_pos(pick_pos))
     }
     fn split(&self, pos: &WPos) -> (Option<Box<dyn Shape>>, Option<Box<dyn Shape>>) {
-        // TODO
-        (None, None)
+        // De Casteljau subdivision at the parameter of the point
+        let t = self.get_ratio_from_pos(pos);
+        let start = self.start_point.wpos + self.position;
+        let ctrl = self.ctrl_point.wpos + self.position;
+        let end = self.end_point.wpos + self.position;
+
+        let p01 = start.lerp(&ctrl, t);
+        let p12 = ctrl.lerp(&end, t);
+        let split_pos = p01.lerp(&p12, t);
+        (
+            if let Some(mut bezier1) = QuadBezier::new(&start, &p01, &split_pos) {
+                bezier1.init_done();
+                Some(Box::new(bezier1))
+            } else {
+                None
+            },
+            if let Some(mut bezier2) = QuadBezier::new(&split_pos, &p12, &end) {
+                bezier2.init_done();
+                Some(Box::new(bezier2))
+            } else {
+                None
+            },
+        )
     }
 
     fn dist(&self, pick_pos: &WPos) -> f64 {
-        // TODO
-        0.
-
-        // TODO
-        // let steps = 100; // Increase for better precision
-        // let mut min_distance = f64::MAX;
-
-        // for i in 0..steps {
-        //     let r1 = i as f64 / steps as f64;
-        //     let r2 = (i + 1) as f64 / steps as f64;
-        //     let start_pos = self.get_pos_from_ratio(r1);
-        //     let end_pos = self.get_pos_from_ratio(r2);
-        //     let distance = pos.sign_dist_to_seg(&start_pos, &end_pos);
-        //     min_distance = min_distance.min(distance);
-        // }
-        // min_distance
+        pick_pos.dist(&self.get_projected_pos(pick_pos))
     }
 
     // fn is_shape_under_pick_pos(&self, pick_pos: &WPos, grab_handle_precision: f64) -> bool {
@@ -246,6 +272,32 @@ impl Shape for QuadBezier {
             *pick_pos = self.position + end_pos;
         }
     }
+    fn clone_box(&self) -> Box<dyn Shape> {
+        Box::new(self.clone())
+    }
+    fn transform(&mut self, transform: &Transform) -> Option<Vec<Box<dyn Shape>>> {
+        let linear = transform.get_linear();
+        self.position = transform.apply(&self.position);
+        self.saved_position = self.position;
+        for point in [
+            &mut self.start_point,
+            &mut self.ctrl_point,
+            &mut self.end_point,
+        ] {
+            point.wpos = linear.apply(&point.wpos);
+        }
+        None
+    }
+    fn get_type_name(&self) -> &str {
+        "quadbezier"
+    }
+    fn get_points(&self) -> Vec<(PointType, Point)> {
+        vec![
+            (PointType::Start, self.start_point),
+            (PointType::Ctrl, self.ctrl_point),
+            (PointType::End, self.end_point),
+        ]
+    }
 
     fn get_construction(&self) -> Vec<ConstructionType> {
         let mut cst: Vec<ConstructionType> = vec![];
@@ -368,3 +420,47 @@ impl Shape for QuadBezier {
     }
 }
 // impl ShapePool for QuadBezier {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_arch() -> QuadBezier {
+        QuadBezier::new(&WPos::new(0., 0.), &WPos::new(5., 10.), &WPos::new(10., 0.)).unwrap()
+    }
+
+    #[test]
+    fn test_dist_and_projection() {
+        let bezier = get_arch();
+        // The top of the arch is at t = 0.5
+        let top = bezier.get_pos_from_ratio(0.5);
+        assert!(top.dist(&WPos::new(5., 5.)) < 1e-9);
+        assert!(bezier.dist(&top) < 1e-6);
+        assert!((bezier.dist(&WPos::new(5., 10.)) - 5.).abs() < 1e-6);
+        assert!(bezier.get_projected_pos(&WPos::new(5., 10.)).dist(&top) < 1e-6);
+        assert!((bezier.get_ratio_from_pos(&WPos::new(5., 10.)) - 0.5).abs() < 1e-6);
+        // Beyond the ends the closest point is an end
+        assert!(
+            bezier
+                .get_projected_pos(&WPos::new(-5., -5.))
+                .dist(&WPos::new(0., 0.))
+                < 1e-6
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        let bezier = get_arch();
+        let pos = bezier.get_pos_from_ratio(0.25);
+        let (first, second) = bezier.split(&pos);
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert!(first.get_pos_from_ratio(1.).dist(&pos) < 1e-6);
+        assert!(second.get_pos_from_ratio(0.).dist(&pos) < 1e-6);
+        // Both parts follow the original curve
+        for i in 0..=10 {
+            let r = i as f64 / 10.;
+            assert!(bezier.dist(&first.get_pos_from_ratio(r)) < 1e-6);
+            assert!(bezier.dist(&second.get_pos_from_ratio(r)) < 1e-6);
+        }
+    }
+}
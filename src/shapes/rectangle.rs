@@ -1,3 +1,4 @@
+use super::polyline::Polyline;
 use super::types::{ConstructionType, LayerType, Point, PointType, Shape, WPos};
 use crate::math::*;
 
@@ -36,6 +37,34 @@ impl Rectangle {
             init: true,
         })
     }
+    pub fn from_points(position: &WPos, points: &[(PointType, Point)]) -> Option<Rectangle> {
+        let point = |point_type: PointType| {
+            points
+                .iter()
+                .find(|(pt, _)| *pt == point_type)
+                .map(|(_, point)| *point)
+        };
+        Some(Rectangle {
+            bl_pt: point(PointType::BL)?,
+            tl_pt: point(PointType::TL)?,
+            tr_pt: point(PointType::TR)?,
+            br_pt: point(PointType::BR)?,
+            position: *position,
+            saved_position: *position,
+            selected: false,
+            init: false,
+        })
+    }
+    // The perimeter is followed like it is drawn, from the bottom left point
+    fn get_corners(&self) -> [WPos; 5] {
+        [
+            self.bl_pt.wpos + self.position,
+            self.tl_pt.wpos + self.position,
+            self.tr_pt.wpos + self.position,
+            self.br_pt.wpos + self.position,
+            self.bl_pt.wpos + self.position,
+        ]
+    }
     pub fn is_point_on_rectangle(&self, pos: &WPos, precision: f64) -> bool {
         pos.sign_dist_to_seg(&self.bl_pt.wpos, &self.tl_pt.wpos) < precision
             || pos.sign_dist_to_seg(&self.tl_pt.wpos, &self.tr_pt.wpos) < precision
@@ -88,31 +117,57 @@ impl Shape for Rectangle {
     fn get_pos(&self) -> WPos {
         self.position
     }
-    fn get_step_r(&self, grab_handle_precision: f64) -> f64 {
-        //
-        0.
+    fn get_step_r(&self, step: f64) -> f64 {
+        get_step_ratio(step, get_polyline_length(&self.get_corners()))
     }
 
     fn get_pos_from_ratio(&self, r: f64) -> WPos {
-        // TODO
-        WPos::zero()
+        get_polyline_pos_from_ratio(&self.get_corners(), r)
     }
-    fn get_ratio_from_pos(&self, rpos: &WPos) -> f64 {
-        // TODO
-        0.
+    fn get_ratio_from_pos(&self, pos: &WPos) -> f64 {
+        get_polyline_ratio_from_pos(&self.get_corners(), pos)
     }
     fn get_projected_pos(&self, pick_pos: &WPos) -> WPos {
-        // TODO
-        WPos::zero()
+        self.get_pos_from_ratio(self.get_ratio_from_pos(pick_pos))
     }
     fn split(&self, pos: &WPos) -> (Option<Box<dyn Shape>>, Option<Box<dyn Shape>>) {
-        // TODO
-        (None, None)
+        // Once opened, the rectangle becomes a polyline, from its bottom left
+        // corner to pos then from pos back to the bottom left corner
+        let r = self.get_ratio_from_pos(pos);
+        let (first, second) = split_polyline(&self.get_corners(), r);
+        (
+            if let Some(mut polyline1) = Polyline::new(&first) {
+                polyline1.init_done();
+                Some(Box::new(polyline1))
+            } else {
+                None
+            },
+            if let Some(mut polyline2) = Polyline::new(&second) {
+                polyline2.init_done();
+                Some(Box::new(polyline2))
+            } else {
+                None
+            },
+        )
+    }
+    fn is_closed(&self) -> bool {
+        true
+    }
+    fn split_closed(&self, start: &WPos, end: &WPos) -> Option<Box<dyn Shape>> {
+        let corners = self.get_corners();
+        let piece = get_closed_polyline_piece(
+            &corners,
+            get_polyline_ratio_from_pos(&corners, start),
+            get_polyline_ratio_from_pos(&corners, end),
+        );
+        Polyline::new(&piece).map(|mut polyline| {
+            polyline.init_done();
+            Box::new(polyline) as Box<dyn Shape>
+        })
     }
 
     fn dist(&self, pick_pos: &WPos) -> f64 {
-        // TODO
-        0.
+        pick_pos.dist(&self.get_projected_pos(pick_pos))
     }
     // fn is_shape_under_pick_pos(&self, pick_pos: &WPos, grab_handle_precision: f64) -> bool {
     //     let pick_pos = *pick_pos - self.position;
@@ -243,6 +298,47 @@ impl Shape for Rectangle {
             *pick_pos = self.position + br_pos;
         }
     }
+    fn clone_box(&self) -> Box<dyn Shape> {
+        Box::new(self.clone())
+    }
+    // A rectangle turned by other than a quarter of turn or skewed becomes a
+    // closed polyline
+    fn transform(&mut self, transform: &Transform) -> Option<Vec<Box<dyn Shape>>> {
+        let corners: Vec<WPos> = self
+            .get_corners()
+            .iter()
+            .map(|pos| transform.apply(pos))
+            .collect();
+        if !transform.keeps_axes() {
+            return Some(
+                Polyline::new(&corners)
+                    .into_iter()
+                    .map(|polyline| Box::new(polyline) as Box<dyn Shape>)
+                    .collect(),
+            );
+        }
+        self.position = transform.apply(&self.position);
+        self.saved_position = self.position;
+        let mut bb = [corners[0], corners[2]];
+        reorder_corners(&mut bb);
+        let [tl, br] = [bb[0] - self.position, bb[1] - self.position];
+        self.tl_pt.wpos = tl;
+        self.tr_pt.wpos = WPos::new(br.wx, tl.wy);
+        self.br_pt.wpos = br;
+        self.bl_pt.wpos = WPos::new(tl.wx, br.wy);
+        None
+    }
+    fn get_type_name(&self) -> &str {
+        "rectangle"
+    }
+    fn get_points(&self) -> Vec<(PointType, Point)> {
+        vec![
+            (PointType::BL, self.bl_pt),
+            (PointType::TL, self.tl_pt),
+            (PointType::TR, self.tr_pt),
+            (PointType::BR, self.br_pt),
+        ]
+    }
 
     fn get_construction(&self) -> Vec<ConstructionType> {
         let mut cst: Vec<ConstructionType> = vec![];
@@ -303,9 +399,64 @@ impl Shape for Rectangle {
     }
     fn get_bounded_rectangle(&self) -> [WPos; 2] {
         [
-            self.position + self.bl_pt.wpos,
             self.position + self.tl_pt.wpos,
+            self.position + self.br_pt.wpos,
         ]
     }
 }
 // impl ShapePool for Rectangle {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_perimeter_parametrization() {
+        let rectangle = Rectangle::new(&WPos::new(0., 0.), 30., 10.).unwrap();
+        // Perimeter of 80 starting from the bottom left corner (0, 10)
+        assert!(rectangle.get_pos_from_ratio(0.).dist(&WPos::new(0., 10.)) < 1e-9);
+        assert!(rectangle.get_pos_from_ratio(0.125).dist(&WPos::new(0., 0.)) < 1e-9);
+        assert!(rectangle.get_pos_from_ratio(0.5).dist(&WPos::new(30., 0.)) < 1e-9);
+        assert!((rectangle.get_ratio_from_pos(&WPos::new(15., -2.)) - 0.3125).abs() < 1e-9);
+        assert!((rectangle.dist(&WPos::new(15., -2.)) - 2.).abs() < 1e-9);
+        assert!((rectangle.dist(&WPos::new(15., 6.)) - 4.).abs() < 1e-9);
+        assert!((rectangle.get_step_r(8.) - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_split_into_polylines() {
+        let rectangle = Rectangle::new(&WPos::new(0., 0.), 30., 10.).unwrap();
+        let pos = WPos::new(15., 0.);
+        let (first, second) = rectangle.split(&pos);
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert!(first.get_pos_from_ratio(0.).dist(&WPos::new(0., 10.)) < 1e-9);
+        assert!(first.get_pos_from_ratio(1.).dist(&pos) < 1e-9);
+        assert!(second.get_pos_from_ratio(0.).dist(&pos) < 1e-9);
+        assert!(second.get_pos_from_ratio(1.).dist(&WPos::new(0., 10.)) < 1e-9);
+        // The first part goes through the top left corner, the second one
+        // through the three other corners
+        assert!(first.dist(&WPos::new(0., 0.)) < 1e-9);
+        assert!(second.dist(&WPos::new(30., 0.)) < 1e-9);
+        assert!(second.dist(&WPos::new(30., 10.)) < 1e-9);
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut rectangle = Rectangle::new(&WPos::new(0., 0.), 30., 10.).unwrap();
+        // A quarter of turn around the top left corner, then a mirroring
+        let transform = Transform::scale(-1., 1.) * Transform::rotate(PI / 2.);
+        assert!(rectangle.transform(&transform).is_none());
+        let bb = rectangle.get_bounded_rectangle();
+        assert!(bb[0].dist(&WPos::new(0., 0.)) < 1e-9);
+        assert!(bb[1].dist(&WPos::new(10., 30.)) < 1e-9);
+        assert!(rectangle.get_pos_from_ratio(0.).dist(&WPos::new(0., 30.)) < 1e-9);
+
+        // Turned by an eighth of turn, it is no more a rectangle
+        let shapes = rectangle.transform(&Transform::rotate(PI / 4.)).unwrap();
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(shapes[0].get_type_name(), "polyline");
+        let corner = Transform::rotate(PI / 4.).apply(&WPos::new(10., 30.));
+        assert!(shapes[0].dist(&corner) < 1e-9);
+    }
+}
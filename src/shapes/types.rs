@@ -5,6 +5,7 @@
 //     }
 // }
 
+use crate::math::Transform;
 use std::hash::{Hash, Hasher};
 use std::ops::Add;
 use std::ops::AddAssign;
@@ -32,6 +33,16 @@ pub trait Shape {
     // ratio of the point found
     fn get_projected_pos(&self, pick_pos: &WPos) -> WPos;
     fn split(&self, pos: &WPos) -> (Option<Box<dyn Shape>>, Option<Box<dyn Shape>>);
+    // A closed shape has no ends, splitting it at its start leaves both
+    // pieces of a cut on either side of it. It is rather opened between two
+    // of its points: the piece runs from start to end, across the start of
+    // the shape when needed.
+    fn is_closed(&self) -> bool {
+        false
+    }
+    fn split_closed(&self, _start: &WPos, _end: &WPos) -> Option<Box<dyn Shape>> {
+        None
+    }
 
     fn dist(&self, pick_pos: &WPos) -> f64;
 
@@ -54,6 +65,16 @@ pub trait Shape {
     fn get_helpers_construction(&self) -> Vec<ConstructionType>;
     fn get_bounded_rectangle(&self) -> [WPos; 2];
     fn magnet_to_point(&self, pick_pos: &mut WPos, magnet_distance: f64);
+    // Deep copy, selection included
+    fn clone_box(&self) -> Box<dyn Shape>;
+    // Maps the shape by an affine transformation. A shape which can't keep
+    // its kind returns the shapes replacing it.
+    fn transform(&mut self, transform: &Transform) -> Option<Vec<Box<dyn Shape>>>;
+
+    // Kind of the shape and its points, relative to its position, everything
+    // needed to build it back
+    fn get_type_name(&self) -> &str;
+    fn get_points(&self) -> Vec<(PointType, Point)>;
 }
 
 #[allow(dead_code)]
@@ -75,6 +96,12 @@ pub enum LayerType {
     Selected,
     Highlight,
     Handle(bool),
+    Lead,
+    Rapid,
+    // Closed or open contour
+    Contour(bool),
+    // Move of a G-code program, cutting or not
+    Backplot(bool),
 }
 
 #[allow(dead_code)]
@@ -85,6 +112,8 @@ pub enum ConstructionType {
     Line(WPos),
     QuadBezier(WPos, WPos),
     CubicBezier(WPos, WPos, WPos),
+    // Center, radius, rotation, start and end angles swept the way
+    // get_sweep_angle does, and whether it is filled
     Ellipse(WPos, WPos, f64, f64, f64, bool),
     Rectangle(WPos, WPos, bool),
     Text(WPos, String),
@@ -107,6 +136,8 @@ pub enum PointType {
     Ctrl,
     Ctrl1,
     Ctrl2,
+    Vertex(usize),
+    Axis,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -0,0 +1,219 @@
+use crate::datapool::{GroupsPool, ShapesPool};
+use crate::math::*;
+use crate::shapes::types::{ConstructionType, Shape, ShapeId, WPos};
+use std::collections::HashSet;
+use std::f64::consts::PI;
+use svg::node::element::path::Data;
+use svg::node::element::{Group, Path};
+use svg::Document;
+
+// Width of the strokes, in millimeters
+const STROKE_WIDTH: f64 = 0.2;
+
+// The working area is the view box, one user unit is one millimeter, the y
+// axis goes down like on the canvas
+pub fn get_svg_from_pools(
+    shapes_pool: &ShapesPool,
+    groups_pool: &GroupsPool,
+    working_area: &WPos,
+) -> String {
+    let mut document = Document::new()
+        .set("width", format!("{}mm", working_area.wx))
+        .set("height", format!("{}mm", working_area.wy))
+        .set("viewBox", (0., 0., working_area.wx, working_area.wy));
+
+    // Groups first, ordered by their first shape, then the shapes alone. A
+    // shape in several groups is written once, in its first group.
+    let mut written: HashSet<ShapeId> = HashSet::new();
+    let mut groups = groups_pool.get_sorted_groups(shapes_pool);
+    for sh_ids in groups.iter_mut() {
+        sh_ids.retain(|sh_id| written.insert(*sh_id));
+    }
+    for (idx, sh_ids) in groups.iter().enumerate() {
+        let mut group = Group::new().set("id", format!("group{}", idx + 1));
+        for sh_id in sh_ids.iter() {
+            if let Some(path) = get_svg_path(shapes_pool.get(sh_id).unwrap().as_ref()) {
+                group = group.add(path);
+            }
+        }
+        document = document.add(group);
+    }
+
+    let mut sh_ids: Vec<&ShapeId> = shapes_pool
+        .keys()
+        .filter(|sh_id| !written.contains(sh_id))
+        .collect();
+    sh_ids.sort_by_key(|sh_id| ***sh_id);
+    for sh_id in sh_ids {
+        if let Some(path) = get_svg_path(shapes_pool.get(sh_id).unwrap().as_ref()) {
+            document = document.add(path);
+        }
+    }
+
+    document.to_string()
+}
+
+fn get_svg_path(shape: &dyn Shape) -> Option<Path> {
+    let mut data = Data::new();
+    let mut nb_segments = 0;
+    let mut start = WPos::zero();
+    let mut pos = WPos::zero();
+    for cst in shape.get_construction().iter() {
+        match cst {
+            ConstructionType::Move(end) => {
+                data = data.move_to((end.wx, end.wy));
+                start = *end;
+                pos = *end;
+            }
+            ConstructionType::Line(end) => {
+                data = data.line_to((end.wx, end.wy));
+                nb_segments += 1;
+                pos = *end;
+            }
+            ConstructionType::QuadBezier(ctrl, end) => {
+                data = data.quadratic_curve_to((ctrl.wx, ctrl.wy, end.wx, end.wy));
+                nb_segments += 1;
+                pos = *end;
+            }
+            ConstructionType::CubicBezier(ctrl1, ctrl2, end) => {
+                data =
+                    data.cubic_curve_to((ctrl1.wx, ctrl1.wy, ctrl2.wx, ctrl2.wy, end.wx, end.wy));
+                nb_segments += 1;
+                pos = *end;
+            }
+            ConstructionType::Ellipse(center, radius, rotation, start_angle, end_angle, _) => {
+                let sweep = get_sweep_angle(*start_angle, *end_angle);
+                // An arc command can't draw a whole ellipse, it is drawn in
+                // two halves
+                let nb_arcs = if sweep.abs() > 2. * PI - 1e-6 { 2 } else { 1 };
+                let arc_sweep = sweep / nb_arcs as f64;
+                for i in 1..=nb_arcs {
+                    let end = get_ellipse_pos(
+                        center,
+                        radius,
+                        *rotation,
+                        start_angle + i as f64 * arc_sweep,
+                    );
+                    data = data.elliptical_arc_to((
+                        radius.wx.abs(),
+                        radius.wy.abs(),
+                        rotation.to_degrees(),
+                        (arc_sweep.abs() > PI) as u8,
+                        (arc_sweep > 0.) as u8,
+                        end.wx,
+                        end.wy,
+                    ));
+                    pos = end;
+                }
+                nb_segments += nb_arcs;
+            }
+            _ => (),
+        }
+    }
+    if nb_segments == 0 {
+        return None;
+    }
+    if nb_segments > 1 && pos.dist(&start) < EPSILON {
+        data = data.close();
+    }
+    Some(
+        Path::new()
+            .set("fill", "none")
+            .set("stroke", "black")
+            .set("stroke-width", STROKE_WIDTH)
+            .set("d", data),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::ellipse::Ellipse;
+    use crate::shapes::line::Line;
+    use crate::shapes::rectangle::Rectangle;
+    use crate::shapes::types::GroupId;
+    use std::collections::HashMap;
+    use svg::node::element::path::Command;
+    use svg::parser::Event;
+
+    #[test]
+    fn test_svg_export() {
+        let mut shapes_pool = ShapesPool(HashMap::new());
+        shapes_pool.insert(
+            ShapeId(1),
+            Box::new(Rectangle::new(&WPos::new(10., 10.), 30., 20.).unwrap()),
+        );
+        shapes_pool.insert(
+            ShapeId(2),
+            Box::new(Ellipse::new(
+                &WPos::new(50., 50.),
+                &WPos::new(60., 55.),
+                0.,
+                0.,
+                1.,
+            )),
+        );
+        shapes_pool.insert(
+            ShapeId(3),
+            Box::new(Line::new(&WPos::new(0., 0.), &WPos::new(100., 100.)).unwrap()),
+        );
+        let mut groups_pool = GroupsPool::new();
+        let grp_id = GroupId::new_id();
+        groups_pool.insert_shape_id(&grp_id, &ShapeId(1));
+        groups_pool.insert_shape_id(&grp_id, &ShapeId(2));
+
+        let content = get_svg_from_pools(&shapes_pool, &groups_pool, &WPos::new(1000., 500.));
+        assert!(content.contains("viewBox=\"0 0 1000 500\""));
+        assert!(content.contains("width=\"1000mm\""));
+
+        let mut depth = 0;
+        let mut paths = vec![];
+        for event in svg::read(&content).unwrap() {
+            match event {
+                Event::Tag("g", svg::node::element::tag::Type::Start, _) => depth += 1,
+                Event::Tag("g", svg::node::element::tag::Type::End, _) => depth -= 1,
+                Event::Tag("path", _, attributes) => {
+                    let data = Data::parse(attributes.get("d").unwrap()).unwrap();
+                    let commands: Vec<Command> = data.iter().cloned().collect();
+                    paths.push((depth, commands));
+                }
+                _ => (),
+            }
+        }
+        assert_eq!(paths.len(), 3);
+        // The rectangle, closed, and the circle in two arcs are in the group
+        assert_eq!(paths[0].0, 1);
+        assert_eq!(paths[0].1.len(), 6);
+        assert!(matches!(paths[0].1[5], Command::Close));
+        assert_eq!(paths[1].0, 1);
+        assert!(matches!(paths[1].1[1], Command::EllipticalArc(..)));
+        assert!(matches!(paths[1].1[2], Command::EllipticalArc(..)));
+        if let Command::EllipticalArc(_, params) = &paths[1].1[2] {
+            assert_eq!(params.len(), 7);
+            assert!((params[5] - 60.).abs() < 1e-3 && (params[6] - 50.).abs() < 1e-3);
+        }
+        assert_eq!(paths[2].0, 0);
+        assert_eq!(paths[2].1.len(), 2);
+    }
+
+    #[test]
+    fn test_svg_export_shape_in_several_groups() {
+        let mut shapes_pool = ShapesPool(HashMap::new());
+        for idx in 1..=3 {
+            let x = 20. * idx as f64;
+            let line = Line::new(&WPos::new(x, 0.), &WPos::new(x, 10.)).unwrap();
+            shapes_pool.insert(ShapeId(idx), Box::new(line));
+        }
+        let mut groups_pool = GroupsPool::new();
+        for sh_ids in [[1, 2], [1, 3]] {
+            let grp_id = GroupId::new_id();
+            for sh_id in sh_ids {
+                groups_pool.insert_shape_id(&grp_id, &ShapeId(sh_id));
+            }
+        }
+
+        let content = get_svg_from_pools(&shapes_pool, &groups_pool, &WPos::new(1000., 500.));
+        // The first line is written with its first group only
+        assert_eq!(content.matches("<path").count(), 3);
+    }
+}
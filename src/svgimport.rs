@@ -0,0 +1,1200 @@
+use crate::import::{
+    get_placement, push_cubic_bezier, push_ellipse_arc, push_line, push_quad_bezier, push_shape,
+    ImportOptions, ImportReport,
+};
+use crate::math::Transform;
+use crate::shapes::rectangle::Rectangle;
+use crate::shapes::types::{Shape, WPos};
+use std::f64::consts::PI;
+use svg::node::element::path::{Command, Data, Position};
+use svg::node::element::tag;
+use svg::node::element::tag::Type;
+use svg::node::Attributes;
+use svg::parser::{Event, Parser};
+
+// Millimeters in a CSS pixel, the SVG user unit when nothing else is given
+const MM_PER_PX: f64 = 25.4 / 96.;
+
+// Shapes of the paths and of the basic shapes of an SVG file, in millimeters
+// and placed on the working sheet, with the report of the import
+pub fn get_shapes_from_svg(
+    svg_data: &str,
+    options: &ImportOptions,
+) -> (Vec<Box<dyn Shape>>, ImportReport) {
+    let transform = Transform::scale(options.scale, options.scale);
+    let (shapes, report) = get_transformed_shapes(svg_data, &transform, options.flip_y);
+    let Some(placement) = get_placement(&shapes, options) else {
+        return (shapes, report);
+    };
+    // The file is read again with the offset, the shapes have no way to be
+    // moved outside of a selection
+    let transform = placement * transform;
+    get_transformed_shapes(svg_data, &transform, options.flip_y)
+}
+
+// The transformation of an element is the one of its parent followed by its
+// own, the content of the definitions is only drawn when used
+fn get_transformed_shapes(
+    svg_data: &str,
+    transform: &Transform,
+    flip_y: bool,
+) -> (Vec<Box<dyn Shape>>, ImportReport) {
+    let mut shapes: Vec<Box<dyn Shape>> = vec![];
+    let mut report = ImportReport::new();
+    // Transformation and hidden state of the opened elements
+    let mut parents: Vec<(Transform, bool)> = vec![];
+    for event in Parser::new(svg_data) {
+        let (tag_name, tag_type, attributes) = match event {
+            Event::Tag(tag_name, tag_type, attributes) => (tag_name, tag_type, attributes),
+            // The error tells where it is
+            Event::Error(e) => {
+                report.add_error(0, e.to_string());
+                continue;
+            }
+            _ => continue,
+        };
+        if tag_type == Type::End {
+            parents.pop();
+            continue;
+        }
+        let line = get_line(svg_data, tag_name);
+        let (parent_transform, parent_hidden) = parents
+            .last()
+            .copied()
+            .unwrap_or((*transform * Transform::scale(MM_PER_PX, MM_PER_PX), false));
+        // An element misplaced would be worse than a missing one
+        let (own_transform, invalid_transform) = match attributes.get("transform") {
+            Some(value) => match get_transform(value) {
+                Ok(own_transform) => (own_transform, false),
+                Err(e) => {
+                    if !parent_hidden {
+                        report.add_skipped(line, format!("{}: {}", tag_name, e));
+                    }
+                    (Transform::identity(), true)
+                }
+            },
+            None => (Transform::identity(), false),
+        };
+        let transform = if tag_name == tag::SVG {
+            let (viewport, size) = get_viewport(&attributes, line, &mut report);
+            if parents.is_empty() {
+                // The outermost viewport gives the size of the page
+                let flip = if flip_y {
+                    Transform::new(1., 0., 0., -1., 0., size.wy * MM_PER_PX)
+                } else {
+                    Transform::identity()
+                };
+                *transform * flip * Transform::scale(MM_PER_PX, MM_PER_PX) * viewport
+            } else {
+                parent_transform * own_transform * viewport
+            }
+        } else {
+            parent_transform * own_transform
+        };
+        let hidden = parent_hidden
+            || invalid_transform
+            || [
+                tag::Definitions,
+                tag::Symbol,
+                tag::ClipPath,
+                tag::Mask,
+                tag::Marker,
+                tag::Pattern,
+            ]
+            .contains(&tag_name);
+        if tag_type == Type::Start {
+            parents.push((transform, hidden));
+        }
+        if hidden {
+            continue;
+        }
+        match tag_name {
+            tag::Path => {
+                let Some(data) = attributes.get("d") else {
+                    report.add_skipped(line, "path without data".into());
+                    continue;
+                };
+                let data = match Data::parse(data) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        report.add_error(line, format!("path data: {}", e));
+                        continue;
+                    }
+                };
+                let nb_invalid_commands = push_path_shapes(&data, &transform, &mut shapes);
+                if nb_invalid_commands > 0 {
+                    report.add_warning(
+                        line,
+                        format!(
+                            "path: {} command(s) with a wrong number of parameters ignored",
+                            nb_invalid_commands
+                        ),
+                    );
+                }
+            }
+            tag::Rectangle => push_rect_shapes(&attributes, &transform, &mut shapes),
+            tag::Circle => {
+                let center = get_attribute_pos(&attributes, "cx", "cy");
+                let radius = get_attribute(&attributes, "r");
+                let radius = WPos::new(radius, radius);
+                push_ellipse_arc(&center, &radius, 0., 0., 2. * PI, &transform, &mut shapes);
+            }
+            tag::Ellipse => {
+                let center = get_attribute_pos(&attributes, "cx", "cy");
+                let radius = get_attribute_pos(&attributes, "rx", "ry");
+                push_ellipse_arc(&center, &radius, 0., 0., 2. * PI, &transform, &mut shapes);
+            }
+            tag::Line => {
+                let start = get_attribute_pos(&attributes, "x1", "y1");
+                let end = get_attribute_pos(&attributes, "x2", "y2");
+                push_line(&start, &end, &transform, &mut shapes);
+            }
+            tag::Polyline | tag::Polygon => {
+                let coords = attributes
+                    .get("points")
+                    .map(|points| get_numbers(points))
+                    .unwrap_or_default();
+                let mut points: Vec<WPos> = coords
+                    .chunks_exact(2)
+                    .map(|coords| WPos::new(coords[0], coords[1]))
+                    .collect();
+                if tag_name == tag::Polygon && points.len() > 2 {
+                    points.push(points[0]);
+                }
+                for pts in points.windows(2) {
+                    push_line(&pts[0], &pts[1], &transform, &mut shapes);
+                }
+            }
+            tag::Text | tag::Image | tag::Use | tag::ForeignObject => {
+                report.add_skipped(line, format!("{} is not supported", tag_name));
+            }
+            _ => (),
+        }
+    }
+    report.nb_shapes = shapes.len();
+    (shapes, report)
+}
+
+// Line of a tag in the SVG data, the name of the tag is a slice of the data
+fn get_line(svg_data: &str, tag_name: &str) -> usize {
+    let offset = (tag_name.as_ptr() as usize)
+        .saturating_sub(svg_data.as_ptr() as usize)
+        .min(svg_data.len());
+    svg_data.as_bytes()[..offset]
+        .iter()
+        .filter(|c| **c == b'\n')
+        .count()
+        + 1
+}
+
+// Viewport of an svg element, its view box is scaled to its size and aligned
+// (SVG 1.1, 7.8). The size of the viewport is returned too, both are in the
+// user units of the parent.
+fn get_viewport(
+    attributes: &Attributes,
+    line: usize,
+    report: &mut ImportReport,
+) -> (Transform, WPos) {
+    let pos = get_attribute_pos(attributes, "x", "y");
+    let (width, height) = (
+        get_length(attributes, "width"),
+        get_length(attributes, "height"),
+    );
+    let view_box = attributes
+        .get("viewBox")
+        .map(|value| get_numbers(value))
+        .filter(|view_box| view_box.len() == 4 && view_box[2] > 0. && view_box[3] > 0.);
+    let Some(view_box) = view_box else {
+        let size = WPos::new(width.unwrap_or(0.), height.unwrap_or(0.));
+        return (Transform::translate(pos.wx, pos.wy), size);
+    };
+
+    let size = WPos::new(width.unwrap_or(view_box[2]), height.unwrap_or(view_box[3]));
+    let (mut sx, mut sy) = (size.wx / view_box[2], size.wy / view_box[3]);
+    let mut offset = pos;
+    let aspect_ratio = match attributes.get("preserveAspectRatio") {
+        Some(value) => get_aspect_ratio(value).unwrap_or_else(|e| {
+            report.add_warning(line, e);
+            Some((WPos::new(0.5, 0.5), false))
+        }),
+        None => Some((WPos::new(0.5, 0.5), false)),
+    };
+    if let Some((align, slice)) = aspect_ratio {
+        sx = if slice { sx.max(sy) } else { sx.min(sy) };
+        sy = sx;
+        offset += WPos::new(
+            (size.wx - view_box[2] * sx) * align.wx,
+            (size.wy - view_box[3] * sy) * align.wy,
+        );
+    }
+    let transform =
+        Transform::translate(offset.wx - view_box[0] * sx, offset.wy - view_box[1] * sy)
+            * Transform::scale(sx, sy);
+    (transform, size)
+}
+
+// Alignment of the view box in its viewport, from 0 at the min side to 1 at
+// the max one, and whether it is scaled to cover the viewport (slice) rather
+// than to fit in it (meet). None when the ratio isn't preserved.
+fn get_aspect_ratio(value: &str) -> Result<Option<(WPos, bool)>, String> {
+    let mut words = value.split_whitespace().peekable();
+    if words.peek() == Some(&"defer") {
+        words.next();
+    }
+    let align = words.next().unwrap_or("xMidYMid");
+    let slice = match words.next() {
+        None | Some("meet") => false,
+        Some("slice") => true,
+        Some(word) => return Err(format!("unknown preserveAspectRatio \"{}\"", word)),
+    };
+    if words.next().is_some() || (align == "none" && slice) {
+        return Err(format!("invalid preserveAspectRatio \"{}\"", value.trim()));
+    }
+    if align == "none" {
+        return Ok(None);
+    }
+    let get_factor = |name: &str| match name {
+        "Min" => Some(0.),
+        "Mid" => Some(0.5),
+        "Max" => Some(1.),
+        _ => None,
+    };
+    let factors = align
+        .strip_prefix('x')
+        .filter(|align| align.len() == 7 && align.is_char_boundary(3))
+        .and_then(|align| {
+            let (x, y) = align.split_at(3);
+            Some((get_factor(x)?, get_factor(y.strip_prefix('Y')?)?))
+        });
+    match factors {
+        Some((x, y)) => Ok(Some((WPos::new(x, y), slice))),
+        None => Err(format!("unknown preserveAspectRatio \"{}\"", align)),
+    }
+}
+
+// Length of an attribute in pixels, a percentage has no absolute length
+fn get_length(attributes: &Attributes, name: &str) -> Option<f64> {
+    let value = attributes.get(name)?.trim();
+    if value.ends_with('%') {
+        return None;
+    }
+    let length = get_numbers(value).first().copied()?;
+    let px_per_unit = [
+        ("mm", 96. / 25.4),
+        ("cm", 96. / 2.54),
+        ("in", 96.),
+        ("pt", 96. / 72.),
+        ("pc", 16.),
+    ]
+    .iter()
+    .find(|(unit, _)| value.ends_with(unit))
+    .map(|(_, px_per_unit)| *px_per_unit)
+    .unwrap_or(1.);
+    Some(length * px_per_unit)
+}
+
+// Transformation of a transform attribute, the first one of the list is the
+// last applied
+fn get_transform(value: &str) -> Result<Transform, String> {
+    let mut transform = Transform::identity();
+    for item in value.split(')') {
+        if item
+            .trim_matches(|c: char| c.is_whitespace() || c == ',')
+            .is_empty()
+        {
+            continue;
+        }
+        let Some((name, args)) = item.split_once('(') else {
+            return Err(format!("invalid transform \"{}\"", item.trim()));
+        };
+        let name = name.trim_matches(|c: char| c.is_whitespace() || c == ',');
+        let args = get_numbers(args);
+        let item_transform = match (name, args.len()) {
+            ("matrix", 6) => Transform::new(args[0], args[1], args[2], args[3], args[4], args[5]),
+            ("translate", 1) => Transform::translate(args[0], 0.),
+            ("translate", 2) => Transform::translate(args[0], args[1]),
+            ("scale", 1) => Transform::scale(args[0], args[0]),
+            ("scale", 2) => Transform::scale(args[0], args[1]),
+            ("rotate", 1) => Transform::rotate(args[0].to_radians()),
+            ("rotate", 3) => {
+                Transform::translate(args[1], args[2])
+                    * Transform::rotate(args[0].to_radians())
+                    * Transform::translate(-args[1], -args[2])
+            }
+            ("skewX", 1) => Transform::skew(args[0].to_radians(), 0.),
+            ("skewY", 1) => Transform::skew(0., args[0].to_radians()),
+            _ => return Err(format!("invalid transform \"{}\"", item.trim())),
+        };
+        transform = transform * item_transform;
+    }
+    Ok(transform)
+}
+
+// Numbers of an attribute, separated by spaces or commas, the units are
+// ignored
+fn get_numbers(value: &str) -> Vec<f64> {
+    let mut numbers = vec![];
+    let mut number = String::new();
+    let mut push_number = |number: &mut String| {
+        if let Ok(value) = number.parse::<f64>() {
+            numbers.push(value);
+        }
+        number.clear();
+    };
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            // A sign or a second dot starts the next number, except in an
+            // exponent
+            '-' | '+' if !number.ends_with(['e', 'E']) => {
+                push_number(&mut number);
+                number.push(c);
+            }
+            '.' if number.contains('.') || number.contains(['e', 'E']) => {
+                push_number(&mut number);
+                number.push(c);
+            }
+            '-' | '+' | '.' => number.push(c),
+            'e' | 'E' if !number.is_empty() => number.push(c),
+            _ => push_number(&mut number),
+        }
+    }
+    push_number(&mut number);
+    numbers
+}
+fn get_attribute(attributes: &Attributes, name: &str) -> f64 {
+    attributes
+        .get(name)
+        .and_then(|value| get_numbers(value).first().copied())
+        .unwrap_or(0.)
+}
+fn get_attribute_pos(attributes: &Attributes, name_x: &str, name_y: &str) -> WPos {
+    WPos::new(
+        get_attribute(attributes, name_x),
+        get_attribute(attributes, name_y),
+    )
+}
+
+// A rectangle with rounded corners is made of its straight sides and of four
+// quarters of ellipse, a turned rectangle of its sides
+fn push_rect_shapes(
+    attributes: &Attributes,
+    transform: &Transform,
+    shapes: &mut Vec<Box<dyn Shape>>,
+) {
+    let pos = get_attribute_pos(attributes, "x", "y");
+    let (width, height) = (
+        get_attribute(attributes, "width"),
+        get_attribute(attributes, "height"),
+    );
+    if width <= 0. || height <= 0. {
+        return;
+    }
+    // A missing radius is the same as the other one
+    let (rx, ry) = match (attributes.get("rx"), attributes.get("ry")) {
+        (None, None) => (0., 0.),
+        (Some(_), None) => {
+            let rx = get_attribute(attributes, "rx");
+            (rx, rx)
+        }
+        (None, Some(_)) => {
+            let ry = get_attribute(attributes, "ry");
+            (ry, ry)
+        }
+        (Some(_), Some(_)) => (
+            get_attribute(attributes, "rx"),
+            get_attribute(attributes, "ry"),
+        ),
+    };
+    let (rx, ry) = (rx.clamp(0., width / 2.), ry.clamp(0., height / 2.));
+    let (x0, y0) = (pos.wx, pos.wy);
+    let (x1, y1) = (pos.wx + width, pos.wy + height);
+    if rx == 0. || ry == 0. {
+        let corners = [
+            WPos::new(x0, y0),
+            WPos::new(x1, y0),
+            WPos::new(x1, y1),
+            WPos::new(x0, y1),
+        ];
+        if transform.is_axis_aligned() {
+            let (corner0, corner1) = (transform.apply(&corners[0]), transform.apply(&corners[2]));
+            let top_left = WPos::new(corner0.wx.min(corner1.wx), corner0.wy.min(corner1.wy));
+            let size = (corner1 - corner0).abs();
+            push_shape(Rectangle::new(&top_left, size.wx, size.wy), shapes);
+        } else {
+            for idx in 0..4 {
+                push_line(&corners[idx], &corners[(idx + 1) % 4], transform, shapes);
+            }
+        }
+        return;
+    }
+
+    let radius = WPos::new(rx, ry);
+    // Clockwise on screen, from the top left corner
+    let corner_arc = |center: WPos, start_angle: f64, shapes: &mut Vec<Box<dyn Shape>>| {
+        push_ellipse_arc(
+            &center,
+            &radius,
+            0.,
+            start_angle,
+            PI / 2.,
+            transform,
+            shapes,
+        );
+    };
+    corner_arc(WPos::new(x0 + rx, y0 + ry), PI, shapes);
+    push_line(
+        &WPos::new(x0 + rx, y0),
+        &WPos::new(x1 - rx, y0),
+        transform,
+        shapes,
+    );
+    corner_arc(WPos::new(x1 - rx, y0 + ry), 1.5 * PI, shapes);
+    push_line(
+        &WPos::new(x1, y0 + ry),
+        &WPos::new(x1, y1 - ry),
+        transform,
+        shapes,
+    );
+    corner_arc(WPos::new(x1 - rx, y1 - ry), 0., shapes);
+    push_line(
+        &WPos::new(x1 - rx, y1),
+        &WPos::new(x0 + rx, y1),
+        transform,
+        shapes,
+    );
+    corner_arc(WPos::new(x0 + rx, y1 - ry), 0.5 * PI, shapes);
+    push_line(
+        &WPos::new(x0, y1 - ry),
+        &WPos::new(x0, y0 + ry),
+        transform,
+        shapes,
+    );
+}
+
+// Returns the number of commands ignored for a wrong number of parameters
+fn push_path_shapes(data: &Data, transform: &Transform, shapes: &mut Vec<Box<dyn Shape>>) -> usize {
+    let mut nb_invalid_commands = 0;
+    let mut current_position = WPos::default();
+    let mut start_position = WPos::default();
+    let mut last_quad_control_point: Option<WPos> = None;
+    let mut last_cubic_control_point: Option<WPos> = None;
+    for command in data.iter() {
+        match command.clone() {
+            Command::Move(postype, params) => {
+                if params.len() >= 2 && params.len() % 2 == 0 {
+                    // The pairs after the first one are lines, relative after
+                    // a relative move
+                    for (idx, pair) in params.chunks(2).enumerate() {
+                        let end_point = WPos {
+                            wx: pair[0] as f64,
+                            wy: pair[1] as f64,
+                        };
+                        let new_position = match postype {
+                            Position::Absolute => end_point,
+                            Position::Relative => current_position + end_point,
+                        };
+                        if idx == 0 {
+                            start_position = new_position;
+                        } else {
+                            push_line(&current_position, &new_position, transform, shapes);
+                        }
+                        current_position = new_position;
+                    }
+                    last_quad_control_point = None;
+                    last_cubic_control_point = None;
+                } else {
+                    nb_invalid_commands += 1;
+                }
+            }
+            Command::Line(postype, params) => {
+                if params.len() % 2 == 0 {
+                    let nb_curves = params.len() / 2;
+                    for curve in 0..nb_curves {
+                        let end_point = WPos {
+                            wx: params[2 * curve] as f64,
+                            wy: params[2 * curve + 1] as f64,
+                        };
+                        let new_position = match postype {
+                            Position::Absolute => end_point,
+                            Position::Relative => current_position + end_point,
+                        };
+                        push_line(&current_position, &new_position, transform, shapes);
+
+                        current_position = new_position;
+                        last_quad_control_point = None;
+                        last_cubic_control_point = None;
+                    }
+                } else {
+                    nb_invalid_commands += 1;
+                }
+            }
+            Command::HorizontalLine(postype, params) => {
+                for param in params.iter() {
+                    let new_position = match postype {
+                        Position::Absolute => WPos::new(*param as f64, current_position.wy),
+                        Position::Relative => current_position + WPos::new(*param as f64, 0.),
+                    };
+                    push_line(&current_position, &new_position, transform, shapes);
+
+                    current_position = new_position;
+                    last_quad_control_point = None;
+                    last_cubic_control_point = None;
+                }
+            }
+            Command::VerticalLine(postype, params) => {
+                for param in params.iter() {
+                    let new_position = match postype {
+                        Position::Absolute => WPos::new(current_position.wx, *param as f64),
+                        Position::Relative => current_position + WPos::new(0., *param as f64),
+                    };
+                    push_line(&current_position, &new_position, transform, shapes);
+
+                    current_position = new_position;
+                    last_quad_control_point = None;
+                    last_cubic_control_point = None;
+                }
+            }
+            Command::QuadraticCurve(postype, params) => {
+                if params.len() % 4 == 0 {
+                    let nb_curves = params.len() / 4;
+                    for curve in 0..nb_curves {
+                        let mut control_point = WPos {
+                            wx: params[4 * curve] as f64,
+                            wy: params[4 * curve + 1] as f64,
+                        };
+                        let end_point = WPos {
+                            wx: params[4 * curve + 2] as f64,
+                            wy: params[4 * curve + 3] as f64,
+                        };
+                        let new_position = match postype {
+                            Position::Absolute => end_point,
+                            Position::Relative => {
+                                control_point += current_position;
+                                current_position + end_point
+                            }
+                        };
+                        push_quad_bezier(
+                            &current_position,
+                            &control_point,
+                            &new_position,
+                            transform,
+                            shapes,
+                        );
+
+                        current_position = new_position;
+                        last_quad_control_point = Some(control_point);
+                        last_cubic_control_point = None;
+                    }
+                } else {
+                    nb_invalid_commands += 1;
+                }
+            }
+            Command::SmoothQuadraticCurve(postype, params) => {
+                if params.len() % 2 == 0 {
+                    let nb_curves = params.len() / 2;
+                    for curve in 0..nb_curves {
+                        let control_point = if let Some(last_ctrl_pt) = last_quad_control_point {
+                            current_position + (current_position - last_ctrl_pt)
+                        } else {
+                            current_position
+                        };
+                        let end_point = WPos {
+                            wx: params[2 * curve] as f64,
+                            wy: params[2 * curve + 1] as f64,
+                        };
+                        let new_position = match postype {
+                            Position::Absolute => end_point,
+                            Position::Relative => current_position + end_point,
+                        };
+                        push_quad_bezier(
+                            &current_position,
+                            &control_point,
+                            &new_position,
+                            transform,
+                            shapes,
+                        );
+
+                        current_position = new_position;
+                        last_quad_control_point = Some(control_point);
+                        last_cubic_control_point = None;
+                    }
+                } else {
+                    nb_invalid_commands += 1;
+                }
+            }
+            Command::CubicCurve(postype, params) => {
+                if params.len() % 6 == 0 {
+                    let nb_curves = params.len() / 6;
+                    for curve in 0..nb_curves {
+                        let mut control_point1 = WPos {
+                            wx: params[6 * curve] as f64,
+                            wy: params[6 * curve + 1] as f64,
+                        };
+                        let mut control_point2 = WPos {
+                            wx: params[6 * curve + 2] as f64,
+                            wy: params[6 * curve + 3] as f64,
+                        };
+                        let end_point = WPos {
+                            wx: params[6 * curve + 4] as f64,
+                            wy: params[6 * curve + 5] as f64,
+                        };
+                        let new_position = match postype {
+                            Position::Absolute => end_point,
+                            Position::Relative => {
+                                control_point1 += current_position;
+                                control_point2 += current_position;
+                                current_position + end_point
+                            }
+                        };
+                        push_cubic_bezier(
+                            &current_position,
+                            &control_point1,
+                            &control_point2,
+                            &new_position,
+                            transform,
+                            shapes,
+                        );
+                        current_position = new_position;
+                        last_quad_control_point = None;
+                        last_cubic_control_point = Some(control_point2);
+                    }
+                } else {
+                    nb_invalid_commands += 1;
+                }
+            }
+            Command::SmoothCubicCurve(postype, params) => {
+                if params.len() % 4 == 0 {
+                    let nb_curves = params.len() / 4;
+                    for curve in 0..nb_curves {
+                        let control_point1 = if let Some(last_ctrl_pt) = last_cubic_control_point {
+                            current_position + (current_position - last_ctrl_pt)
+                        } else {
+                            current_position
+                        };
+                        let mut control_point2 = WPos {
+                            wx: params[4 * curve] as f64,
+                            wy: params[4 * curve + 1] as f64,
+                        };
+                        let end_point = WPos {
+                            wx: params[4 * curve + 2] as f64,
+                            wy: params[4 * curve + 3] as f64,
+                        };
+                        let new_position = match postype {
+                            Position::Absolute => end_point,
+                            Position::Relative => {
+                                control_point2 += current_position;
+                                current_position + end_point
+                            }
+                        };
+                        push_cubic_bezier(
+                            &current_position,
+                            &control_point1,
+                            &control_point2,
+                            &new_position,
+                            transform,
+                            shapes,
+                        );
+
+                        current_position = new_position;
+                        last_quad_control_point = None;
+                        last_cubic_control_point = Some(control_point2);
+                    }
+                } else {
+                    nb_invalid_commands += 1;
+                }
+            }
+            Command::EllipticalArc(postype, params) => {
+                if params.len() % 7 == 0 {
+                    let nb_curves = params.len() / 7;
+                    for curve in 0..nb_curves {
+                        let radius = WPos {
+                            wx: params[7 * curve] as f64,
+                            wy: params[7 * curve + 1] as f64,
+                        };
+                        let rotation = (params[7 * curve + 2] as f64).to_radians();
+                        let large_arc = params[7 * curve + 3] != 0.;
+                        let sweep = params[7 * curve + 4] != 0.;
+                        let end_point = WPos {
+                            wx: params[7 * curve + 5] as f64,
+                            wy: params[7 * curve + 6] as f64,
+                        };
+                        let new_position = match postype {
+                            Position::Absolute => end_point,
+                            Position::Relative => current_position + end_point,
+                        };
+                        shapes.extend(get_svg_arc_shapes(
+                            &current_position,
+                            &radius,
+                            rotation,
+                            large_arc,
+                            sweep,
+                            &new_position,
+                            transform,
+                        ));
+
+                        current_position = new_position;
+                        last_quad_control_point = None;
+                        last_cubic_control_point = None;
+                    }
+                } else {
+                    nb_invalid_commands += 1;
+                }
+            }
+            Command::Close => {
+                push_line(&current_position, &start_position, transform, shapes);
+
+                current_position = start_position;
+                last_quad_control_point = None;
+                last_cubic_control_point = None;
+            }
+        }
+    }
+    nb_invalid_commands
+}
+
+// Arc of an SVG path, in endpoint parameterization. The ellipse is found
+// back from the radii and the flags (SVG 1.1, appendix F.6.5).
+pub fn get_svg_arc_shapes(
+    start: &WPos,
+    radius: &WPos,
+    rotation: f64,
+    large_arc: bool,
+    sweep: bool,
+    end: &WPos,
+    transform: &Transform,
+) -> Vec<Box<dyn Shape>> {
+    let mut shapes: Vec<Box<dyn Shape>> = vec![];
+    if start == end {
+        return shapes;
+    }
+    let (mut rx, mut ry) = (radius.wx.abs(), radius.wy.abs());
+    if rx == 0. || ry == 0. {
+        // Degenerated ellipse, straight line
+        push_line(start, end, transform, &mut shapes);
+        return shapes;
+    }
+
+    // Middle of the chord, in the axes of the ellipse
+    let (sin_rot, cos_rot) = rotation.sin_cos();
+    let half = (*start - *end) / 2.;
+    let x1 = cos_rot * half.wx + sin_rot * half.wy;
+    let y1 = -sin_rot * half.wx + cos_rot * half.wy;
+
+    // Radii too small to join the ends are scaled up
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1. {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coef = (num / den).max(0.).sqrt();
+    if large_arc == sweep {
+        coef = -coef;
+    }
+    let cx1 = coef * rx * y1 / ry;
+    let cy1 = -coef * ry * x1 / rx;
+    let center = WPos::new(
+        cos_rot * cx1 - sin_rot * cy1 + (start.wx + end.wx) / 2.,
+        sin_rot * cx1 + cos_rot * cy1 + (start.wy + end.wy) / 2.,
+    );
+
+    let start_angle = ((y1 - cy1) / ry).atan2((x1 - cx1) / rx);
+    let end_angle = ((-y1 - cy1) / ry).atan2((-x1 - cx1) / rx);
+    let mut sweep_angle = (end_angle - start_angle).rem_euclid(2. * PI);
+    if !sweep && sweep_angle > 0. {
+        sweep_angle -= 2. * PI;
+    }
+    push_ellipse_arc(
+        &center,
+        &WPos::new(rx, ry),
+        rotation,
+        start_angle,
+        sweep_angle,
+        transform,
+        &mut shapes,
+    );
+    shapes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::{ImportMessage, ImportOrigin};
+
+    fn assert_ends(shapes: &[Box<dyn Shape>], start: &WPos, end: &WPos) {
+        let first = shapes.first().unwrap();
+        let last = shapes.last().unwrap();
+        let (pos0, pos1) = (first.get_pos_from_ratio(0.), last.get_pos_from_ratio(1.));
+        // The Ellipse arcs may go the other way
+        assert!(
+            (pos0.dist(start) < 1e-6 && pos1.dist(end) < 1e-6)
+                || (pos0.dist(end) < 1e-6 && pos1.dist(start) < 1e-6),
+            "{:?} {:?}",
+            pos0,
+            pos1
+        );
+    }
+
+    #[test]
+    fn test_svg_arc_to_ellipse() {
+        // Quarter of circle of radius 10 centered on (10, 10), from (0, 10)
+        // to (10, 0), the small arc going clockwise on screen
+        let (start, end) = (WPos::new(0., 10.), WPos::new(10., 0.));
+        let shapes = get_svg_arc_shapes(
+            &start,
+            &WPos::new(10., 10.),
+            0.,
+            false,
+            true,
+            &end,
+            &Transform::identity(),
+        );
+        assert_eq!(shapes.len(), 1);
+        assert_ends(&shapes, &start, &end);
+        assert!(
+            shapes[0]
+                .get_pos_from_ratio(0.5)
+                .dist(&WPos::new(10. - 50_f64.sqrt(), 10. - 50_f64.sqrt()))
+                < 1e-6
+        );
+
+        // The large arc, going the other way around the same center
+        let shapes = get_svg_arc_shapes(
+            &start,
+            &WPos::new(10., 10.),
+            0.,
+            true,
+            false,
+            &end,
+            &Transform::identity(),
+        );
+        assert_ends(&shapes, &start, &end);
+        assert!(shapes[0].dist(&WPos::new(20., 10.)) < 1e-6);
+        assert!(shapes[0].dist(&WPos::new(10., 20.)) < 1e-6);
+        // The small arc going the other way is around (0, 0)
+        let shapes = get_svg_arc_shapes(
+            &start,
+            &WPos::new(10., 10.),
+            0.,
+            false,
+            false,
+            &end,
+            &Transform::identity(),
+        );
+        assert!(shapes[0].dist(&WPos::new(50_f64.sqrt(), 50_f64.sqrt())) < 1e-6);
+
+        // Radii too small are scaled up to a half circle
+        let shapes = get_svg_arc_shapes(
+            &WPos::new(0., 0.),
+            &WPos::new(1., 1.),
+            0.,
+            false,
+            true,
+            &WPos::new(20., 0.),
+            &Transform::identity(),
+        );
+        assert!(shapes[0].dist(&WPos::new(10., -10.)) < 1e-6);
+
+        // An ellipse rotated by a quarter of turn
+        let (start, end) = (WPos::new(0., -20.), WPos::new(10., 0.));
+        let shapes = get_svg_arc_shapes(
+            &start,
+            &WPos::new(20., 10.),
+            PI / 2.,
+            false,
+            true,
+            &end,
+            &Transform::identity(),
+        );
+        assert_eq!(shapes.len(), 1);
+        assert_ends(&shapes, &start, &end);
+        assert!(shapes[0].dist(&WPos::new(-10., 0.)) > 1.);
+    }
+
+    #[test]
+    fn test_svg_turned_arc() {
+        // Half of an ellipse of radii 20 and 10 centered on the origin, turned
+        // by 30 degrees
+        let rotation = PI / 6.;
+        let (sin_rot, cos_rot) = rotation.sin_cos();
+        let start = WPos::new(20. * cos_rot, 20. * sin_rot);
+        let end = -start;
+        let shapes = get_svg_arc_shapes(
+            &start,
+            &WPos::new(20., 10.),
+            rotation,
+            false,
+            true,
+            &end,
+            &Transform::identity(),
+        );
+        // Turned, the arc stays an ellipse
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(shapes[0].get_type_name(), "ellipse");
+        assert_ends(&shapes, &start, &end);
+        for shape in shapes.iter() {
+            for i in 0..=10 {
+                let pos = shape.get_pos_from_ratio(i as f64 / 10.);
+                let x = cos_rot * pos.wx + sin_rot * pos.wy;
+                let y = -sin_rot * pos.wx + cos_rot * pos.wy;
+                assert!(((x / 20.).powi(2) + (y / 10.).powi(2) - 1.).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_svg_basic_shapes() {
+        let svg_data = r#"<svg xmlns="http://www.w3.org/2000/svg" width="200mm" height="200mm"
+            viewBox="0 0 200 200">
+            <rect x="10" y="10" width="30" height="20"/>
+            <rect x="50" y="10" width="40" height="20" rx="5"/>
+            <circle cx="100" cy="100" r="10"/>
+            <ellipse cx="150" cy="100" rx="20" ry="10"></ellipse>
+            <line x1="0" y1="0" x2="10" y2="0"/>
+            <polyline points="0,50 10,50 10,60"/>
+            <polygon points="0 80, 10 80 5-70"/>
+            <path d="M 0 100 L 20 100"/>
+            <path d="M 30 100 40 100 40 110"/>
+            <path d="m 50 100 10 0 0 10"/>
+            <path d="M 70 100 h 10 v 10 H 70 V 100"/>
+        </svg>"#;
+        let (shapes, _) = get_shapes_from_svg(svg_data, &ImportOptions::default());
+        let names: Vec<&str> = shapes.iter().map(|shape| shape.get_type_name()).collect();
+        // The rounded rectangle is made of 4 arcs and 4 lines
+        assert_eq!(
+            names,
+            vec![
+                "rectangle",
+                "ellipse",
+                "line",
+                "ellipse",
+                "line",
+                "ellipse",
+                "line",
+                "ellipse",
+                "line",
+                "ellipse",
+                "ellipse",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line",
+                "line"
+            ]
+        );
+        // The outline of the rounded rectangle is closed
+        for idx in 1..9 {
+            let next_idx = if idx == 8 { 1 } else { idx + 1 };
+            let end = shapes[idx].get_pos_from_ratio(1.);
+            assert!(end.dist(&shapes[next_idx].get_pos_from_ratio(0.)) < 1e-9);
+        }
+        assert!(shapes[9].dist(&WPos::new(110., 100.)) < 1e-9);
+        assert!(shapes[10].dist(&WPos::new(150., 90.)) < 1e-9);
+        assert!(shapes[16].get_pos_from_ratio(0.).dist(&WPos::new(5., -70.)) < 1e-9);
+        assert!(shapes[16].get_pos_from_ratio(1.).dist(&WPos::new(0., 80.)) < 1e-9);
+        // The pairs following a move are lines, relative after a relative
+        // one, and the relative h and v only move along their axis
+        let ends = [
+            (18, (30., 100.), (40., 100.)),
+            (19, (40., 100.), (40., 110.)),
+            (20, (50., 100.), (60., 100.)),
+            (21, (60., 100.), (60., 110.)),
+            (22, (70., 100.), (80., 100.)),
+            (23, (80., 100.), (80., 110.)),
+            (24, (80., 110.), (70., 110.)),
+            (25, (70., 110.), (70., 100.)),
+        ];
+        for (idx, start, end) in ends {
+            let shape = &shapes[idx];
+            assert!(
+                shape
+                    .get_pos_from_ratio(0.)
+                    .dist(&WPos::new(start.0, start.1))
+                    < 1e-9
+            );
+            assert!(shape.get_pos_from_ratio(1.).dist(&WPos::new(end.0, end.1)) < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_svg_transforms() {
+        let pos = get_transform("translate(10) rotate(90 5 5)")
+            .unwrap()
+            .apply(&WPos::new(5., 0.));
+        assert!(pos.dist(&WPos::new(20., 5.)) < 1e-9);
+        let pos = get_transform("matrix(1,2,3,4,5,6) skewX(45)")
+            .unwrap()
+            .apply(&WPos::new(1., 1.));
+        assert!(pos.dist(&WPos::new(10., 14.)) < 1e-9);
+
+        // Half a millimeter per user unit
+        let svg_data = r#"<svg width="100mm" height="50mm" viewBox="0 0 200 100">
+            <g transform="translate(10 20)">
+                <g transform="scale(2)">
+                    <line x1="0" y1="0" x2="10" y2="0" transform="rotate(90)"/>
+                </g>
+            </g>
+            <defs><rect width="10" height="10"/></defs>
+            <circle cx="20" cy="20" r="10" transform="scale(2 1)"/>
+            <rect width="20" height="10" transform="rotate(30)"/>
+            <rect width="20" height="10" transform="matrix(1 0 0 -1 0 100)"/>
+            <ellipse rx="20" ry="10" transform="rotate(45)"/>
+        </svg>"#;
+        let (shapes, _) = get_shapes_from_svg(svg_data, &ImportOptions::default());
+        let names: Vec<&str> = shapes.iter().map(|shape| shape.get_type_name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "line",
+                "ellipse",
+                "line",
+                "line",
+                "line",
+                "line",
+                "rectangle",
+                "ellipse"
+            ]
+        );
+        assert!(shapes[0].get_pos_from_ratio(0.).dist(&WPos::new(5., 10.)) < 1e-9);
+        assert!(shapes[0].get_pos_from_ratio(1.).dist(&WPos::new(5., 20.)) < 1e-9);
+        assert!(shapes[1].dist(&WPos::new(30., 10.)) < 1e-9);
+        assert!(shapes[1].dist(&WPos::new(20., 15.)) < 1e-9);
+        assert!(shapes[6].dist(&WPos::new(0., 45.)) < 1e-9);
+        assert!(shapes[6].dist(&WPos::new(10., 50.)) < 1e-9);
+        // The turned ellipse
+        let (sin, cos) = (PI / 4.).sin_cos();
+        for shape in shapes[7..].iter() {
+            for i in 0..=10 {
+                let pos = shape.get_pos_from_ratio(i as f64 / 10.);
+                let x = cos * pos.wx + sin * pos.wy;
+                let y = -sin * pos.wx + cos * pos.wy;
+                assert!(((x / 10.).powi(2) + (y / 5.).powi(2) - 1.).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn test_svg_units_and_placement() {
+        // Without a view box, a user unit is a pixel
+        let (shapes, _) = get_shapes_from_svg(
+            r#"<svg><line x1="0" y1="0" x2="96" y2="0"/></svg>"#,
+            &ImportOptions::default(),
+        );
+        assert!(shapes[0].get_pos_from_ratio(1.).dist(&WPos::new(25.4, 0.)) < 1e-9);
+        // The view box is centered in a page of another ratio
+        let (shapes, _) = get_shapes_from_svg(
+            r#"<svg width="4in" height="2in" viewBox="0 0 1 1"><line x1="0" y1="0" x2="1" y2="1"/></svg>"#,
+            &ImportOptions::default(),
+        );
+        assert!(shapes[0].get_pos_from_ratio(0.).dist(&WPos::new(25.4, 0.)) < 1e-9);
+        assert!(
+            shapes[0]
+                .get_pos_from_ratio(1.)
+                .dist(&WPos::new(76.2, 50.8))
+                < 1e-9
+        );
+        // Aligned on the right, or covering the page from its top
+        let get_line_ends = |aspect_ratio: &str| {
+            let svg_data = format!(
+                r#"<svg width="4in" height="2in" viewBox="0 0 1 1" preserveAspectRatio="{}"><line x1="0" y1="0" x2="1" y2="1"/></svg>"#,
+                aspect_ratio
+            );
+            let (shapes, report) = get_shapes_from_svg(&svg_data, &ImportOptions::default());
+            (
+                shapes[0].get_pos_from_ratio(0.),
+                shapes[0].get_pos_from_ratio(1.),
+                report,
+            )
+        };
+        let (start, end, _) = get_line_ends("xMaxYMax meet");
+        assert!(start.dist(&WPos::new(50.8, 0.)) < 1e-9);
+        assert!(end.dist(&WPos::new(101.6, 50.8)) < 1e-9);
+        let (start, end, _) = get_line_ends("defer xMidYMin slice");
+        assert!(start.dist(&WPos::new(0., 0.)) < 1e-9);
+        assert!(end.dist(&WPos::new(101.6, 101.6)) < 1e-9);
+        let (start, end, _) = get_line_ends("none");
+        assert!(start.dist(&WPos::new(0., 0.)) < 1e-9);
+        assert!(end.dist(&WPos::new(101.6, 50.8)) < 1e-9);
+        // Not understood, it is centered with a warning
+        let (start, _, report) = get_line_ends("xLeftYTop");
+        assert!(start.dist(&WPos::new(25.4, 0.)) < 1e-9);
+        assert_eq!(report.warnings.len(), 1);
+
+        let svg_data = r#"<svg width="100mm" height="50mm" viewBox="0 0 100 50">
+            <line x1="10" y1="10" x2="30" y2="10"/>
+        </svg>"#;
+        let mut options = ImportOptions {
+            scale: 2.,
+            flip_y: true,
+            origin: ImportOrigin::Document,
+            working_area: WPos::new(1000., 500.),
+        };
+        let (shapes, _) = get_shapes_from_svg(svg_data, &options);
+        assert!(shapes[0].get_pos_from_ratio(0.).dist(&WPos::new(20., 80.)) < 1e-9);
+        assert!(shapes[0].get_pos_from_ratio(1.).dist(&WPos::new(60., 80.)) < 1e-9);
+        options.origin = ImportOrigin::BottomLeft;
+        let (shapes, _) = get_shapes_from_svg(svg_data, &options);
+        assert!(shapes[0].get_pos_from_ratio(0.).dist(&WPos::new(0., 500.)) < 1e-9);
+        options.origin = ImportOrigin::Center;
+        let (shapes, _) = get_shapes_from_svg(svg_data, &options);
+        assert!(
+            shapes[0]
+                .get_pos_from_ratio(0.)
+                .dist(&WPos::new(480., 250.))
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_svg_import_report() {
+        let svg_data = r#"<svg width="100mm" height="100mm" viewBox="0 0 100 100">
+            <path/>
+            <path d="M 0 0 X 10"/>
+            <path d="M 0 0 L 10 0 L 20"/>
+            <g transform="rotate(a)"><line x2="10"/></g>
+            <text>Hello</text>
+            <line x1="0" y1="50" x2="10" y2="50"/>
+            <rect x="1" <line/>
+        </svg>"#;
+        let (shapes, report) = get_shapes_from_svg(svg_data, &ImportOptions::default());
+        assert_eq!(shapes.len(), 2);
+        assert_eq!(report.nb_shapes, 2);
+        assert!(!report.is_clean());
+        let lines = |messages: &Vec<ImportMessage>| -> Vec<usize> {
+            messages.iter().map(|message| message.line).collect()
+        };
+        assert_eq!(lines(&report.errors), vec![3, 0]);
+        assert_eq!(lines(&report.warnings), vec![4]);
+        assert_eq!(lines(&report.skipped), vec![2, 5, 6]);
+        assert!(report
+            .get_summary()
+            .starts_with("2 shape(s) imported, 2 error(s)"));
+
+        let (_, report) = get_shapes_from_svg(
+            r#"<svg><line x1="0" y1="0" x2="10" y2="0"/></svg>"#,
+            &ImportOptions::default(),
+        );
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(
+            get_numbers("1,2 -3.5-4 .5.5 1e-2 10px"),
+            vec![1., 2., -3.5, -4., 0.5, 0.5, 0.01, 10.]
+        );
+    }
+}